@@ -1,23 +1,73 @@
 use crossterm::{
     cursor::{self, MoveTo},
-    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    event::{
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    },
     execute, queue,
     style::{
         Attribute, Color, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor,
     },
     terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::any::Any;
+use std::cell::Cell;
+use std::cmp::{Ordering, Reverse};
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::env;
+use std::fmt;
 use std::fs::{self, OpenOptions};
-use std::io::{self, Stdout, Write};
-use std::path::Path;
+use std::io::{self, IsTerminal, Stdout, Write};
+use std::ops::Range;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
+use std::str::FromStr;
+use std::sync::OnceLock;
 use std::thread;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+mod puzzle;
+
+use puzzle::{
+    generate_random_target_from_start, generate_target_with_presses, press_delta, press_indicator,
+    shortest_solution, solution_depth, ColorCycle, NodeColor, PressRule, INDICATOR_COUNT,
+    START_STATE,
+};
 
-const INDICATOR_COUNT: usize = 6;
-const START_STATE: [NodeColor; INDICATOR_COUNT] = [NodeColor::Off; INDICATOR_COUNT];
+const REPLAY_HEADER: &str = "# BoaAI replay v1";
+const REPLAY_STEP: Duration = Duration::from_millis(700);
+/// Random presses applied to the start state before each seeded puzzle.
+const START_SCRAMBLE_PRESSES: usize = 6;
+const EMAIL_MAX_LEN: usize = 120;
+/// Presses kept for the move-history panel; older ones scroll off.
+const RECENT_PRESSES: usize = 32;
+const HISTORY_PANEL_WIDTH: u16 = 30;
+/// Widest frame of the stacked layout.
+const FRAME_MAX_WIDTH: u16 = 124;
+/// Each side column of the wide layout, panel box included.
+const SIDE_COLUMN_WIDTH: u16 = 34;
+/// Frame width of the wide layout: the stacked frame with a side column on
+/// either side. Narrower terminals keep the stacked layout.
+const WIDE_FRAME_WIDTH: u16 = FRAME_MAX_WIDTH + 2 * SIDE_COLUMN_WIDTH;
+const SOLVED_HOLD: Duration = Duration::from_secs(1);
+const SOLVE_FLASH: Duration = Duration::from_millis(200);
+const SCORE_BASE: u32 = 1000;
+const SESSION_WARNING: Duration = Duration::from_secs(30);
+const SESSION_SUBMIT_GRACE: Duration = Duration::from_secs(30);
+/// Quiet time after the last resize event before the screen is redrawn, so a
+/// drag-resize flood produces one redraw instead of dozens.
+const RESIZE_SETTLE: Duration = Duration::from_millis(100);
+/// Longest wait for input when no timer or animation is due sooner.
+const IDLE_POLL: Duration = Duration::from_secs(5);
+/// How long the target row cycles when the puzzle view first appears, and
+/// how often it changes meanwhile.
+const TARGET_PREVIEW: Duration = Duration::from_millis(900);
+const TARGET_PREVIEW_FRAME: Duration = Duration::from_millis(75);
+/// How long "Session timed out" stays up before an idle session exits.
+const IDLE_NOTICE: Duration = Duration::from_secs(2);
 
 const SPLASH_LOGO: &str = r#"
                                             ..=%@@@@@@@@@@*-..
@@ -51,56 +101,518 @@ const SPLASH_LOGO: &str = r#"
                                         ..:+%@@@@@@@@@@@@#=:..     .:--..
 "#;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-enum NodeColor {
-    Off,
-    Green,
-    Blue,
-    Red,
-    Purple,
-    White,
-}
-
 impl NodeColor {
-    fn next(self) -> Self {
-        match self {
-            Self::Off => Self::Green,
-            Self::Green => Self::Blue,
-            Self::Blue => Self::Red,
-            Self::Red => Self::Purple,
-            Self::Purple => Self::White,
-            Self::White => Self::Off,
+    fn term_color(self, palette: Palette) -> Color {
+        match palette {
+            Palette::Default => match self {
+                Self::Off => Color::DarkGrey,
+                Self::Green => Color::Green,
+                Self::Blue => Color::Blue,
+                Self::Red => Color::Red,
+                Self::Purple => Color::Magenta,
+                Self::White => Color::White,
+            },
+            // Blue/orange/yellow hues that stay apart for red-green color
+            // blindness; glyphs carry the difference where hue cannot.
+            Palette::ColorBlind => match self {
+                Self::Off => Color::DarkGrey,
+                Self::Green => Color::Rgb {
+                    r: 0,
+                    g: 114,
+                    b: 178,
+                },
+                Self::Blue => Color::Rgb {
+                    r: 86,
+                    g: 180,
+                    b: 233,
+                },
+                Self::Red => Color::Rgb {
+                    r: 230,
+                    g: 159,
+                    b: 0,
+                },
+                Self::Purple => Color::Rgb {
+                    r: 240,
+                    g: 228,
+                    b: 66,
+                },
+                Self::White => Color::White,
+            },
         }
     }
 
-    fn as_str(self) -> &'static str {
+    /// Shape shown next to the color in the color-blind palette.
+    fn glyph(self) -> char {
         match self {
-            Self::Off => "OFF",
-            Self::Green => "GREEN",
-            Self::Blue => "BLUE",
-            Self::Red => "RED",
-            Self::Purple => "PURPLE",
-            Self::White => "WHITE",
+            Self::Off => '·',
+            Self::Green => '▲',
+            Self::Blue => '●',
+            Self::Red => '■',
+            Self::Purple => '◆',
+            Self::White => '○',
+        }
+    }
+
+    /// Name as written in state rows, with the glyph in the color-blind palette.
+    fn state_token(self, palette: Palette) -> String {
+        match palette {
+            Palette::Default => self.as_str().to_string(),
+            Palette::ColorBlind => format!("{}{}", self.as_str(), self.glyph()),
+        }
+    }
+}
+
+/// Indicator color scheme, chosen by `BOAAI_PALETTE=default|cb` or the `C` key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Palette {
+    Default,
+    ColorBlind,
+}
+
+/// Interface colors shared by every view. Only the accent is configurable,
+/// through `BOAAI_ACCENT`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Theme {
+    /// Warnings, status lines and other highlights.
+    accent: Color,
+    /// Fill behind the selected button.
+    selection: Color,
+    /// Frames of boxes, unselected buttons and secondary text.
+    border: Color,
+    /// Headings, body text and the selected button's frame.
+    text: Color,
+    /// Solved frame and flash, matched indicators, scores and other good news.
+    success: Color,
+    /// Revealed paths and the frame around a press that would finish.
+    warning: Color,
+    /// Screen and box fill, and the selected button's label.
+    background: Color,
+}
+
+const DEFAULT_ACCENT: Color = Color::Rgb {
+    r: 255,
+    g: 90,
+    b: 0,
+};
+
+impl Theme {
+    fn new(accent: Color) -> Self {
+        Self {
+            accent,
+            selection: Color::Grey,
+            border: Color::DarkGrey,
+            text: Color::White,
+            success: Color::Green,
+            warning: Color::Yellow,
+            background: Color::Black,
+        }
+    }
+}
+
+/// An `r,g,b` triple of 0-255 components, as in `BOAAI_ACCENT="255,90,0"`.
+fn parse_accent(value: &str) -> Option<Color> {
+    let parts: Vec<u8> = value
+        .split(',')
+        .map(|part| part.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    match parts[..] {
+        [r, g, b] => Some(Color::Rgb { r, g, b }),
+        _ => None,
+    }
+}
+
+impl FromStr for NodeColor {
+    type Err = ConfigError;
+
+    /// Accepts a color name (case-insensitive) or its 0-5 cycle index.
+    fn from_str(token: &str) -> Result<Self, Self::Err> {
+        let token = token.trim();
+        if let Ok(number) = token.parse::<usize>() {
+            return Self::ALL
+                .get(number)
+                .copied()
+                .ok_or_else(|| ConfigError::InvalidColor(format!("color index {number}")));
         }
+
+        Self::ALL
+            .into_iter()
+            .find(|color| color.as_str().eq_ignore_ascii_case(token))
+            .ok_or_else(|| ConfigError::InvalidColor(format!("'{token}'")))
     }
+}
+
+/// A configuration problem found while parsing env vars, replay files,
+/// scripts or preferences. `main` collects these and reports them together.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum ConfigError {
+    InvalidColor(String),
+    LengthMismatch {
+        expected: usize,
+        found: usize,
+    },
+    BadIndex(String),
+    DuplicateIndex(usize),
+    MalformedEntry {
+        expected: &'static str,
+        found: String,
+    },
+    MissingOff,
+    TooFewColors,
+    ColorOutsideCycle(NodeColor),
+    UnreachableTarget {
+        initial: [NodeColor; INDICATOR_COUNT],
+        target: [NodeColor; INDICATOR_COUNT],
+    },
+    /// A target equal to the pinned start, which would open already solved.
+    TargetIsStart([NodeColor; INDICATOR_COUNT]),
+    UnknownKey(String),
+    InvalidValue {
+        value: String,
+        expected: &'static str,
+    },
+    Unreadable {
+        path: String,
+        reason: String,
+    },
+    AtLine {
+        line: usize,
+        error: Box<ConfigError>,
+    },
+    BadShareCode(String),
+    ShareCodeChecksum(String),
+}
 
-    fn term_color(self) -> Color {
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::Off => Color::DarkGrey,
-            Self::Green => Color::Green,
-            Self::Blue => Color::Blue,
-            Self::Red => Color::Red,
-            Self::Purple => Color::Magenta,
-            Self::White => Color::White,
+            Self::InvalidColor(token) => write!(f, "unknown color {token} (use a name or 0-5)"),
+            Self::LengthMismatch { expected, found } => {
+                write!(f, "expected {expected} colors, got {found}")
+            }
+            Self::BadIndex(index) => {
+                write!(f, "indicator '{index}' is not in 1-{INDICATOR_COUNT}")
+            }
+            Self::DuplicateIndex(index) => write!(f, "indicator {index} is set more than once"),
+            Self::MalformedEntry { expected, found } => {
+                write!(f, "expected {expected}, got '{found}'")
+            }
+            Self::MissingOff => write!(f, "the color set must include OFF"),
+            Self::TooFewColors => write!(f, "the color set needs at least one color besides OFF"),
+            Self::ColorOutsideCycle(color) => {
+                write!(f, "{} is not in the active color set", color.as_str())
+            }
+            Self::UnreachableTarget { initial, target } => write!(
+                f,
+                "target {} is unreachable from start {}",
+                render_state_spec(*target),
+                render_state_spec(*initial)
+            ),
+            Self::TargetIsStart(target) => write!(
+                f,
+                "target {} equals the pinned start, so the puzzle would open solved",
+                render_state_spec(*target)
+            ),
+            Self::UnknownKey(key) => write!(f, "unknown key '{key}'"),
+            Self::InvalidValue { value, expected } => {
+                write!(f, "'{value}' is not {expected}")
+            }
+            Self::Unreadable { path, reason } => write!(f, "{path}: {reason}"),
+            Self::AtLine { line, error } => write!(f, "line {line}: {error}"),
+            Self::BadShareCode(code) => write!(
+                f,
+                "'{code}' is not a share code ({SHARE_CODE_LEN} characters, 0-9 and A-Z)"
+            ),
+            Self::ShareCodeChecksum(code) => {
+                write!(f, "share code '{code}' fails its check digit")
+            }
         }
     }
 }
 
-#[derive(Clone, Copy)]
+impl std::error::Error for ConfigError {}
+
+impl ColorCycle {
+    /// Parses a list such as `OFF,GREEN,BLUE`; order and duplicates are ignored.
+    fn parse(spec: &str) -> Result<Self, ConfigError> {
+        let colors = spec
+            .split(',')
+            .filter(|token| !token.trim().is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<NodeColor>, _>>()?;
+        let cycle = Self::from_colors(colors);
+
+        if !cycle.contains(NodeColor::Off) {
+            return Err(ConfigError::MissingOff);
+        }
+        if cycle.len() < 2 {
+            return Err(ConfigError::TooFewColors);
+        }
+        Ok(cycle)
+    }
+
+    fn check_state(self, state: [NodeColor; INDICATOR_COUNT]) -> Result<(), ConfigError> {
+        match state.iter().find(|color| !self.contains(**color)) {
+            Some(color) => Err(ConfigError::ColorOutsideCycle(*color)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum AppPhase {
+    Menu,
     Puzzle,
+    Solved,
     Email,
     Submitted,
+    Victory,
+    Drills,
+    GameOver,
+    Leaderboard,
+    Stats,
+    /// `BOAAI_MOVE_CAP` ran out before the solve; presses wait for a retry.
+    OutOfMoves,
+}
+
+/// One run on the local leaderboard, ranked by its composite score.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct LeaderboardEntry {
+    email: String,
+    solve_seconds: u64,
+    moves_taken: usize,
+    score: u32,
+}
+
+/// An endless survival run: each cleared puzzle is scrambled deeper, and
+/// running out a puzzle's time budget ends the run.
+struct SurvivalRun {
+    depth: usize,
+    depth_step: usize,
+    budget: Duration,
+    cleared: usize,
+    started_at: Instant,
+    puzzle_started: Instant,
+    total: Option<Duration>,
+    record_path: PathBuf,
+    record_status: String,
+}
+
+/// A practice puzzle that leans on one press rule. The target is built by
+/// applying `presses` to the start state, so it is always reachable. The
+/// lessons describe `PressRule::Classic`, so drills always play by it.
+struct Drill {
+    name: &'static str,
+    presses: &'static [usize],
+    lesson: &'static str,
+}
+
+const DRILLS: &[Drill] = &[
+    Drill {
+        name: "Pressed button",
+        presses: &[0],
+        lesson: "The pressed indicator itself moves two colors forward.",
+    },
+    Drill {
+        name: "Neighbors",
+        presses: &[0, 1],
+        lesson: "Immediate neighbors move one color forward, so adjacent presses stack.",
+    },
+    Drill {
+        name: "Two apart",
+        presses: &[0, 2],
+        lesson: "Indicators two away step one color backward, undoing a neighbor's nudge.",
+    },
+    Drill {
+        name: "Opposite",
+        presses: &[0, 3],
+        lesson: "The opposite indicator jumps three colors forward.",
+    },
+];
+
+/// What the last stage of a campaign leads to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CampaignFinale {
+    Email,
+    Victory,
+    Loop,
+}
+
+/// How far the starting board is scrambled from the target, picked in the
+/// difficulty menu or with `--difficulty`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    const ALL: [Self; 3] = [Self::Easy, Self::Medium, Self::Hard];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Easy => "easy",
+            Self::Medium => "medium",
+            Self::Hard => "hard",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|difficulty| value.trim().eq_ignore_ascii_case(difficulty.name()))
+    }
+
+    /// Random presses applied to the target to produce the starting board.
+    fn presses(self) -> usize {
+        match self {
+            Self::Easy => 3,
+            Self::Medium => 7,
+            Self::Hard => 12,
+        }
+    }
+}
+
+/// How the six indicators are arranged, chosen by `BOAAI_LAYOUT=row|ring`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum IndicatorLayout {
+    Row,
+    Ring,
+}
+
+/// Where each indicator button sits on screen, shared by drawing and mouse
+/// hit-testing. `below` is the first free line under the buttons.
+struct IndicatorGrid {
+    cells: [(u16, u16); INDICATOR_COUNT],
+    width: u16,
+    below: u16,
+    /// The ring actually fit; `false` when it fell back to the row.
+    ring: bool,
+}
+
+impl IndicatorGrid {
+    /// Line pieces joining ring neighbors, as `(x, y, text)`: a dash across
+    /// the top and bottom gaps and a slash at each corner where a side
+    /// indicator meets the top or bottom pair. Empty for the row.
+    fn connectors(&self) -> Vec<(u16, u16, &'static str)> {
+        let &[top_left, top_right, _, bottom_right, bottom_left, _] = self.cells.as_slice() else {
+            return Vec::new();
+        };
+        if !self.ring {
+            return Vec::new();
+        }
+        let right = top_right.0 + self.width;
+        vec![
+            (top_left.0 + self.width, top_left.1 + 1, "──"),
+            (right, top_right.1 + 2, "╲"),
+            (right, bottom_right.1, "╱"),
+            (bottom_left.0 + self.width, bottom_left.1 + 1, "──"),
+            (bottom_left.0 - 1, bottom_left.1, "╲"),
+            (top_left.0 - 1, top_left.1 + 2, "╱"),
+        ]
+    }
+
+    fn hit(&self, column: u16, row: u16) -> Option<usize> {
+        button_hit(&self.cells, self.width, column, row)
+    }
+}
+
+/// Index of the three-line-tall button in `cells` covering the given cell.
+fn button_hit(cells: &[(u16, u16)], width: u16, column: u16, row: u16) -> Option<usize> {
+    cells
+        .iter()
+        .position(|&(x, y)| (x..x + width).contains(&column) && (y..y + 3).contains(&row))
+}
+
+/// Hint, Reset and Rules.
+const ACTION_COUNT: usize = 3;
+const ACTION_WIDTH: u16 = 18;
+const ACTION_GAP: u16 = 2;
+
+/// Top-left corners of the Hint, Reset and Rules buttons on line `y`.
+fn action_cells(x: u16, width: u16, y: u16) -> [(u16, u16); ACTION_COUNT] {
+    let span = ACTION_WIDTH * ACTION_COUNT as u16 + ACTION_GAP * (ACTION_COUNT as u16 - 1);
+    let start_x = x + width.saturating_sub(span) / 2;
+    [0, 1, 2].map(|index| (start_x + index * (ACTION_WIDTH + ACTION_GAP), y))
+}
+
+/// Email view controls: the input field and the Confirm/Solve Again buttons.
+struct EmailLayout {
+    field: (u16, u16),
+    field_width: u16,
+    buttons: [(u16, u16); 2],
+    button_width: u16,
+}
+
+fn email_layout(x: u16, body_y: u16, width: u16) -> EmailLayout {
+    let field_y = body_y + 6;
+    let field_width = width.saturating_sub(8).max(20);
+    let button_y = field_y + 5;
+    let button_width = 24;
+    let button_gap = 4;
+    let button_start_x = x + width.saturating_sub(button_width * 2 + button_gap) / 2;
+    EmailLayout {
+        field: (x + (width.saturating_sub(field_width)) / 2, field_y),
+        field_width,
+        buttons: [0, 1].map(|index| {
+            (
+                button_start_x + index * (button_width + button_gap),
+                button_y,
+            )
+        }),
+        button_width,
+    }
+}
+
+/// What a left click landed on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ClickTarget {
+    Indicator(usize),
+    Action(usize),
+    EmailInput,
+    EmailButton(usize),
+}
+
+/// The one-shot target row animation: it waits for the puzzle view, runs
+/// once, and stays done for the rest of the process.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TargetPreview {
+    Waiting,
+    Running(Instant),
+    Done,
+}
+
+/// Outer frame and body box placement for a terminal of the given size.
+struct FrameLayout {
+    x: u16,
+    width: u16,
+    body_y: u16,
+    body_height: u16,
+}
+
+/// Horizontal split of the frame body. Views draw in `x..x + width`; on a
+/// `WIDE_FRAME_WIDTH` frame that is the middle column and the puzzle's
+/// history and rules panels move to `sides`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct ViewColumns {
+    x: u16,
+    width: u16,
+    sides: Option<SideColumns>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct SideColumns {
+    left_x: u16,
+    right_x: u16,
+    /// Width of each side panel box.
+    width: u16,
+}
+
+/// What to do when the session cap hits a player who solved but has not submitted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum UnsubmittedPolicy {
+    Prompt,
+    Discard,
 }
 
 #[derive(Clone, Copy)]
@@ -116,1230 +628,10139 @@ enum EmailFocus {
 }
 
 struct PuzzleState {
+    cycle: ColorCycle,
+    rule: PressRule,
     initial: [NodeColor; INDICATOR_COUNT],
     target: [NodeColor; INDICATOR_COUNT],
     current: [NodeColor; INDICATOR_COUNT],
     optimal_moves: usize,
     moves_taken: usize,
+    moves: Vec<usize>,
+    /// Board before each entry of `moves`, so presses can be undone.
+    history: Vec<[NodeColor; INDICATOR_COUNT]>,
+    /// Undone presses, most recent last; any fresh press clears it.
+    redo_stack: Vec<usize>,
+    /// The last `RECENT_PRESSES` presses, newest last, for the history panel.
+    recent_presses: VecDeque<usize>,
+    bookmark: Option<[NodeColor; INDICATOR_COUNT]>,
+    distance_to_target: usize,
+    worsening_streak: usize,
     focus: PuzzleFocus,
+    /// Indicators the player has marked as settled. A personal annotation
+    /// only: presses, the solve check and par ignore it.
+    locked: [bool; INDICATOR_COUNT],
+    /// Set by the debug `F12` solve, whose submission records no moves.
+    debug_solved: bool,
+    /// Set by `Z`. Timing from a chosen position is practice: the solve
+    /// earns no score, records no moves and stays off the leaderboard.
+    scoring_restarted: bool,
     show_rules: bool,
+    /// First rule line shown when the rules do not fit the body.
+    rules_scroll: usize,
     status: String,
+    started_at: Instant,
+    /// Time from `started_at` to the solve; freezes the header timer.
+    solved_in: Option<Duration>,
+    /// Reproduces this layout via `BOAAI_SEED` or `--seed`; `None` for
+    /// puzzles that are not seeded (drills, survival, replays).
+    seed: Option<u64>,
+    /// Full solution revealed from the board it was computed for; only drawn
+    /// while the board is unchanged.
+    revealed_path: Option<([NodeColor; INDICATOR_COUNT], Vec<usize>)>,
+    /// How many times the full path was revealed for this puzzle.
+    path_reveals: usize,
 }
 
-struct EmailState {
-    email: String,
-    focus: EmailFocus,
-    selected_button: usize,
-    status: String,
+/// A phase state with a user-facing status line.
+trait StatusLine {
+    const PHASE: &'static str;
+    fn status_mut(&mut self) -> &mut String;
+
+    /// The one place status lines change, so each message can also be
+    /// appended to the `BOAAI_TRANSCRIPT` file.
+    fn set_status(&mut self, message: impl Into<String>) {
+        let message = message.into();
+        append_transcript(Self::PHASE, &message);
+        *self.status_mut() = message;
+    }
 }
 
-struct App {
-    phase: AppPhase,
-    puzzle: PuzzleState,
-    email: EmailState,
-    submitted_email: Option<String>,
-    debug: bool,
-    should_quit: bool,
+impl StatusLine for PuzzleState {
+    const PHASE: &'static str = "puzzle";
+
+    fn status_mut(&mut self) -> &mut String {
+        &mut self.status
+    }
 }
 
-struct TerminalSession;
+impl StatusLine for EmailState {
+    const PHASE: &'static str = "email";
 
-impl TerminalSession {
-    fn enter(stdout: &mut Stdout) -> io::Result<Self> {
-        terminal::enable_raw_mode()?;
-        execute!(stdout, EnterAlternateScreen, cursor::Hide)?;
-        Ok(Self)
+    fn status_mut(&mut self) -> &mut String {
+        &mut self.status
     }
 }
 
-impl Drop for TerminalSession {
-    fn drop(&mut self) {
-        let mut stdout = io::stdout();
-        let _ = execute!(stdout, cursor::Show, LeaveAlternateScreen, ResetColor);
-        let _ = terminal::disable_raw_mode();
+/// Appends `unix_secs.millis<TAB>phase<TAB>message` when `BOAAI_TRANSCRIPT`
+/// is set. Best effort: a transcript failure never interrupts the session.
+fn append_transcript(phase: &str, message: &str) {
+    let Ok(path) = env::var("BOAAI_TRANSCRIPT") else {
+        return;
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+        let _ = writeln!(
+            file,
+            "{}.{:03}\t{phase}\t{message}",
+            now.as_secs(),
+            now.subsec_millis()
+        );
     }
 }
 
-impl App {
-    fn new(debug: bool) -> Self {
-        Self {
-            phase: AppPhase::Puzzle,
-            puzzle: new_puzzle_state(),
-            email: EmailState {
-                email: String::new(),
-                focus: EmailFocus::Input,
-                selected_button: 0,
-                status: "Solve the puzzle to unlock event invite submission.".to_string(),
-            },
-            submitted_email: None,
-            debug,
-            should_quit: false,
-        }
+/// Appends `unix_secs.millis<TAB>event` to `BOAAI_LOG_FILE`, an audit trail
+/// for debugging deployments. The variable is read once; when it is unset
+/// every call returns straight away.
+fn log_event(event: &str) {
+    static LOG_FILE: OnceLock<Option<PathBuf>> = OnceLock::new();
+    if let Some(path) = LOG_FILE.get_or_init(|| env::var_os("BOAAI_LOG_FILE").map(PathBuf::from)) {
+        append_log_line(path, event);
     }
 }
 
-fn main() -> io::Result<()> {
-    let mut stdout = io::stdout();
-    show_splash_screen(&mut stdout)?;
+/// `NO_COLOR` (any non-empty value) or `BOAAI_PLAIN=1`: draw without colors
+/// for logging terminals. Read once, like the log file.
+fn plain_mode() -> bool {
+    static PLAIN: OnceLock<bool> = OnceLock::new();
+    *PLAIN.get_or_init(|| {
+        env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+            || env_bool("BOAAI_PLAIN") == Some(true)
+    })
+}
 
-    let _terminal = TerminalSession::enter(&mut stdout)?;
-    let mut app = App::new(debug_enabled());
-    let mut needs_redraw = true;
+thread_local! {
+    /// Set while `draw_frame` paints the view behind the help overlay, so
+    /// `Fg` and `Bg` dim it without each draw function knowing.
+    static DIMMED: Cell<bool> = const { Cell::new(false) };
+}
 
-    loop {
-        if needs_redraw {
-            draw_app(&mut stdout, &app)?;
-            needs_redraw = false;
-        }
+/// `SetForegroundColor` that writes nothing in plain mode. All drawing goes
+/// through this and `Bg`, so call sites never check the mode themselves.
+struct Fg(Color);
 
-        if app.should_quit {
-            break;
+impl Fg {
+    fn color(&self) -> Color {
+        if DIMMED.with(Cell::get) {
+            Color::DarkGrey
+        } else {
+            self.0
         }
+    }
+}
 
-        if event::poll(Duration::from_millis(200))? {
-            match event::read()? {
-                Event::Key(key) => {
-                    needs_redraw = handle_key(&mut app, key)?;
-                }
-                Event::Resize(_, _) => {
-                    needs_redraw = true;
-                }
-                _ => {}
-            }
+impl crossterm::Command for Fg {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        if plain_mode() {
+            return Ok(());
         }
+        SetForegroundColor(self.color()).write_ansi(f)
     }
 
-    Ok(())
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> io::Result<()> {
+        if plain_mode() {
+            return Ok(());
+        }
+        SetForegroundColor(self.color()).execute_winapi()
+    }
 }
 
-fn show_splash_screen(stdout: &mut Stdout) -> io::Result<()> {
-    let (cols, rows) = terminal::size().unwrap_or((120, 40));
-    let raw_logo_lines: Vec<String> = SPLASH_LOGO
-        .lines()
-        .filter(|line| !line.trim().is_empty())
-        .map(|line| line.trim_end().to_string())
-        .collect();
+/// Background counterpart of `Fg`.
+struct Bg(Color);
 
-    let common_indent = raw_logo_lines
-        .iter()
-        .map(|line| line.chars().take_while(|c| *c == ' ').count())
-        .min()
-        .unwrap_or(0);
+impl Bg {
+    fn color(&self) -> Color {
+        if DIMMED.with(Cell::get) {
+            Color::Black
+        } else {
+            self.0
+        }
+    }
+}
 
-    let logo_lines: Vec<String> = raw_logo_lines
-        .iter()
-        .map(|line| line.chars().skip(common_indent).collect::<String>())
-        .collect();
+impl crossterm::Command for Bg {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        if plain_mode() {
+            return Ok(());
+        }
+        SetBackgroundColor(self.color()).write_ansi(f)
+    }
 
-    let block_width = logo_lines
-        .iter()
-        .map(|line| line.chars().count())
-        .max()
-        .unwrap_or(0) as u16;
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> io::Result<()> {
+        if plain_mode() {
+            return Ok(());
+        }
+        SetBackgroundColor(self.color()).execute_winapi()
+    }
+}
 
-    execute!(
-        stdout,
-        Clear(ClearType::All),
-        MoveTo(0, 0),
-        SetBackgroundColor(Color::Black),
-        cursor::Hide
-    )?;
+/// Best effort, like the transcript: logging never interrupts the session.
+fn append_log_line(path: &Path, event: &str) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+        let _ = writeln!(
+            file,
+            "{}.{:03}\t{event}",
+            now.as_secs(),
+            now.subsec_millis()
+        );
+    }
+}
 
-    let block_height = logo_lines.len() as u16 + 2;
-    let start_x = cols.saturating_sub(block_width) / 2;
-    let start_y = rows.saturating_sub(block_height) / 2;
+struct EmailState {
+    email: String,
+    focus: EmailFocus,
+    selected_button: usize,
+    status: String,
+    suggestion: Option<String>,
+    /// Confirm Invite was pressed once; the next Enter writes the submission
+    /// and Esc goes back to editing.
+    awaiting_confirmation: bool,
+}
 
-    for (offset, line) in logo_lines.iter().enumerate() {
-        let color = if line.contains("HACK THE WORLD") {
-            Color::White
-        } else {
-            Color::DarkGrey
-        };
+/// Campaign settings: `BOAAI_CAMPAIGN_STAGES` (two or more stages) and
+/// `BOAAI_CAMPAIGN_FINALE`, `email` (default), `victory` or `loop`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct CampaignPlan {
+    stages: usize,
+    finale: CampaignFinale,
+}
 
-        queue!(
-            stdout,
-            MoveTo(start_x, start_y + offset as u16),
-            SetForegroundColor(color),
-            Print(line)
-        )?;
+impl CampaignPlan {
+    fn start(self) -> Campaign {
+        Campaign {
+            stages: self.stages,
+            stage: 0,
+            finale: self.finale,
+            total_moves: 0,
+            started_at: Instant::now(),
+            laps: 0,
+        }
     }
-
-    let subheading = "ACCESS CHALLENGE INITIALIZING";
-    let subheading_x = start_x + block_width.saturating_sub(subheading.len() as u16) / 2;
-    queue!(
-        stdout,
-        MoveTo(subheading_x, start_y + logo_lines.len() as u16 + 1),
-        SetForegroundColor(Color::Rgb {
-            r: 255,
-            g: 90,
-            b: 0
-        }),
-        SetAttribute(Attribute::Bold),
-        Print(subheading),
-        SetAttribute(Attribute::Reset),
-        ResetColor
-    )?;
-
-    stdout.flush()?;
-    thread::sleep(Duration::from_secs(4));
-    execute!(
-        stdout,
-        Clear(ClearType::All),
-        MoveTo(0, 0),
-        ResetColor,
-        cursor::Show
-    )?;
-    Ok(())
 }
 
-fn draw_app(stdout: &mut Stdout, app: &App) -> io::Result<()> {
-    let (cols, rows) = terminal::size()?;
-    queue!(
-        stdout,
-        MoveTo(0, 0),
-        Clear(ClearType::All),
-        SetBackgroundColor(Color::Black)
-    )?;
+/// Multi-stage play: several generated puzzles solved back to back.
+struct Campaign {
+    stages: usize,
+    stage: usize,
+    finale: CampaignFinale,
+    total_moves: usize,
+    started_at: Instant,
+    laps: usize,
+}
 
-    if cols < 78 || rows < 24 {
-        draw_resize_message(stdout, cols, rows)?;
-        stdout.flush()?;
-        return Ok(());
+impl Campaign {
+    fn summary(&self) -> String {
+        format!(
+            "Campaign: {} stage(s), {} total move(s), {}",
+            self.stages,
+            self.total_moves,
+            format_duration(self.started_at.elapsed())
+        )
     }
+}
 
-    let frame_width = cols.saturating_sub(4).min(124);
-    let frame_x = cols.saturating_sub(frame_width) / 2;
-    let header_y = 1;
-    let body_y = header_y + 4;
-    let body_height = rows.saturating_sub(body_y + 3);
+/// Step-by-step playback of the player's own recorded presses.
+struct SolutionReplay {
+    state: [NodeColor; INDICATOR_COUNT],
+    step: usize,
+    last_step: Instant,
+}
 
-    draw_header_bar(stdout, frame_x, header_y, frame_width, app)?;
-    draw_box(
-        stdout,
-        frame_x,
-        body_y,
-        frame_width,
-        body_height,
-        Color::DarkGrey,
-    )?;
+/// Points lost per press over optimal and per second of solve time, set by
+/// `BOAAI_SCORE_MOVE_WEIGHT` and `BOAAI_SCORE_TIME_WEIGHT`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct ScoreWeights {
+    per_extra_move: u32,
+    per_second: u32,
+}
 
-    match app.phase {
-        AppPhase::Puzzle => {
-            draw_puzzle_view(stdout, frame_x, body_y, frame_width, body_height, app)?
-        }
-        AppPhase::Email => draw_email_view(stdout, frame_x, body_y, frame_width, body_height, app)?,
-        AppPhase::Submitted => {
-            draw_submitted_view(stdout, frame_x, body_y, frame_width, body_height, app)?
+impl Default for ScoreWeights {
+    fn default() -> Self {
+        Self {
+            per_extra_move: 50,
+            per_second: 5,
         }
     }
-
-    draw_footer(stdout, frame_x, frame_width, rows, app)?;
-    queue!(stdout, ResetColor, SetAttribute(Attribute::Reset))?;
-    stdout.flush()?;
-    Ok(())
 }
 
-fn draw_resize_message(stdout: &mut Stdout, cols: u16, rows: u16) -> io::Result<()> {
-    let line_1 = "Terminal size too small for puzzle UI.";
-    let line_2 = "Resize to at least 78x24.";
-    let x_1 = cols.saturating_sub(line_1.len() as u16) / 2;
-    let x_2 = cols.saturating_sub(line_2.len() as u16) / 2;
-    let y = rows / 2;
-
-    queue!(
-        stdout,
-        MoveTo(x_1, y.saturating_sub(1)),
-        SetForegroundColor(Color::DarkGrey),
-        Print(line_1),
-        MoveTo(x_2, y + 1),
-        SetForegroundColor(Color::Rgb {
-            r: 255,
-            g: 90,
-            b: 0
-        }),
-        SetAttribute(Attribute::Bold),
-        Print(line_2),
-        SetAttribute(Attribute::Reset),
-        ResetColor
-    )?;
-    Ok(())
+/// Wall-clock cap on the whole session, set by `BOAAI_SESSION_SECS`.
+struct SessionLimit {
+    deadline: Instant,
+    on_unsubmitted: UnsubmittedPolicy,
+    grace_until: Option<Instant>,
 }
 
-fn draw_header_bar(stdout: &mut Stdout, x: u16, y: u16, width: u16, app: &App) -> io::Result<()> {
-    let tab_label = match app.phase {
-        AppPhase::Puzzle => "puzzle node",
-        AppPhase::Email => "invite form",
-        AppPhase::Submitted => "request sent",
-    };
-
-    let segments = vec![
-        center_text("Boa AI", 12),
-        center_text(tab_label, 16),
-        center_text(
-            &format!(
-                "moves {}/{}",
-                app.puzzle.moves_taken, app.puzzle.optimal_moves
-            ),
-            14,
-        ),
-        center_text("event access", 20),
-    ];
+/// Cutoff for abandoned sessions, set by `BOAAI_IDLE_SECS`. Any key, mouse
+/// or paste event restarts the window.
+struct IdleTimeout {
+    window: Duration,
+    last_input: Instant,
+    /// Set once the window has passed in a phase that shows the notice.
+    quit_at: Option<Instant>,
+}
 
-    let content_width = segments.iter().map(String::len).sum::<usize>() + segments.len() - 1;
-    if content_width as u16 + 2 > width {
-        return draw_box(stdout, x, y, width, 3, Color::DarkGrey);
-    }
+/// In-app toggles remembered between runs, stored as `key=value` lines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Preferences {
+    finishing_hint: bool,
+    show_rules: bool,
+    skip_matched: bool,
+    reduced_motion: bool,
+    color_blind: bool,
+    /// Last choice in the difficulty menu, selected first next time.
+    difficulty: Difficulty,
+}
 
-    let mut top_border = String::from("┌");
-    let mut bottom_border = String::from("└");
-    for (index, segment) in segments.iter().enumerate() {
-        top_border.push_str(&"─".repeat(segment.len()));
-        bottom_border.push_str(&"─".repeat(segment.len()));
-        if index < segments.len() - 1 {
-            top_border.push('┬');
-            bottom_border.push('┴');
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            finishing_hint: true,
+            show_rules: false,
+            skip_matched: false,
+            reduced_motion: false,
+            color_blind: false,
+            difficulty: Difficulty::Medium,
         }
     }
-    top_border.push('┐');
-    bottom_border.push('┘');
-
-    queue!(
-        stdout,
-        MoveTo(x, y),
-        SetForegroundColor(Color::DarkGrey),
-        Print(top_border),
-        MoveTo(x, y + 2),
-        Print(bottom_border),
-        MoveTo(x, y + 1),
-        Print("│")
-    )?;
+}
 
-    let mut cursor_x = x + 1;
-    for (index, segment) in segments.iter().enumerate() {
-        queue!(stdout, MoveTo(cursor_x, y + 1))?;
-        match index {
-            0 => {
-                queue!(
-                    stdout,
-                    SetForegroundColor(Color::White),
-                    SetAttribute(Attribute::Bold),
-                    Print(segment),
-                    SetAttribute(Attribute::Reset),
-                    SetForegroundColor(Color::DarkGrey)
-                )?;
+impl Preferences {
+    fn parse(text: &str) -> Result<Self, ConfigError> {
+        let mut prefs = Self::default();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
             }
-            1 => {
-                queue!(
-                    stdout,
-                    SetForegroundColor(Color::Rgb {
-                        r: 255,
-                        g: 90,
-                        b: 0
-                    }),
-                    SetAttribute(Attribute::Bold),
-                    Print(segment),
-                    SetAttribute(Attribute::Reset),
-                    SetForegroundColor(Color::DarkGrey)
-                )?;
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| ConfigError::MalformedEntry {
+                    expected: "key=value",
+                    found: line.to_string(),
+                })?;
+            let value = value.trim();
+            if key.trim() == "difficulty" {
+                prefs.difficulty =
+                    Difficulty::parse(value).ok_or_else(|| ConfigError::InvalidValue {
+                        value: value.to_string(),
+                        expected: "easy, medium or hard",
+                    })?;
+                continue;
             }
-            _ => {
-                queue!(stdout, SetForegroundColor(Color::DarkGrey), Print(segment))?;
+            let value = match value {
+                "true" => true,
+                "false" => false,
+                other => {
+                    return Err(ConfigError::InvalidValue {
+                        value: other.to_string(),
+                        expected: "true or false",
+                    })
+                }
+            };
+            match key.trim() {
+                "finishing_hint" => prefs.finishing_hint = value,
+                "show_rules" => prefs.show_rules = value,
+                "skip_matched" => prefs.skip_matched = value,
+                "reduced_motion" => prefs.reduced_motion = value,
+                "color_blind" => prefs.color_blind = value,
+                other => return Err(ConfigError::UnknownKey(other.to_string())),
             }
         }
+        Ok(prefs)
+    }
 
-        cursor_x += segment.len() as u16;
-        if index < segments.len() - 1 {
-            queue!(stdout, MoveTo(cursor_x, y + 1), Print("│"))?;
-            cursor_x += 1;
+    fn render(&self) -> String {
+        format!(
+            "finishing_hint={}\nshow_rules={}\nskip_matched={}\nreduced_motion={}\ncolor_blind={}\ndifficulty={}\n",
+            self.finishing_hint,
+            self.show_rules,
+            self.skip_matched,
+            self.reduced_motion,
+            self.color_blind,
+            self.difficulty.name()
+        )
+    }
+
+    /// Missing or corrupt files fall back to defaults; the next save rewrites them.
+    fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|text| Self::parse(&text).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
         }
+        fs::write(path, self.render())
     }
+}
 
-    queue!(stdout, MoveTo(cursor_x, y + 1), Print("│"), ResetColor)?;
-    Ok(())
+struct App {
+    phase: AppPhase,
+    /// Preferences as loaded, changed only by the in-app toggles, so an env
+    /// override applies to its own run and is never saved.
+    prefs: Preferences,
+    start: [NodeColor; INDICATOR_COUNT],
+    /// Scrambles `start` for each puzzle; off when `BOAAI_START` pins it.
+    random_start: bool,
+    /// Seed of the next generated puzzle, advanced by one per puzzle.
+    next_seed: u64,
+    /// Fixed goal from `BOAAI_TARGET`; `None` generates one per puzzle.
+    fixed_target: Option<[NodeColor; INDICATOR_COUNT]>,
+    /// Scramble depth from the menu; `None` keeps the classic generator.
+    difficulty: Option<Difficulty>,
+    /// Highlighted entry of `Difficulty::ALL` in the menu.
+    difficulty_selected: usize,
+    /// The first puzzle came from `BOAAI_SAVE_FILE`, so the menu is skipped.
+    resumed: bool,
+    cycle: ColorCycle,
+    rule: PressRule,
+    puzzle: PuzzleState,
+    campaign: Option<Campaign>,
+    /// Restarts the campaign after a submission.
+    campaign_plan: Option<CampaignPlan>,
+    email: EmailState,
+    submitted_email: Option<String>,
+    solved_at: Option<Instant>,
+    solution_replay: Option<SolutionReplay>,
+    max_moves_over_optimal: Option<usize>,
+    /// `BOAAI_MOVE_CAP`: presses allowed per puzzle; `None` is unlimited.
+    move_cap: Option<usize>,
+    auto_hint_after: Option<usize>,
+    show_finishing_press: bool,
+    /// Shows the board a press of the focused indicator would leave; off
+    /// unless `BOAAI_PRESS_PREVIEW=on`.
+    press_preview: bool,
+    skip_matched: bool,
+    /// Skips time-based motion (the solved hold and the solution replay
+    /// stepping) and snaps straight to the final state.
+    reduced_motion: bool,
+    target_preview: TargetPreview,
+    /// Rings the terminal bell on a solve; `BOAAI_BELL=off` silences it.
+    bell: bool,
+    /// A solve bell waiting for the main loop to write it.
+    bell_pending: bool,
+    /// Text for the terminal clipboard (debug `F11`), written by the main
+    /// loop as an OSC 52 sequence.
+    clipboard_pending: Option<String>,
+    /// Set by Ctrl+Z; the main loop suspends the process. Always false on
+    /// Windows, which has no `SIGTSTP`.
+    suspend_pending: bool,
+    /// The body box is drawn inverted until then, right after a solve.
+    flash_until: Option<Instant>,
+    layout: IndicatorLayout,
+    palette: Palette,
+    /// Shows the optimal path next to the player's on the submitted and
+    /// victory screens (`BOAAI_REVEAL_OPTIMAL=after`); never during play.
+    reveal_optimal: bool,
+    drill_selected: usize,
+    active_drill: Option<usize>,
+    drill_status: String,
+    survival: Option<SurvivalRun>,
+    /// `--sandbox`: puzzles solved so far. Each solve rolls a new puzzle and
+    /// nothing is ever submitted.
+    sandbox: Option<usize>,
+    session_limit: Option<SessionLimit>,
+    idle: Option<IdleTimeout>,
+    score_weights: ScoreWeights,
+    score: Option<u32>,
+    /// Seconds the submitted puzzle took, recorded with the submission.
+    solve_secs: Option<u64>,
+    /// Normalized emails already submitted, loaded from the destination on
+    /// the first submission of the run.
+    registered_emails: Option<HashSet<String>>,
+    /// In-progress puzzle save from `BOAAI_SAVE_FILE`.
+    save_path: Option<PathBuf>,
+    /// Submissions file from `--invite-file` or `BOAAI_INVITE_FILE`.
+    invite_file: Option<PathBuf>,
+    /// Local leaderboard from `BOAAI_LEADERBOARD_FILE`.
+    leaderboard_file: PathBuf,
+    theme: Theme,
+    /// Random 8 hex digit tag for this run, fixed at startup and stored with
+    /// each submission so support can match it to connection logs.
+    session_id: String,
+    /// Where the last submission was written, shown on the submitted view.
+    last_submission_path: Option<PathBuf>,
+    /// `--dry-run` or `BOAAI_DRY_RUN`: submissions reach the submitted view
+    /// without touching the invite file, leaderboard or duplicate check.
+    dry_run: bool,
+    /// Entries shown by the leaderboard view, read when it opens.
+    leaderboard: Vec<LeaderboardEntry>,
+    leaderboard_status: String,
+    /// Totals from the invite file, read when the stats view opens.
+    stats: SubmissionStats,
+    stats_status: String,
+    /// Scroll offset of the `?` keybindings overlay while it is open. It
+    /// takes every key until closed.
+    help: Option<usize>,
+    debug: bool,
+    should_quit: bool,
 }
 
-fn draw_box(
-    stdout: &mut Stdout,
-    x: u16,
-    y: u16,
-    width: u16,
-    height: u16,
-    border_color: Color,
-) -> io::Result<()> {
-    if width < 2 || height < 2 {
-        return Ok(());
-    }
+struct TerminalSession {
+    /// False when the session draws on the primary buffer, either forced by
+    /// `--no-alt-screen` or because the terminal refused the alternate one.
+    alternate_screen: bool,
+}
 
-    let horizontal = "─".repeat((width - 2) as usize);
-    queue!(
-        stdout,
-        SetForegroundColor(border_color),
-        MoveTo(x, y),
-        Print(format!("┌{}┐", horizontal)),
-        MoveTo(x, y + height - 1),
-        Print(format!("└{}┘", horizontal))
-    )?;
+impl TerminalSession {
+    fn enter(stdout: &mut Stdout, alternate_screen: bool) -> io::Result<Self> {
+        Ok(Self {
+            alternate_screen: Self::set_up(stdout, alternate_screen)?,
+        })
+    }
 
-    for row in (y + 1)..(y + height - 1) {
-        queue!(
+    /// Raw mode, the screen and input capture. Returns whether the alternate
+    /// screen is in use.
+    fn set_up(stdout: &mut Stdout, alternate_screen: bool) -> io::Result<bool> {
+        terminal::enable_raw_mode()?;
+        let alternate_screen = enter_screen(stdout, alternate_screen);
+        execute!(
             stdout,
-            MoveTo(x, row),
-            Print("│"),
-            MoveTo(x + width - 1, row),
-            Print("│")
+            EnableMouseCapture,
+            EnableBracketedPaste,
+            cursor::Hide
         )?;
+        Ok(alternate_screen)
     }
 
-    queue!(stdout, ResetColor)?;
-    Ok(())
-}
-
-fn draw_colored_state_line(
-    stdout: &mut Stdout,
-    x: u16,
-    y: u16,
-    label: &str,
-    state: [NodeColor; INDICATOR_COUNT],
-) -> io::Result<()> {
-    let mut cursor_x = x;
-    queue!(
-        stdout,
-        MoveTo(cursor_x, y),
-        SetForegroundColor(Color::DarkGrey),
-        Print(format!("{label}   ["))
-    )?;
-    cursor_x += (label.len() + 4) as u16;
-
-    for (index, color) in state.iter().enumerate() {
-        let token = color.as_str();
-        queue!(
+    /// Hands the terminal back in the state the shell expects.
+    fn restore(&self, stdout: &mut Stdout) {
+        let _ = execute!(
             stdout,
-            MoveTo(cursor_x, y),
-            SetForegroundColor(color.term_color()),
-            SetAttribute(Attribute::Bold),
-            Print(token),
-            SetAttribute(Attribute::Reset)
-        )?;
-        cursor_x += token.len() as u16;
+            cursor::Show,
+            DisableMouseCapture,
+            DisableBracketedPaste,
+            ResetColor
+        );
+        let _ = if self.alternate_screen {
+            execute!(stdout, LeaveAlternateScreen)
+        } else {
+            execute!(stdout, Clear(ClearType::All), MoveTo(0, 0))
+        };
+        let _ = terminal::disable_raw_mode();
+    }
 
-        if index < INDICATOR_COUNT - 1 {
-            queue!(
-                stdout,
-                MoveTo(cursor_x, y),
-                SetForegroundColor(Color::DarkGrey),
-                Print(" | ")
-            )?;
-            cursor_x += 3;
-        }
+    /// Ctrl+Z: restores the terminal, stops the process with `SIGTSTP` and
+    /// sets the session up again once the shell resumes it (`fg`). The
+    /// caller redraws. Raw mode turns off the terminal's own Ctrl+Z handling,
+    /// so the signal is sent through `kill`.
+    #[cfg(unix)]
+    fn suspend(&mut self, stdout: &mut Stdout) -> io::Result<()> {
+        self.restore(stdout);
+        let stopped = Command::new("kill")
+            .args(["-TSTP", &process::id().to_string()])
+            .status();
+        self.alternate_screen = Self::set_up(stdout, self.alternate_screen)?;
+        stopped.map(drop)
     }
+}
 
-    queue!(
-        stdout,
-        MoveTo(cursor_x, y),
-        SetForegroundColor(Color::DarkGrey),
-        Print("]"),
-        ResetColor
-    )?;
+impl Drop for TerminalSession {
+    fn drop(&mut self) {
+        self.restore(&mut io::stdout());
+    }
+}
 
-    Ok(())
+/// Switches to the alternate screen when asked and the terminal accepts it,
+/// otherwise clears the primary buffer to draw on. Returns whether the
+/// alternate screen is in use.
+fn enter_screen(out: &mut impl Write, alternate_screen: bool) -> bool {
+    if alternate_screen && execute!(out, EnterAlternateScreen).is_ok() {
+        return true;
+    }
+    let _ = execute!(out, Clear(ClearType::All), MoveTo(0, 0));
+    false
 }
 
-fn draw_puzzle_view(
-    stdout: &mut Stdout,
-    x: u16,
-    body_y: u16,
-    width: u16,
-    body_height: u16,
-    app: &App,
-) -> io::Result<()> {
-    let puzzle = &app.puzzle;
-    let bottom = body_y + body_height - 1;
-    let mut line = body_y + 1;
+impl App {
+    fn new(debug: bool, start: [NodeColor; INDICATOR_COUNT], cycle: ColorCycle) -> Self {
+        Self::with_config(
+            &Config {
+                debug,
+                // The replayed board brings its own target.
+                target: None,
+                ..Config::from_env()
+            },
+            start,
+            cycle,
+        )
+    }
 
-    queue!(
-        stdout,
-        MoveTo(x + 3, line),
-        SetForegroundColor(Color::White),
-        SetAttribute(Attribute::Bold),
-        Print("LATTICE NODE // ACCESS CHALLENGE"),
-        SetAttribute(Attribute::Reset),
-        MoveTo(x + 3, line + 1),
-        SetForegroundColor(Color::DarkGrey),
-        Print("6-button custom puzzle. Use only controls below.")
-    )?;
+    fn with_config(
+        config: &Config,
+        start: [NodeColor; INDICATOR_COUNT],
+        cycle: ColorCycle,
+    ) -> Self {
+        let Config {
+            debug,
+            seed,
+            ref invite_file,
+            dry_run,
+            press_rule: rule,
+            random_start,
+            prefs,
+            ..
+        } = *config;
+        let seed = seed.unwrap_or_else(clock_seed);
+        let fixed_target = configured_target(config, start, cycle)
+            .expect("main refuses to start with a bad BOAAI_TARGET");
+        let save_path = config.save_file.clone();
+        let resumed = save_path
+            .as_deref()
+            .and_then(|path| resume_puzzle(path, cycle, rule, fixed_target));
+        let was_resumed = resumed.is_some();
+        let (mut puzzle, difficulty) = resumed.unwrap_or_else(|| {
+            (
+                seeded_puzzle_state(seed, start, cycle, rule, random_start, fixed_target),
+                None,
+            )
+        });
+        puzzle.show_rules = prefs.show_rules;
+        if let Some(warning) = &config.warning {
+            puzzle.set_status(warning.clone());
+        }
 
-    line += 3;
-    queue!(
-        stdout,
-        MoveTo(x + 3, line),
-        SetForegroundColor(Color::DarkGrey)
-    )?;
-    draw_colored_state_line(stdout, x + 3, line, "Target", puzzle.target)?;
-    queue!(
-        stdout,
-        MoveTo(x + 3, line + 1),
-        SetForegroundColor(Color::DarkGrey),
-        Print(format!(
-            "Current  [{}]",
-            render_state(puzzle.current).to_ascii_uppercase()
-        ))
-    )?;
+        Self {
+            phase: AppPhase::Puzzle,
+            prefs,
+            start,
+            random_start,
+            next_seed: seed.wrapping_add(1),
+            fixed_target,
+            difficulty,
+            difficulty_selected: Difficulty::ALL
+                .iter()
+                .position(|&choice| choice == difficulty.unwrap_or(prefs.difficulty))
+                .unwrap_or(0),
+            resumed: was_resumed,
+            cycle,
+            rule,
+            puzzle,
+            campaign: config.campaign.map(CampaignPlan::start),
+            campaign_plan: config.campaign,
+            email: EmailState {
+                email: String::new(),
+                focus: EmailFocus::Input,
+                selected_button: 0,
+                status: "Solve the puzzle to unlock event invite submission.".to_string(),
+                suggestion: None,
+                awaiting_confirmation: false,
+            },
+            submitted_email: None,
+            solved_at: None,
+            solution_replay: None,
+            max_moves_over_optimal: config.max_moves_over_optimal,
+            move_cap: config.move_cap,
+            auto_hint_after: config.auto_hint_after,
+            show_finishing_press: config.finishing_hint.unwrap_or(prefs.finishing_hint),
+            press_preview: config.press_preview,
+            skip_matched: config.skip_matched.unwrap_or(prefs.skip_matched),
+            reduced_motion: config.reduced_motion.unwrap_or(prefs.reduced_motion),
+            target_preview: TargetPreview::Waiting,
+            bell: config.bell,
+            bell_pending: false,
+            clipboard_pending: None,
+            suspend_pending: false,
+            flash_until: None,
+            layout: config.layout,
+            palette: config.palette.unwrap_or(if prefs.color_blind {
+                Palette::ColorBlind
+            } else {
+                Palette::Default
+            }),
+            reveal_optimal: config.reveal_optimal,
+            drill_selected: 0,
+            active_drill: None,
+            drill_status: "Pick a drill to practice one rule at a time.".to_string(),
+            survival: None,
+            sandbox: None,
+            session_limit: config
+                .session_limit
+                .map(|(length, on_unsubmitted)| SessionLimit {
+                    deadline: Instant::now() + length,
+                    on_unsubmitted,
+                    grace_until: None,
+                }),
+            idle: config.idle_window.map(|window| IdleTimeout {
+                window,
+                last_input: Instant::now(),
+                quit_at: None,
+            }),
+            score_weights: config.score_weights,
+            score: None,
+            solve_secs: None,
+            registered_emails: None,
+            save_path,
+            invite_file: invite_file.clone(),
+            leaderboard_file: config.leaderboard_file.clone(),
+            theme: Theme::new(config.accent),
+            session_id: session_id(seed),
+            last_submission_path: None,
+            dry_run,
+            leaderboard: Vec::new(),
+            leaderboard_status: String::new(),
+            stats: SubmissionStats::default(),
+            stats_status: String::new(),
+            help: None,
+            debug,
+            should_quit: false,
+        }
+    }
+}
 
-    let indicator_y = line + 3;
-    let indicator_width = (width
-        .saturating_sub(8)
-        .saturating_sub(2 * (INDICATOR_COUNT as u16 - 1))
-        / INDICATOR_COUNT as u16)
-        .clamp(10, 16);
-    let indicator_gap = 2;
-    let indicator_span = indicator_width * INDICATOR_COUNT as u16 + indicator_gap * 3;
-    let indicator_start_x = x + width.saturating_sub(indicator_span) / 2;
+/// One line of a `BOAAI_INPUT_SCRIPT` file.
+#[derive(Debug, PartialEq, Eq)]
+enum ScriptStep {
+    Key(KeyEvent),
+    Wait(Duration),
+}
 
-    if indicator_y + 2 < bottom {
-        for index in 0..INDICATOR_COUNT {
-            let selected = matches!(puzzle.focus, PuzzleFocus::Indicator(i) if i == index);
-            let label = format!("{} {}", index + 1, puzzle.current[index].as_str());
-            draw_button(
-                stdout,
-                indicator_start_x + index as u16 * (indicator_width + indicator_gap),
-                indicator_y,
-                indicator_width,
-                &label,
-                selected,
-                puzzle.current[index].term_color(),
-            )?;
-        }
+/// Scripted key input consumed by the main loop before (or instead of) the
+/// live terminal.
+struct InputScript {
+    steps: VecDeque<ScriptStep>,
+    quit_when_done: bool,
+}
+
+/// A recorded puzzle run: where it started, what it aimed for, and the
+/// 0-based indicator presses applied in order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Replay {
+    seed: u64,
+    cycle: ColorCycle,
+    rule: PressRule,
+    initial: [NodeColor; INDICATOR_COUNT],
+    target: [NodeColor; INDICATOR_COUNT],
+    moves: Vec<usize>,
+    /// Level of a saved difficulty-menu puzzle, so a resumed session keeps
+    /// generating at it; `None` for replays and plain seeded puzzles.
+    difficulty: Option<Difficulty>,
+}
+
+impl Replay {
+    fn final_state(&self) -> [NodeColor; INDICATOR_COUNT] {
+        self.moves.iter().fold(self.initial, |state, &press| {
+            press_indicator(state, press, self.cycle, self.rule)
+        })
     }
 
-    let action_y = indicator_y + 4;
-    let action_width = 18;
-    let action_gap = 2;
-    let action_span = action_width * 3 + action_gap * 2;
-    let action_start_x = x + width.saturating_sub(action_span) / 2;
-    let action_labels = [
-        "Hint",
-        "Reset",
-        if puzzle.show_rules {
-            "Hide Rules"
-        } else {
-            "Show Rules"
-        },
-    ];
+    fn solves(&self) -> bool {
+        self.final_state() == self.target
+    }
+}
 
-    if action_y + 2 < bottom {
-        for (index, label) in action_labels.iter().enumerate() {
-            let selected = matches!(puzzle.focus, PuzzleFocus::Action(i) if i == index);
-            draw_button(
-                stdout,
-                action_start_x + index as u16 * (action_width + action_gap),
-                action_y,
-                action_width,
-                label,
-                selected,
-                Color::White,
-            )?;
+fn main() -> io::Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let cli = parse_args(&args).unwrap_or_else(|error| {
+        eprintln!("{error}\n\n{USAGE}");
+        process::exit(2);
+    });
+    if cli.help {
+        print!("{USAGE}");
+        return Ok(());
+    }
+    let config = Config::load().with_args(&cli);
+    let StartupConfig {
+        cycle,
+        start,
+        fixed_target,
+        mut script,
+    } = load_startup_config(&config).unwrap_or_else(|errors| {
+        eprintln!("Configuration problems:");
+        for (name, error) in errors {
+            eprintln!("  - {name}: {error}");
         }
+        process::exit(2);
+    });
+    let shared_start = cli.code.as_deref().map(|code| {
+        decode_share_code(code)
+            .and_then(|state| cycle.check_state(state).map(|()| state))
+            .unwrap_or_else(|error| {
+                eprintln!("Invalid --code: {error}.");
+                process::exit(2);
+            })
+    });
+
+    if let Some(output) = &cli.generate_replay {
+        return generate_replay(output, &config, start, cycle, fixed_target);
     }
 
-    let status_y = action_y + 4;
-    if status_y < bottom {
-        queue!(
-            stdout,
-            MoveTo(x + 3, status_y),
-            SetForegroundColor(Color::Rgb {
-                r: 255,
-                g: 90,
-                b: 0
-            }),
-            Print(trim_to_width(
-                &puzzle.status,
-                width.saturating_sub(6) as usize
-            ))
-        )?;
+    if let Some(frames) = cli.bench_render {
+        return bench_render(frames);
     }
 
-    if puzzle.show_rules {
-        let rules = [
-            "1) Pressed button advances by +2 color steps (OFF>GREEN>...>WHITE>OFF)",
-            "2) Adjacent buttons (distance 1) advance by +1 step",
-            "3) Distance-2 buttons move backward by 1 step",
-            "4) Opposite button (distance 3) advances by +3 steps",
-            "Color map: OFF=0 GREEN=1 BLUE=2 RED=3 PURPLE=4 WHITE=5",
-        ];
-        let mut rules_y = status_y + 2;
-        for rule in rules {
-            if rules_y >= bottom {
-                break;
+    if let Some((input, output)) = &cli.convert {
+        match convert_submissions(input, output) {
+            Ok(converted) => {
+                println!(
+                    "Converted {} record{} from {} to {}.",
+                    converted.records,
+                    if converted.records == 1 { "" } else { "s" },
+                    input.display(),
+                    output.display()
+                );
+                if converted.skipped > 0 {
+                    println!("Skipped {} malformed line(s).", converted.skipped);
+                }
+            }
+            Err(error) => {
+                eprintln!("Could not convert {}: {error}", input.display());
+                process::exit(1);
             }
-            queue!(
-                stdout,
-                MoveTo(x + 3, rules_y),
-                SetForegroundColor(Color::DarkGrey),
-                Print(trim_to_width(rule, width.saturating_sub(6) as usize))
-            )?;
-            rules_y += 1;
         }
+        return Ok(());
     }
 
-    if app.debug {
-        queue!(
-            stdout,
-            MoveTo(x + 3, bottom.saturating_sub(1)),
-            SetForegroundColor(Color::DarkGrey),
-            Print("Debug: press F12 for instant solve")
-        )?;
+    if let Some((start_spec, target_spec)) = &cli.solve {
+        match solve_specs(start_spec, target_spec, cycle, config.press_rule) {
+            Ok(Some(moves)) => println!("{}", render_solution(&moves)),
+            Ok(None) => {
+                println!("unsolvable");
+                process::exit(1);
+            }
+            Err(error) => {
+                eprintln!("Invalid state: {error}.");
+                process::exit(2);
+            }
+        }
+        return Ok(());
     }
 
-    queue!(stdout, ResetColor)?;
-    Ok(())
-}
+    if cli.selftest {
+        if !selftest(start, cycle) {
+            process::exit(1);
+        }
+        return Ok(());
+    }
 
-fn draw_email_view(
-    stdout: &mut Stdout,
-    x: u16,
-    body_y: u16,
-    width: u16,
-    body_height: u16,
-    app: &App,
-) -> io::Result<()> {
-    let email = &app.email;
-    let bottom = body_y + body_height - 1;
+    let mut stdout = io::stdout();
+    if let Some(input) = &cli.replay {
+        let replay = load_replay(input)?;
+        let puzzle = puzzle_state_for(replay.initial, replay.target, replay.cycle, replay.rule)
+            .unwrap_or_else(|error| {
+                eprintln!("Misconfigured challenge: {error}.");
+                process::exit(1);
+            });
+        if config.splash_seconds > 0 {
+            show_splash_screen(
+                &mut stdout,
+                config.splash_seconds,
+                &Theme::new(config.accent),
+            )?;
+        }
+        return play_replay(&mut stdout, &replay, puzzle, !cli.no_alt_screen);
+    }
 
-    queue!(
-        stdout,
-        MoveTo(x + 3, body_y + 1),
-        SetForegroundColor(Color::White),
-        SetAttribute(Attribute::Bold),
-        Print("EVENT INVITE REQUEST"),
-        SetAttribute(Attribute::Reset),
-        MoveTo(x + 3, body_y + 3),
-        SetForegroundColor(Color::Rgb {
-            r: 255,
-            g: 90,
-            b: 0
-        }),
-        Print("Warning: confirmation is final. To change it later, solve the puzzle again."),
-        MoveTo(x + 3, body_y + 5),
-        SetForegroundColor(Color::DarkGrey),
-        Print("Email Input")
-    )?;
+    // A scripted run without a terminal (e.g. CI) renders into a sink at a
+    // fixed size so the draw path is still exercised.
+    let headless = script.is_some() && !stdout.is_terminal();
 
-    let field_y = body_y + 6;
-    let field_width = width.saturating_sub(8).max(20);
-    let field_x = x + (width.saturating_sub(field_width)) / 2;
-    let is_input_selected = matches!(email.focus, EmailFocus::Input);
-    let mut email_text = if email.email.is_empty() {
-        "type-your-email@example.com".to_string()
+    if !headless && config.splash_seconds > 0 {
+        show_splash_screen(
+            &mut stdout,
+            config.splash_seconds,
+            &Theme::new(config.accent),
+        )?;
+    }
+
+    // Only suspending (Unix) needs the session mutably.
+    #[cfg_attr(not(unix), allow(unused_mut))]
+    let mut terminal = if headless {
+        None
     } else {
-        email.email.clone()
+        Some(TerminalSession::enter(&mut stdout, !cli.no_alt_screen)?)
     };
-    if is_input_selected && email_text.len() < field_width.saturating_sub(4) as usize {
-        email_text.push('_');
+    let mut app = App::with_config(&config, start, cycle);
+    if let Some(initial) = shared_start {
+        start_from_code(&mut app, initial);
+    }
+    if cli.sandbox {
+        app.sandbox = Some(0);
     }
+    if cli.drills {
+        app.phase = AppPhase::Drills;
+    } else if cli.survival {
+        start_survival(&mut app, survival_from_env());
+    } else if let Some(difficulty) = cli.difficulty {
+        choose_difficulty(&mut app, difficulty);
+    } else if !app.resumed && script.is_none() && shared_start.is_none() {
+        app.phase = AppPhase::Menu;
+    }
+    log_event(&format!(
+        "start phase={:?} seed={} colors={} start={}",
+        app.phase,
+        app.puzzle
+            .seed
+            .map_or("-".to_string(), |seed| seed.to_string()),
+        cycle
+            .colors()
+            .map(NodeColor::as_str)
+            .collect::<Vec<_>>()
+            .join(","),
+        render_state_spec(start)
+    ));
+    let mut logged_phase = app.phase;
+    let mut needs_redraw = true;
+    let mut shown_clock = 0;
+    // Time of the latest resize event not yet drawn.
+    let mut resize_pending: Option<Instant> = None;
 
-    draw_button(
-        stdout,
-        field_x,
-        field_y,
-        field_width,
-        &email_text,
-        is_input_selected,
-        if email.email.is_empty() {
-            Color::DarkGrey
-        } else {
-            Color::White
-        },
-    )?;
+    loop {
+        if app.phase != logged_phase {
+            log_event(&format!("phase {logged_phase:?} -> {:?}", app.phase));
+            logged_phase = app.phase;
+        }
+        if resize_pending.is_some_and(|at| at.elapsed() >= RESIZE_SETTLE) {
+            resize_pending = None;
+            needs_redraw = true;
+        }
+        if needs_redraw {
+            if headless {
+                draw_frame(&mut io::sink(), &app, 120, 40)?;
+            } else {
+                draw_app(&mut stdout, &app)?;
+            }
+            needs_redraw = false;
+        }
+        if app.bell_pending {
+            app.bell_pending = false;
+            if !headless {
+                stdout.write_all(b"\x07")?;
+                stdout.flush()?;
+            }
+        }
+        if let Some(text) = app.clipboard_pending.take() {
+            if !headless {
+                stdout.write_all(osc52_copy(&text).as_bytes())?;
+                stdout.flush()?;
+            }
+        }
+        #[cfg(unix)]
+        if std::mem::take(&mut app.suspend_pending) {
+            if let Some(terminal) = terminal.as_mut() {
+                log_event("suspend");
+                terminal.suspend(&mut stdout)?;
+                log_event("resume");
+                needs_redraw = true;
+                continue;
+            }
+        }
 
-    let button_y = field_y + 5;
-    let button_width = 24;
-    let button_gap = 4;
-    let button_start_x = x + width.saturating_sub(button_width * 2 + button_gap) / 2;
-    let buttons = ["Confirm Invite", "Solve Again"];
-    for (index, label) in buttons.iter().enumerate() {
-        let selected = matches!(email.focus, EmailFocus::Buttons) && email.selected_button == index;
-        draw_button(
-            stdout,
-            button_start_x + index as u16 * (button_width + button_gap),
-            button_y,
-            button_width,
-            label,
-            selected,
-            if index == 0 {
-                Color::Rgb {
-                    r: 255,
-                    g: 90,
-                    b: 0,
+        if app.should_quit {
+            break;
+        }
+
+        if let Some(input) = script.as_mut() {
+            match input.steps.pop_front() {
+                Some(ScriptStep::Key(key)) => {
+                    note_activity(&mut app, Instant::now());
+                    needs_redraw = handle_key(&mut app, key)?;
+                    continue;
                 }
-            } else {
-                Color::DarkGrey
-            },
-        )?;
-    }
+                Some(ScriptStep::Wait(duration)) => thread::sleep(duration),
+                None if input.quit_when_done || headless => break,
+                None => script = None,
+            }
+        } else if event::poll(next_wake(&app, resize_pending, Instant::now()))? {
+            let event = event::read()?;
+            if matches!(event, Event::Key(_) | Event::Mouse(_) | Event::Paste(_)) {
+                note_activity(&mut app, Instant::now());
+            }
+            match event {
+                Event::Key(key) => {
+                    needs_redraw = handle_key(&mut app, key)?;
+                }
+                Event::Mouse(mouse) => {
+                    let (cols, rows) = terminal::size()?;
+                    needs_redraw = handle_mouse(&mut app, mouse, cols, rows)?;
+                }
+                Event::Paste(text) => {
+                    needs_redraw = handle_paste(&mut app, &text);
+                }
+                Event::Resize(_, _) => {
+                    resize_pending = Some(Instant::now());
+                }
+                _ => {}
+            }
+        }
 
-    if button_y + 4 < bottom {
-        queue!(
-            stdout,
-            MoveTo(x + 3, button_y + 4),
-            SetForegroundColor(Color::DarkGrey),
-            Print("Tab switches between input and buttons. Enter activates the selected control."),
-            MoveTo(x + 3, button_y + 5),
-            SetForegroundColor(Color::Rgb {
-                r: 255,
-                g: 90,
-                b: 0
-            }),
-            Print(trim_to_width(
-                &email.status,
-                width.saturating_sub(6) as usize
-            ))
-        )?;
+        if tick_app(&mut app) {
+            needs_redraw = true;
+        }
+        if !needs_redraw
+            && resize_pending.is_none()
+            && !headless
+            && matches!(app.phase, AppPhase::Puzzle)
+        {
+            let clock = solve_clock(&app.puzzle).as_secs();
+            if clock != shown_clock {
+                shown_clock = clock;
+                let (cols, rows) = terminal::size()?;
+                draw_header_clock(&mut stdout, &app, cols, rows)?;
+            }
+        }
     }
 
-    queue!(stdout, ResetColor)?;
+    drop(terminal);
+    if !headless {
+        let path = preferences_path();
+        if let Err(error) = app.prefs.save(&path) {
+            eprintln!("Could not save preferences to {}: {error}", path.display());
+        }
+    }
+    // Printed only now that the terminal session has left the alternate
+    // screen, so the line lands on the normal buffer.
+    if cli.emit_result {
+        println!("{}", result_json(&app));
+        if app.submitted_email.is_none() {
+            process::exit(EXIT_NOT_SUBMITTED);
+        }
+    }
     Ok(())
 }
 
-fn draw_submitted_view(
-    stdout: &mut Stdout,
-    x: u16,
-    body_y: u16,
-    width: u16,
-    _body_height: u16,
-    app: &App,
-) -> io::Result<()> {
-    let email = app.submitted_email.as_deref().unwrap_or("unknown");
-    queue!(
-        stdout,
-        MoveTo(x + 3, body_y + 3),
-        SetForegroundColor(Color::White),
-        SetAttribute(Attribute::Bold),
-        Print("Invite request submitted."),
-        SetAttribute(Attribute::Reset),
-        MoveTo(x + 3, body_y + 5),
-        SetForegroundColor(Color::DarkGrey),
-        Print(trim_to_width(
-            &format!("Recorded email: {email}"),
-            width.saturating_sub(6) as usize
-        )),
-        MoveTo(x + 3, body_y + 7),
-        SetForegroundColor(Color::Rgb {
-            r: 255,
-            g: 90,
-            b: 0
-        }),
-        Print("Press Enter or Esc to close the SSH session.")
-    )?;
-    Ok(())
+/// `--emit-result` exit status when the session ended without a submission.
+const EXIT_NOT_SUBMITTED: i32 = 3;
+
+/// One-line summary for `--emit-result`:
+/// `{"solved":true,"moves":7,"solve_seconds":42,"email":"a@b.co"}`, with
+/// `null` for the solve time and email when there are none.
+fn result_json(app: &App) -> String {
+    let solve_seconds = app
+        .solve_secs
+        .or_else(|| app.puzzle.solved_in.map(|elapsed| elapsed.as_secs()));
+    format!(
+        "{{\"solved\":{},\"moves\":{},\"solve_seconds\":{},\"email\":{}}}",
+        solve_seconds.is_some(),
+        app.puzzle.moves_taken,
+        solve_seconds.map_or("null".to_string(), |secs| secs.to_string()),
+        app.submitted_email
+            .as_deref()
+            .map_or("null".to_string(), |email| format!(
+                "\"{}\"",
+                json_escape(email)
+            ))
+    )
 }
 
-fn draw_footer(stdout: &mut Stdout, x: u16, width: u16, rows: u16, app: &App) -> io::Result<()> {
-    let top = rows.saturating_sub(2);
-    let bottom = rows.saturating_sub(1);
-    let bar = "─".repeat(width as usize);
-    let message = match app.phase {
-        AppPhase::Puzzle => "Left/Right: move   Up/Down: switch row   Enter: activate   Esc: quit",
-        AppPhase::Email => "Type email, Tab to buttons, Enter to activate selection, Esc to quit",
-        AppPhase::Submitted => "Session complete. Press Enter or Esc to exit.",
-    };
+/// How long to wait for input: until the earliest pending work in
+/// `tick_app` or a visible clock is due, or `IDLE_POLL` when nothing is, so
+/// an idle session barely wakes.
+fn next_wake(app: &App, resize_pending: Option<Instant>, now: Instant) -> Duration {
+    let mut due: Vec<Instant> = Vec::new();
+    due.extend(resize_pending.map(|at| at + RESIZE_SETTLE));
+    due.extend(app.flash_until);
+
+    match app.target_preview {
+        TargetPreview::Running(_) => due.push(now + TARGET_PREVIEW_FRAME),
+        // Started by the next tick once the puzzle view is up.
+        TargetPreview::Waiting if matches!(app.phase, AppPhase::Puzzle) => due.push(now),
+        _ => {}
+    }
 
-    let footer_text = trim_to_width(message, width as usize);
-    let text_x = x + width.saturating_sub(footer_text.len() as u16) / 2;
-    queue!(
-        stdout,
-        MoveTo(x, top),
-        SetForegroundColor(Color::DarkGrey),
-        Print(bar),
-        MoveTo(text_x, bottom),
-        Print(footer_text),
-        ResetColor
-    )?;
-    Ok(())
-}
+    if let Some(limit) = &app.session_limit {
+        if let Some(grace_until) = limit.grace_until {
+            due.push(countdown_tick(grace_until, now));
+        } else if limit.deadline.saturating_duration_since(now) <= SESSION_WARNING {
+            due.push(countdown_tick(limit.deadline, now));
+        } else {
+            due.push(limit.deadline - SESSION_WARNING);
+        }
+    }
 
-fn draw_button(
-    stdout: &mut Stdout,
-    x: u16,
-    y: u16,
-    width: u16,
-    label: &str,
-    selected: bool,
-    accent: Color,
-) -> io::Result<()> {
-    if width < 4 {
-        return Ok(());
+    if let Some(idle) = &app.idle {
+        due.push(idle.quit_at.unwrap_or(idle.last_input + idle.window));
     }
 
-    let inner_width = (width - 2) as usize;
-    let top = format!("┌{}┐", "─".repeat(inner_width));
-    let bottom = format!("└{}┘", "─".repeat(inner_width));
-    let text = center_text(&trim_to_width(label, inner_width), inner_width);
+    if matches!(app.phase, AppPhase::Puzzle) {
+        if let Some(run) = &app.survival {
+            due.push(countdown_tick(run.puzzle_started + run.budget, now));
+        }
+        // The header clock shows whole seconds.
+        if app.puzzle.solved_in.is_none() {
+            let elapsed = now.saturating_duration_since(app.puzzle.started_at);
+            due.push(
+                now + Duration::from_secs(1) - Duration::from_nanos(elapsed.subsec_nanos().into()),
+            );
+        }
+    }
 
-    let border_color = if selected {
-        Color::White
-    } else {
-        Color::DarkGrey
-    };
-    let text_color = if selected { Color::Black } else { accent };
-    let fill_color = if selected { Color::Grey } else { Color::Black };
+    if let (AppPhase::Solved, Some(solved_at), None) = (app.phase, app.solved_at, &app.campaign) {
+        due.push(solved_at + SOLVED_HOLD);
+    }
 
-    queue!(
-        stdout,
-        MoveTo(x, y),
-        SetForegroundColor(border_color),
-        SetBackgroundColor(Color::Black),
-        Print(top),
-        MoveTo(x, y + 1),
-        Print("│"),
-        SetBackgroundColor(fill_color),
-        SetForegroundColor(text_color),
-        Print(text),
-        SetBackgroundColor(Color::Black),
-        SetForegroundColor(border_color),
-        Print("│"),
-        MoveTo(x, y + 2),
-        Print(bottom),
-        ResetColor
-    )?;
-    Ok(())
+    if let Some(replay) = &app.solution_replay {
+        if replay.step < app.puzzle.moves.len() {
+            due.push(replay.last_step + REPLAY_STEP);
+        }
+    }
+
+    // A deadline already behind `now` was handled by the last tick; waking
+    // for it again would spin on `poll(0)`.
+    due.into_iter()
+        .filter(|&at| at >= now)
+        .min()
+        .map_or(IDLE_POLL, |at| {
+            at.saturating_duration_since(now).min(IDLE_POLL)
+        })
 }
 
-fn handle_key(app: &mut App, key: KeyEvent) -> io::Result<bool> {
-    if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
-        app.should_quit = true;
-        return Ok(true);
+/// When a whole-second countdown to `deadline` next changes.
+fn countdown_tick(deadline: Instant, now: Instant) -> Instant {
+    let left = deadline.saturating_duration_since(now);
+    match left.subsec_nanos() {
+        0 => now + left.min(Duration::from_secs(1)),
+        nanos => now + Duration::from_nanos(nanos.into()),
     }
+}
 
-    match app.phase {
-        AppPhase::Puzzle => Ok(handle_puzzle_key(app, key)),
-        AppPhase::Email => handle_email_key(app, key),
-        AppPhase::Submitted => Ok(handle_submitted_key(app, key)),
+/// Advances time-driven state; returns true when the screen needs a redraw.
+/// Every check runs on every tick, so one that keeps asking for redraws
+/// (a countdown, the preview) never starves the others.
+fn tick_app(app: &mut App) -> bool {
+    let now = Instant::now();
+    let mut redraw = tick_target_preview(app, now);
+    redraw |= tick_session_limit(app, now);
+    redraw |= tick_idle_timeout(app, now);
+
+    if app.flash_until.is_some_and(|until| now >= until) {
+        app.flash_until = None;
+        redraw = true;
+    }
+
+    if let (AppPhase::Puzzle, Some(run)) = (app.phase, &app.survival) {
+        if now.saturating_duration_since(run.puzzle_started) >= run.budget {
+            end_survival_run(app);
+        }
+        // The footer shows the budget counting down.
+        redraw = true;
     }
+
+    if let (AppPhase::Solved, Some(solved_at), None) = (app.phase, app.solved_at, &app.campaign) {
+        if now.saturating_duration_since(solved_at) >= SOLVED_HOLD {
+            complete_puzzle(app);
+            redraw = true;
+        }
+    }
+
+    if let Some(replay) = app.solution_replay.as_mut() {
+        if replay.step < app.puzzle.moves.len()
+            && now.saturating_duration_since(replay.last_step) >= REPLAY_STEP
+        {
+            replay.state = press_indicator(
+                replay.state,
+                app.puzzle.moves[replay.step],
+                app.puzzle.cycle,
+                app.puzzle.rule,
+            );
+            replay.step += 1;
+            replay.last_step = now;
+            redraw = true;
+        }
+    }
+    redraw
 }
 
-fn handle_puzzle_key(app: &mut App, key: KeyEvent) -> bool {
-    match key.code {
-        KeyCode::Left => {
-            match app.puzzle.focus {
-                PuzzleFocus::Indicator(index) => {
-                    app.puzzle.focus =
-                        PuzzleFocus::Indicator((index + INDICATOR_COUNT - 1) % INDICATOR_COUNT)
-                }
-                PuzzleFocus::Action(index) => {
-                    app.puzzle.focus = PuzzleFocus::Action((index + 2) % 3)
-                }
-            }
+/// Starts the target preview the first time the puzzle view is up and ends
+/// it after `TARGET_PREVIEW`. Plain mode and reduced motion skip it. Input
+/// is handled as usual throughout; only the drawn target row changes.
+fn tick_target_preview(app: &mut App, now: Instant) -> bool {
+    match app.target_preview {
+        TargetPreview::Waiting if plain_mode() || app.reduced_motion => {
+            app.target_preview = TargetPreview::Done;
+            false
+        }
+        TargetPreview::Waiting if matches!(app.phase, AppPhase::Puzzle) => {
+            app.target_preview = TargetPreview::Running(now);
             true
         }
-        KeyCode::Right => {
-            match app.puzzle.focus {
-                PuzzleFocus::Indicator(index) => {
-                    app.puzzle.focus = PuzzleFocus::Indicator((index + 1) % INDICATOR_COUNT)
-                }
-                PuzzleFocus::Action(index) => {
-                    app.puzzle.focus = PuzzleFocus::Action((index + 1) % 3)
-                }
-            }
+        TargetPreview::Running(started)
+            if app.reduced_motion || now.saturating_duration_since(started) >= TARGET_PREVIEW =>
+        {
+            app.target_preview = TargetPreview::Done;
             true
         }
-        KeyCode::Up | KeyCode::Down => {
-            match app.puzzle.focus {
-                PuzzleFocus::Indicator(index) => {
-                    app.puzzle.focus = PuzzleFocus::Action((index / 2).min(2));
-                }
-                PuzzleFocus::Action(index) => {
-                    let target = (index * 2).min(INDICATOR_COUNT - 1);
-                    app.puzzle.focus = PuzzleFocus::Indicator(target);
-                }
+        TargetPreview::Running(_) => true,
+        _ => false,
+    }
+}
+
+/// The target row as drawn: while the preview runs, each position is
+/// advanced a shrinking number of steps so the row cycles and settles on the
+/// real target.
+fn shown_target(app: &App, now: Instant) -> [NodeColor; INDICATOR_COUNT] {
+    let target = app.puzzle.target;
+    let TargetPreview::Running(started) = app.target_preview else {
+        return target;
+    };
+    let left = TARGET_PREVIEW.saturating_sub(now.saturating_duration_since(started));
+    let frames = (left.as_millis() / TARGET_PREVIEW_FRAME.as_millis()) as usize;
+    let cycle = app.puzzle.cycle;
+    std::array::from_fn(|index| {
+        (0..frames * (index + 1) % cycle.len()).fold(target[index], |color, _| cycle.next(color))
+    })
+}
+
+/// Enforces the session cap. A player holding an unsubmitted solve gets a short
+/// grace period to confirm the invite unless the policy says to discard it.
+fn tick_session_limit(app: &mut App, now: Instant) -> bool {
+    let Some(limit) = app.session_limit.as_mut() else {
+        return false;
+    };
+
+    if let Some(grace_until) = limit.grace_until {
+        if now >= grace_until {
+            app.should_quit = true;
+        }
+        return true;
+    }
+
+    if now < limit.deadline {
+        return limit.deadline.saturating_duration_since(now) <= SESSION_WARNING;
+    }
+
+    if limit.on_unsubmitted == UnsubmittedPolicy::Prompt {
+        if matches!(app.phase, AppPhase::Solved) {
+            complete_puzzle(app);
+        }
+        if matches!(app.phase, AppPhase::Email) {
+            if let Some(limit) = app.session_limit.as_mut() {
+                limit.grace_until = Some(now + SESSION_SUBMIT_GRACE);
             }
-            true
+            app.email.set_status(format!(
+                "Time's up! Confirm your invite within {}s.",
+                SESSION_SUBMIT_GRACE.as_secs()
+            ));
+            return true;
         }
-        KeyCode::Enter | KeyCode::Char(' ') => {
-            activate_puzzle_focus(app);
-            true
+    }
+
+    app.should_quit = true;
+    true
+}
+
+/// Ends a session that has seen no input for the idle window. The puzzle and
+/// email phases show "Session timed out" for `IDLE_NOTICE` first, so a player
+/// who walked away mid-task sees why the connection closed.
+fn tick_idle_timeout(app: &mut App, now: Instant) -> bool {
+    let Some(idle) = app.idle.as_mut() else {
+        return false;
+    };
+
+    if let Some(quit_at) = idle.quit_at {
+        if now >= quit_at {
+            app.should_quit = true;
+            return true;
         }
-        KeyCode::F(12) if app.debug => {
-            if let Some(path) = shortest_solution(app.puzzle.current, app.puzzle.target) {
-                for press in &path {
-                    app.puzzle.current = press_indicator(app.puzzle.current, *press);
+        return false;
+    }
+
+    if now.saturating_duration_since(idle.last_input) < idle.window {
+        return false;
+    }
+
+    match app.phase {
+        AppPhase::Puzzle => app.puzzle.set_status("Session timed out."),
+        AppPhase::Email => app.email.set_status("Session timed out."),
+        _ => {
+            app.should_quit = true;
+            return true;
+        }
+    }
+    idle.quit_at = Some(now + IDLE_NOTICE);
+    true
+}
+
+/// Restarts the idle window; a timed-out notice is withdrawn by the input.
+fn note_activity(app: &mut App, now: Instant) {
+    if let Some(idle) = app.idle.as_mut() {
+        idle.last_input = now;
+        idle.quit_at = None;
+    }
+}
+
+/// Footer prefix counting down the final stretch of a capped session.
+fn session_countdown(app: &App, now: Instant) -> Option<String> {
+    let limit = app.session_limit.as_ref()?;
+    if let Some(grace_until) = limit.grace_until {
+        return Some(format!(
+            "Time's up, closing in {}",
+            format_duration(grace_until.saturating_duration_since(now))
+        ));
+    }
+    let remaining = limit.deadline.saturating_duration_since(now);
+    (remaining <= SESSION_WARNING)
+        .then(|| format!("Session ends in {}", format_duration(remaining)))
+}
+
+fn play_replay(
+    stdout: &mut Stdout,
+    replay: &Replay,
+    puzzle: PuzzleState,
+    alternate_screen: bool,
+) -> io::Result<()> {
+    let _terminal = TerminalSession::enter(stdout, alternate_screen)?;
+    let mut app = App::new(false, replay.initial, replay.cycle);
+    app.puzzle = puzzle;
+    app.puzzle
+        .set_status(format!("Replay of seed {}. Esc exits.", replay.seed));
+
+    let mut applied = 0;
+    let mut last_step = Instant::now();
+    let mut needs_redraw = true;
+
+    loop {
+        if needs_redraw {
+            draw_app(stdout, &app)?;
+            needs_redraw = false;
+        }
+
+        if event::poll(Duration::from_millis(200))? {
+            match event::read()? {
+                Event::Key(key)
+                    if matches!(key.code, KeyCode::Esc | KeyCode::Enter)
+                        || (key.code == KeyCode::Char('c')
+                            && key.modifiers.contains(KeyModifiers::CONTROL)) =>
+                {
+                    return Ok(());
                 }
-                app.puzzle.moves_taken += path.len();
-                app.puzzle.status = format!("Debug solve used {} move(s).", path.len());
-            } else {
-                app.puzzle.status = "Debug solve did not find a valid route.".to_string();
+                Event::Resize(_, _) => needs_redraw = true,
+                _ => {}
             }
+        }
+
+        if applied < replay.moves.len() && last_step.elapsed() >= REPLAY_STEP {
+            let press = replay.moves[applied];
+            app.puzzle.focus = PuzzleFocus::Indicator(press);
+            app.puzzle.current =
+                press_indicator(app.puzzle.current, press, replay.cycle, replay.rule);
+            app.puzzle.moves_taken += 1;
+            app.puzzle.distance_to_target = solution_depth(
+                app.puzzle.current,
+                app.puzzle.target,
+                replay.cycle,
+                replay.rule,
+            )
+            .unwrap_or(0);
+            applied += 1;
+            app.puzzle.set_status(if applied < replay.moves.len() {
+                format!(
+                    "Replay: pressed indicator {} ({applied}/{}).",
+                    press + 1,
+                    replay.moves.len()
+                )
+            } else if app.puzzle.current == app.puzzle.target {
+                "Replay complete: target reached. Press Esc to exit.".to_string()
+            } else {
+                "Replay complete, but the target was not reached.".to_string()
+            });
+            last_step = Instant::now();
+            needs_redraw = true;
+        }
+    }
+}
 
-            if app.puzzle.current == app.puzzle.target {
-                transition_to_email(app);
+/// One combination of the `--selftest` matrix.
+struct SelftestCase {
+    cycle: ColorCycle,
+    rule: PressRule,
+    start: [NodeColor; INDICATOR_COUNT],
+    /// Presses from `start` to the target; ignored with a `difficulty`.
+    scramble: usize,
+    max_moves_over_optimal: Option<usize>,
+    layout: IndicatorLayout,
+    /// Generates the puzzle as the difficulty menu does.
+    difficulty: Option<Difficulty>,
+    /// Sets `BOAAI_MOVE_CAP` to exactly the optimal press count.
+    tight_move_cap: bool,
+}
+
+impl fmt::Display for SelftestCase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cycle: Vec<&str> = NodeColor::ALL
+            .into_iter()
+            .filter(|color| self.cycle.contains(*color))
+            .map(NodeColor::as_str)
+            .collect();
+        let start: Vec<&str> = self.start.into_iter().map(NodeColor::as_str).collect();
+        write!(
+            f,
+            "colors={} rule={} start={} scramble={} cap={} layout={}",
+            cycle.join(","),
+            self.rule.name(),
+            start.join(","),
+            self.difficulty
+                .map_or(self.scramble.to_string(), |difficulty| difficulty
+                    .name()
+                    .to_string()),
+            self.max_moves_over_optimal
+                .map_or("none".to_string(), |cap| cap.to_string()),
+            match self.layout {
+                IndicatorLayout::Row => "row",
+                IndicatorLayout::Ring => "ring",
             }
-            true
+        )?;
+        if self.tight_move_cap {
+            write!(f, " move_cap=optimal")?;
         }
-        KeyCode::Esc => {
-            app.should_quit = true;
-            true
+        Ok(())
+    }
+}
+
+/// Builds an `App` for one combination, generates a puzzle, checks the solver
+/// agrees with the generator, renders it, then plays the solver's presses
+/// through the real key handling and expects the solve to be accepted.
+fn run_selftest_case(case: &SelftestCase, seed: u64) -> Result<(), String> {
+    // Built-in defaults, so only the case decides the outcome.
+    let mut app = App::with_config(&Config::default(), case.start, case.cycle);
+    app.rule = case.rule;
+    app.max_moves_over_optimal = case.max_moves_over_optimal;
+    app.layout = case.layout;
+    app.difficulty = case.difficulty;
+
+    let (initial, target, scramble) = match case.difficulty {
+        Some(difficulty) => {
+            let puzzle =
+                difficulty_puzzle_state(seed, case.start, case.cycle, case.rule, None, difficulty);
+            // The scramble runs forward from the target, so undoing it can
+            // take more presses than were applied; only reachability holds.
+            (puzzle.initial, puzzle.target, None)
         }
-        _ => false,
+        None => {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let (target, _) = generate_target_with_presses(
+                &mut rng,
+                case.start,
+                case.cycle,
+                case.rule,
+                case.scramble,
+            );
+            (case.start, target, Some(case.scramble))
+        }
+    };
+    app.puzzle =
+        puzzle_state_for(initial, target, case.cycle, case.rule).map_err(|e| e.to_string())?;
+    let path = shortest_solution(initial, target, case.cycle, case.rule)
+        .ok_or("solver found no path to a generated target")?;
+    if let Some(scramble) = scramble.filter(|&scramble| path.len() > scramble) {
+        return Err(format!(
+            "solver needed {} presses for a {scramble}-press scramble",
+            path.len()
+        ));
+    }
+    if case.tight_move_cap {
+        app.move_cap = Some(path.len());
+    }
+    drive_keys(
+        &mut app,
+        path.iter().map(|&index| digit_key(index)),
+        &mut io::sink(),
+        120,
+        40,
+    )
+    .map_err(|e| e.to_string())?;
+    if app.puzzle.current != target || matches!(app.phase, AppPhase::Puzzle) {
+        return Err("replaying the optimal presses did not complete the puzzle".to_string());
     }
+    Ok(())
 }
 
-fn activate_puzzle_focus(app: &mut App) {
-    match app.puzzle.focus {
-        PuzzleFocus::Indicator(index) => {
-            app.puzzle.current = press_indicator(app.puzzle.current, index);
-            app.puzzle.moves_taken += 1;
-            app.puzzle.status = format!("Pressed indicator {}.", index + 1);
+/// The `1`-`6` key that presses indicator `index`.
+fn digit_key(index: usize) -> KeyEvent {
+    let digit = char::from_digit(index as u32 + 1, 10).expect("indicator numbers are one digit");
+    KeyEvent::new(KeyCode::Char(digit), KeyModifiers::NONE)
+}
+
+/// Runs `keys` through `handle_key`, rendering every changed frame into `out`
+/// at a fixed size, for the self-test and end-to-end tests. Stops early once
+/// the app quits. This is not `main`'s loop: it never calls `tick_app`, so no
+/// time passes for timers, flashes or animations, and it skips the phase log,
+/// resize settling and the pending bell, clipboard and suspend actions.
+fn drive_keys(
+    app: &mut App,
+    keys: impl IntoIterator<Item = KeyEvent>,
+    out: &mut impl Write,
+    cols: u16,
+    rows: u16,
+) -> io::Result<()> {
+    draw_frame(out, app, cols, rows)?;
+    for key in keys {
+        if app.should_quit {
+            break;
         }
-        PuzzleFocus::Action(0) => {
-            if let Some(path) = shortest_solution(app.puzzle.current, app.puzzle.target) {
-                if let Some(first) = path.first() {
-                    app.puzzle.status = format!("Hint: Haha, there is no hint. But if there were, it would be think outside the terminal.");
+        if handle_key(app, key)? {
+            draw_frame(out, app, cols, rows)?;
+        }
+    }
+    Ok(())
+}
+
+/// `--selftest`: runs `run_selftest_case` over color sets, press rules, start states,
+/// scramble depths, move caps and layouts, plus each difficulty with and
+/// without a `BOAAI_MOVE_CAP` at the optimal count, printing one line per combination.
+/// Panics are caught and reported as failures. Returns whether all passed.
+fn selftest(start: [NodeColor; INDICATOR_COUNT], cycle: ColorCycle) -> bool {
+    let mut cycles = vec![cycle];
+    for spec in ["OFF,GREEN,BLUE", "OFF,BLUE,PURPLE,WHITE"] {
+        let subset = ColorCycle::parse(spec).expect("built-in color set");
+        if !cycles.contains(&subset) {
+            cycles.push(subset);
+        }
+    }
+
+    let mut cases = Vec::new();
+    for cycle in cycles {
+        for rule in PressRule::ALL {
+            let starts = [
+                START_STATE,
+                press_indicator(START_STATE, 2, cycle, rule),
+                if cycle.check_state(start).is_ok() {
+                    start
                 } else {
-                    app.puzzle.status = "State already matches target.".to_string();
+                    START_STATE
+                },
+            ];
+            for (start_index, start) in starts.into_iter().enumerate() {
+                for scramble in [1, 3, 6, 10] {
+                    for max_moves_over_optimal in [None, Some(0), Some(2)] {
+                        for layout in [IndicatorLayout::Row, IndicatorLayout::Ring] {
+                            let case = SelftestCase {
+                                cycle,
+                                rule,
+                                start,
+                                scramble,
+                                max_moves_over_optimal,
+                                layout,
+                                difficulty: None,
+                                tight_move_cap: false,
+                            };
+                            let seed = (start_index * 100 + scramble) as u64;
+                            cases.push((case, seed));
+                        }
+                    }
+                }
+            }
+            // Difficulty-menu puzzles, with and without a move cap at the
+            // optimal count.
+            for (index, difficulty) in Difficulty::ALL.into_iter().enumerate() {
+                for tight_move_cap in [false, true] {
+                    let case = SelftestCase {
+                        cycle,
+                        rule,
+                        start: START_STATE,
+                        scramble: difficulty.presses(),
+                        max_moves_over_optimal: None,
+                        layout: IndicatorLayout::Row,
+                        difficulty: Some(difficulty),
+                        tight_move_cap,
+                    };
+                    cases.push((case, 1000 + index as u64));
                 }
-            } else {
-                app.puzzle.status = "No hint available from this state.".to_string();
             }
         }
-        PuzzleFocus::Action(1) => {
-            app.puzzle.current = app.puzzle.initial;
-            app.puzzle.moves_taken = 0;
-            app.puzzle.status = "Puzzle reset to original generated state.".to_string();
+    }
+
+    panic::set_hook(Box::new(|_| {}));
+    let (mut passed, mut failed) = (0, 0);
+    for (case, seed) in cases {
+        let outcome = panic::catch_unwind(AssertUnwindSafe(|| run_selftest_case(&case, seed)))
+            .unwrap_or_else(|payload| Err(format!("panicked: {}", panic_message(&payload))));
+        match outcome {
+            Ok(()) => {
+                passed += 1;
+                println!("PASS {case}");
+            }
+            Err(reason) => {
+                failed += 1;
+                println!("FAIL {case}: {reason}");
+            }
         }
-        PuzzleFocus::Action(2) => {
-            app.puzzle.show_rules = !app.puzzle.show_rules;
-            app.puzzle.status = if app.puzzle.show_rules {
-                "Rules expanded.".to_string()
-            } else {
-                "Rules collapsed.".to_string()
-            };
+    }
+    let _ = panic::take_hook();
+
+    println!("selftest: {passed} passed, {failed} failed");
+    failed == 0
+}
+
+fn panic_message(payload: &Box<dyn Any + Send>) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+/// Renders `frames` frames of every phase into memory at a fixed 120x40 size
+/// and prints one `key=value` line per phase.
+fn bench_render(frames: u32) -> io::Result<()> {
+    let phases = [
+        ("puzzle", AppPhase::Puzzle),
+        ("solved", AppPhase::Solved),
+        ("email", AppPhase::Email),
+        ("submitted", AppPhase::Submitted),
+        ("victory", AppPhase::Victory),
+        ("menu", AppPhase::Menu),
+        ("drills", AppPhase::Drills),
+        ("gameover", AppPhase::GameOver),
+        ("leaderboard", AppPhase::Leaderboard),
+        ("stats", AppPhase::Stats),
+        ("out of moves", AppPhase::OutOfMoves),
+    ];
+
+    for (name, phase) in phases {
+        let mut app = App::with_config(&Config::default(), START_STATE, ColorCycle::FULL);
+        app.puzzle = new_puzzle_state_from_rng(
+            &mut StdRng::seed_from_u64(0),
+            START_STATE,
+            ColorCycle::FULL,
+            PressRule::Classic,
+        );
+        app.phase = phase;
+        app.email.email = "player@example.com".to_string();
+        app.submitted_email = Some(app.email.email.clone());
+
+        let mut buffer = Vec::new();
+        let started = Instant::now();
+        for _ in 0..frames {
+            buffer.clear();
+            draw_frame(&mut buffer, &app, 120, 40)?;
+        }
+        let secs = started.elapsed().as_secs_f64();
+        let fps = if secs > 0.0 {
+            f64::from(frames) / secs
+        } else {
+            0.0
+        };
+
+        println!(
+            "phase={name} frames={frames} bytes_per_frame={} secs={secs:.4} fps={fps:.1}",
+            buffer.len()
+        );
+    }
+
+    Ok(())
+}
+
+fn show_splash_screen(stdout: &mut Stdout, seconds: u64, theme: &Theme) -> io::Result<()> {
+    let (cols, rows) = terminal::size().unwrap_or((120, 40));
+    let raw_logo_lines: Vec<String> = SPLASH_LOGO
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.trim_end().to_string())
+        .collect();
+
+    let common_indent = raw_logo_lines
+        .iter()
+        .map(|line| line.chars().take_while(|c| *c == ' ').count())
+        .min()
+        .unwrap_or(0);
+
+    let logo_lines: Vec<String> = raw_logo_lines
+        .iter()
+        .map(|line| line.chars().skip(common_indent).collect::<String>())
+        .collect();
+
+    let block_width = logo_lines
+        .iter()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0) as u16;
+
+    execute!(
+        stdout,
+        Clear(ClearType::All),
+        MoveTo(0, 0),
+        Bg(theme.background),
+        cursor::Hide
+    )?;
+
+    let block_height = logo_lines.len() as u16 + 2;
+    let start_x = cols.saturating_sub(block_width) / 2;
+    let start_y = rows.saturating_sub(block_height) / 2;
+
+    for (offset, line) in logo_lines.iter().enumerate() {
+        let color = if line.contains("HACK THE WORLD") {
+            theme.text
+        } else {
+            theme.border
+        };
+
+        queue!(
+            stdout,
+            MoveTo(start_x, start_y + offset as u16),
+            Fg(color),
+            Print(line)
+        )?;
+    }
+
+    let subheading = "ACCESS CHALLENGE INITIALIZING";
+    let subheading_x = start_x + block_width.saturating_sub(subheading.len() as u16) / 2;
+    queue!(
+        stdout,
+        MoveTo(subheading_x, start_y + logo_lines.len() as u16 + 1),
+        Fg(theme.accent),
+        SetAttribute(Attribute::Bold),
+        Print(subheading),
+        SetAttribute(Attribute::Reset),
+        ResetColor
+    )?;
+
+    if let Some(banner) = event_banner(SystemTime::now()) {
+        queue!(
+            stdout,
+            MoveTo(
+                start_x + block_width.saturating_sub(banner.len() as u16) / 2,
+                start_y + logo_lines.len() as u16 + 3
+            ),
+            Fg(theme.text),
+            Print(banner),
+            ResetColor
+        )?;
+    }
+
+    stdout.flush()?;
+    // Raw mode lets a single keypress end the wait, and reading that key here
+    // keeps it from reaching the first screen afterwards.
+    terminal::enable_raw_mode()?;
+    let waited = wait_for_keypress(Duration::from_secs(seconds));
+    terminal::disable_raw_mode()?;
+    waited?;
+    execute!(
+        stdout,
+        Clear(ClearType::All),
+        MoveTo(0, 0),
+        ResetColor,
+        cursor::Show
+    )?;
+    Ok(())
+}
+
+/// Blocks until `timeout` passes or a key is pressed, consuming that key.
+fn wait_for_keypress(timeout: Duration) -> io::Result<()> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() || !event::poll(remaining)? {
+            return Ok(());
+        }
+        if let Event::Key(_) = event::read()? {
+            return Ok(());
+        }
+    }
+}
+
+fn draw_app(stdout: &mut impl Write, app: &App) -> io::Result<()> {
+    let (cols, rows) = terminal::size()?;
+    draw_frame(stdout, app, cols, rows)
+}
+
+/// Renders one full frame for a `cols` x `rows` terminal into any writer.
+fn draw_frame(stdout: &mut impl Write, app: &App, cols: u16, rows: u16) -> io::Result<()> {
+    queue!(
+        stdout,
+        MoveTo(0, 0),
+        Clear(ClearType::All),
+        Bg(app.theme.background)
+    )?;
+
+    let Some(FrameLayout {
+        x: frame_x,
+        width: frame_width,
+        body_y,
+        body_height,
+    }) = frame_layout(cols, rows)
+    else {
+        draw_resize_message(stdout, cols, rows, &app.theme)?;
+        stdout.flush()?;
+        return Ok(());
+    };
+    let header_y = 1;
+
+    DIMMED.with(|dimmed| dimmed.set(app.help.is_some()));
+    draw_header_bar(stdout, frame_x, header_y, frame_width, app)?;
+    // The flash is drawn per frame and switched off again right away, so a
+    // frame drawn after it ends (or an exit mid-flash) is never left inverted.
+    if app.flash_until.is_some_and(|until| Instant::now() < until) {
+        let fill = " ".repeat(frame_width as usize);
+        queue!(
+            stdout,
+            Fg(app.theme.success),
+            SetAttribute(Attribute::Reverse)
+        )?;
+        for row in body_y..body_y + body_height {
+            queue!(stdout, MoveTo(frame_x, row), Print(&fill))?;
+        }
+        queue!(stdout, SetAttribute(Attribute::NoReverse))?;
+    }
+    draw_box(
+        stdout,
+        frame_x,
+        body_y,
+        frame_width,
+        body_height,
+        if matches!(app.phase, AppPhase::Solved) {
+            app.theme.success
+        } else {
+            app.theme.border
+        },
+    )?;
+
+    let ViewColumns { x, width, sides } = view_columns(frame_x, frame_width);
+    match app.phase {
+        AppPhase::Puzzle | AppPhase::Solved | AppPhase::OutOfMoves => {
+            draw_puzzle_view(stdout, x, body_y, width, body_height, sides, app)?
+        }
+        AppPhase::Email => draw_email_view(stdout, x, body_y, width, body_height, app)?,
+        AppPhase::Submitted => draw_submitted_view(stdout, x, body_y, width, body_height, app)?,
+        AppPhase::Victory => draw_victory_view(stdout, x, body_y, width, app)?,
+        AppPhase::Menu => draw_difficulty_menu(stdout, x, body_y, width, app)?,
+        AppPhase::Drills => draw_drill_menu(stdout, x, body_y, width, app)?,
+        AppPhase::GameOver => draw_game_over_view(stdout, x, body_y, width, app)?,
+        AppPhase::Leaderboard => draw_leaderboard_view(stdout, x, body_y, width, body_height, app)?,
+        AppPhase::Stats => draw_stats_view(stdout, x, body_y, width, app)?,
+    }
+
+    draw_footer(stdout, frame_x, frame_width, rows, app)?;
+    DIMMED.with(|dimmed| dimmed.set(false));
+    if let Some(scroll) = app.help {
+        draw_help_overlay(
+            stdout,
+            frame_x,
+            body_y,
+            frame_width,
+            body_height,
+            scroll,
+            app,
+        )?;
+    }
+    queue!(stdout, ResetColor, SetAttribute(Attribute::Reset))?;
+    stdout.flush()?;
+    Ok(())
+}
+
+fn frame_layout(cols: u16, rows: u16) -> Option<FrameLayout> {
+    if cols < 78 || rows < 24 {
+        return None;
+    }
+    let room = cols.saturating_sub(4);
+    let width = if room >= WIDE_FRAME_WIDTH {
+        WIDE_FRAME_WIDTH
+    } else {
+        room.min(FRAME_MAX_WIDTH)
+    };
+    let body_y = 5;
+    Some(FrameLayout {
+        x: cols.saturating_sub(width) / 2,
+        width,
+        body_y,
+        body_height: rows.saturating_sub(body_y + 3),
+    })
+}
+
+fn view_columns(x: u16, width: u16) -> ViewColumns {
+    if width < WIDE_FRAME_WIDTH {
+        return ViewColumns {
+            x,
+            width,
+            sides: None,
+        };
+    }
+    ViewColumns {
+        x: x + SIDE_COLUMN_WIDTH,
+        width: width - 2 * SIDE_COLUMN_WIDTH,
+        sides: Some(SideColumns {
+            left_x: x + 2,
+            right_x: x + width - SIDE_COLUMN_WIDTH,
+            width: SIDE_COLUMN_WIDTH - 2,
+        }),
+    }
+}
+
+/// Width of the move-history panel in the top right of the puzzle body, or
+/// `None` when the frame is too narrow to fit it beside the state lines.
+fn history_panel_width(width: u16) -> Option<u16> {
+    let room = width.saturating_sub(6 + 62);
+    (room >= 20).then(|| room.min(HISTORY_PANEL_WIDTH))
+}
+
+/// One history row: the move number, the pressed indicator, and the color
+/// steps it applied to each position (e.g. `#4  3  -1 +1 +2 +1 -1 +3`).
+fn history_entry(move_number: usize, index: usize, cycle: ColorCycle, rule: PressRule) -> String {
+    let steps: Vec<String> = (0..INDICATOR_COUNT)
+        .map(|position| format!("{:>2}", press_effect(index, position, cycle, rule)))
+        .collect();
+    format!("#{move_number:<3} {}  {}", index + 1, steps.join(" "))
+}
+
+/// The color steps pressing `index` applies at `position`, as `+n`, `-n`
+/// (the shorter way round the cycle) or `0`.
+fn press_effect(index: usize, position: usize, cycle: ColorCycle, rule: PressRule) -> String {
+    let clockwise = (position + INDICATOR_COUNT - index) % INDICATOR_COUNT;
+    let distance = clockwise.min(INDICATOR_COUNT - clockwise);
+    match press_delta(distance, INDICATOR_COUNT, cycle, rule) % cycle.len() {
+        0 => "0".to_string(),
+        steps if steps * 2 > cycle.len() => format!("-{}", cycle.len() - steps),
+        steps => format!("+{steps}"),
+    }
+}
+
+/// Boxed list of the most recent presses, newest on top.
+fn draw_history_panel(
+    stdout: &mut impl Write,
+    x: u16,
+    y: u16,
+    width: u16,
+    height: u16,
+    puzzle: &PuzzleState,
+    theme: &Theme,
+) -> io::Result<()> {
+    draw_box(stdout, x, y, width, height, theme.border)?;
+    queue!(
+        stdout,
+        MoveTo(x + 2, y),
+        Fg(theme.border),
+        Print(" History ")
+    )?;
+    let inner = width.saturating_sub(4) as usize;
+    let rows = height.saturating_sub(2) as usize;
+    if puzzle.recent_presses.is_empty() {
+        return queue!(
+            stdout,
+            MoveTo(x + 2, y + 1),
+            Print(trim_to_width("No presses yet", inner))
+        );
+    }
+    for (row, (back, &index)) in puzzle
+        .recent_presses
+        .iter()
+        .rev()
+        .enumerate()
+        .take(rows)
+        .enumerate()
+    {
+        let move_number = puzzle.moves.len() - back;
+        queue!(
+            stdout,
+            MoveTo(x + 2, y + 1 + row as u16),
+            Fg(if row == 0 { theme.text } else { theme.border }),
+            Print(trim_to_width(
+                &history_entry(move_number, index, puzzle.cycle, puzzle.rule),
+                inner
+            ))
+        )?;
+    }
+    Ok(())
+}
+
+/// Places the indicators for the puzzle view body. The ring puts them on a
+/// hexagon so neighbors and opposites are spatially obvious; it falls back to
+/// the row when the body is too short to also fit the action row below, or
+/// when `INDICATOR_COUNT` is not six.
+fn indicator_grid(
+    x: u16,
+    width: u16,
+    top: u16,
+    bottom: u16,
+    layout: IndicatorLayout,
+) -> IndicatorGrid {
+    let cell_width = (width
+        .saturating_sub(8)
+        .saturating_sub(2 * (INDICATOR_COUNT as u16 - 1))
+        / INDICATOR_COUNT as u16)
+        .clamp(10, 16);
+
+    if layout == IndicatorLayout::Ring && top + 9 + 3 < bottom && 3 * cell_width + 4 <= width {
+        let center = x + width / 2;
+        let inner_left = center - cell_width - 1;
+        let inner_right = center + 1;
+        let outer_left = inner_left - cell_width / 2 - 1;
+        let outer_right = inner_right + cell_width / 2 + 1;
+        // Clockwise from the top left, so opposite indicators face each other.
+        let hexagon = [
+            (inner_left, top),
+            (inner_right, top),
+            (outer_right, top + 3),
+            (inner_right, top + 6),
+            (inner_left, top + 6),
+            (outer_left, top + 3),
+        ];
+        if let Ok(cells) = hexagon.as_slice().try_into() {
+            return IndicatorGrid {
+                cells,
+                width: cell_width,
+                below: top + 9,
+                ring: true,
+            };
+        }
+    }
+
+    let gap = 2;
+    let span = cell_width * INDICATOR_COUNT as u16 + gap * 3;
+    let start_x = x + width.saturating_sub(span) / 2;
+    let mut cells = [(0, top); INDICATOR_COUNT];
+    for (index, cell) in cells.iter_mut().enumerate() {
+        cell.0 = start_x + index as u16 * (cell_width + gap);
+    }
+    IndicatorGrid {
+        cells,
+        width: cell_width,
+        below: top + 3,
+        ring: false,
+    }
+}
+
+fn draw_resize_message(
+    stdout: &mut impl Write,
+    cols: u16,
+    rows: u16,
+    theme: &Theme,
+) -> io::Result<()> {
+    let line_1 = "Terminal size too small for puzzle UI.";
+    let line_2 = "Resize to at least 78x24.";
+    let x_1 = cols.saturating_sub(line_1.len() as u16) / 2;
+    let x_2 = cols.saturating_sub(line_2.len() as u16) / 2;
+    let y = rows / 2;
+
+    queue!(
+        stdout,
+        MoveTo(x_1, y.saturating_sub(1)),
+        Fg(theme.border),
+        Print(line_1),
+        MoveTo(x_2, y + 1),
+        Fg(theme.accent),
+        SetAttribute(Attribute::Bold),
+        Print(line_2),
+        SetAttribute(Attribute::Reset),
+        ResetColor
+    )?;
+    Ok(())
+}
+
+/// Widest the moves cell can be while the header still fits the narrowest
+/// frame; longer text is cut so the dividers never move.
+const HEADER_MOVES_WIDTH: usize = 21;
+
+/// Header text for the moves counter and the solve timer beside it. Past
+/// the optimal count the label gives way to the overage.
+fn moves_segment(app: &App) -> String {
+    let puzzle = &app.puzzle;
+    let clock = format_duration(solve_clock(puzzle));
+    if let Some(cap) = app.move_cap {
+        let left = cap.saturating_sub(puzzle.moves_taken);
+        return format!("{left}/{cap} left {clock}");
+    }
+    match puzzle.moves_taken.checked_sub(puzzle.optimal_moves) {
+        Some(over) if over > 0 => format!(
+            "{}/{} (+{over} over) {clock}",
+            puzzle.moves_taken, puzzle.optimal_moves
+        ),
+        _ => format!(
+            "moves {}/{} {clock}",
+            puzzle.moves_taken, puzzle.optimal_moves
+        ),
+    }
+}
+
+/// Grey while within the optimal count, the accent once past it.
+fn moves_color(puzzle: &PuzzleState, theme: &Theme) -> Color {
+    if puzzle.moves_taken > puzzle.optimal_moves {
+        theme.accent
+    } else {
+        theme.border
+    }
+}
+
+/// Time spent on the current puzzle, frozen once it is solved.
+fn solve_clock(puzzle: &PuzzleState) -> Duration {
+    puzzle
+        .solved_in
+        .unwrap_or_else(|| puzzle.started_at.elapsed())
+}
+
+/// Repaints only the moves/timer cell of the header, so the timer can tick
+/// between full redraws without clearing the screen.
+fn draw_header_clock(stdout: &mut impl Write, app: &App, cols: u16, rows: u16) -> io::Result<()> {
+    let Some(frame) = frame_layout(cols, rows) else {
+        return Ok(());
+    };
+    // Cell follows the "Boa AI" (12) and tab label (16) cells and their dividers.
+    queue!(
+        stdout,
+        MoveTo(frame.x + 1 + 12 + 1 + 16 + 1, 2),
+        Fg(moves_color(&app.puzzle, &app.theme)),
+        Print(center_text(&moves_segment(app), HEADER_MOVES_WIDTH)),
+        ResetColor
+    )?;
+    stdout.flush()
+}
+
+fn draw_header_bar(
+    stdout: &mut impl Write,
+    x: u16,
+    y: u16,
+    width: u16,
+    app: &App,
+) -> io::Result<()> {
+    let tab_label = match (app.phase, &app.campaign) {
+        (AppPhase::Puzzle, _) if app.active_drill.is_some() => "drill".to_string(),
+        (AppPhase::Puzzle, _) if app.sandbox.is_some() => {
+            format!("sandbox {} solved", app.sandbox.unwrap_or_default())
+        }
+        (AppPhase::Puzzle, _) if app.survival.is_some() => format!(
+            "survival {}",
+            app.survival.as_ref().map_or(0, |run| run.cleared) + 1
+        ),
+        (AppPhase::Puzzle, Some(campaign)) => {
+            format!("stage {}/{}", campaign.stage + 1, campaign.stages)
+        }
+        (AppPhase::Puzzle, None) => match app.difficulty {
+            Some(difficulty) => format!("{} puzzle", difficulty.name()),
+            None => "puzzle node".to_string(),
+        },
+        (AppPhase::Menu, _) => "difficulty".to_string(),
+        (AppPhase::Solved, _) => "solved!".to_string(),
+        (AppPhase::Email, _) => "invite form".to_string(),
+        (AppPhase::Submitted, _) => "request sent".to_string(),
+        (AppPhase::Victory, _) => "victory".to_string(),
+        (AppPhase::Drills, _) => "drills".to_string(),
+        (AppPhase::GameOver, _) => "game over".to_string(),
+        (AppPhase::Leaderboard, _) => "leaderboard".to_string(),
+        (AppPhase::Stats, _) => "stats".to_string(),
+        (AppPhase::OutOfMoves, _) => "out of moves".to_string(),
+    };
+    let tab_label = if plain_mode() {
+        format!("*{tab_label}*")
+    } else {
+        tab_label
+    };
+
+    let segments = [
+        center_text("Boa AI", 12),
+        center_text(&tab_label, 16),
+        center_text(&moves_segment(app), HEADER_MOVES_WIDTH),
+        center_text("event access", 20),
+    ];
+
+    let content_width = segments
+        .iter()
+        .map(|segment| display_width(segment))
+        .sum::<usize>()
+        + segments.len()
+        - 1;
+    if content_width as u16 + 2 > width {
+        return draw_box(stdout, x, y, width, 3, app.theme.border);
+    }
+
+    let mut top_border = String::from("┌");
+    let mut bottom_border = String::from("└");
+    for (index, segment) in segments.iter().enumerate() {
+        top_border.push_str(&"─".repeat(display_width(segment)));
+        bottom_border.push_str(&"─".repeat(display_width(segment)));
+        if index < segments.len() - 1 {
+            top_border.push('┬');
+            bottom_border.push('┴');
+        }
+    }
+    top_border.push('┐');
+    bottom_border.push('┘');
+
+    queue!(
+        stdout,
+        MoveTo(x, y),
+        Fg(app.theme.border),
+        Print(top_border),
+        MoveTo(x, y + 2),
+        Print(bottom_border),
+        MoveTo(x, y + 1),
+        Print("│")
+    )?;
+
+    let mut cursor_x = x + 1;
+    for (index, segment) in segments.iter().enumerate() {
+        queue!(stdout, MoveTo(cursor_x, y + 1))?;
+        match index {
+            0 => {
+                queue!(
+                    stdout,
+                    Fg(app.theme.text),
+                    SetAttribute(Attribute::Bold),
+                    Print(segment),
+                    SetAttribute(Attribute::Reset),
+                    Fg(app.theme.border)
+                )?;
+            }
+            1 => {
+                queue!(
+                    stdout,
+                    Fg(app.theme.accent),
+                    SetAttribute(Attribute::Bold),
+                    Print(segment),
+                    SetAttribute(Attribute::Reset),
+                    Fg(app.theme.border)
+                )?;
+            }
+            2 => {
+                queue!(
+                    stdout,
+                    Fg(moves_color(&app.puzzle, &app.theme)),
+                    Print(segment),
+                    Fg(app.theme.border)
+                )?;
+            }
+            _ => {
+                queue!(stdout, Fg(app.theme.border), Print(segment))?;
+            }
+        }
+
+        cursor_x += display_width(segment) as u16;
+        if index < segments.len() - 1 {
+            queue!(stdout, MoveTo(cursor_x, y + 1), Print("│"))?;
+            cursor_x += 1;
+        }
+    }
+
+    queue!(stdout, MoveTo(cursor_x, y + 1), Print("│"), ResetColor)?;
+    Ok(())
+}
+
+fn draw_box(
+    stdout: &mut impl Write,
+    x: u16,
+    y: u16,
+    width: u16,
+    height: u16,
+    border_color: Color,
+) -> io::Result<()> {
+    if width < 2 || height < 2 {
+        return Ok(());
+    }
+
+    let horizontal = "─".repeat((width - 2) as usize);
+    queue!(
+        stdout,
+        Fg(border_color),
+        MoveTo(x, y),
+        Print(format!("┌{}┐", horizontal)),
+        MoveTo(x, y + height - 1),
+        Print(format!("└{}┘", horizontal))
+    )?;
+
+    for row in (y + 1)..(y + height - 1) {
+        queue!(
+            stdout,
+            MoveTo(x, row),
+            Print("│"),
+            MoveTo(x + width - 1, row),
+            Print("│")
+        )?;
+    }
+
+    queue!(stdout, ResetColor)?;
+    Ok(())
+}
+
+fn draw_colored_state_line(
+    stdout: &mut impl Write,
+    x: u16,
+    y: u16,
+    label: &str,
+    state: [NodeColor; INDICATOR_COUNT],
+    palette: Palette,
+    theme: &Theme,
+) -> io::Result<()> {
+    let mut cursor_x = x;
+    queue!(
+        stdout,
+        MoveTo(cursor_x, y),
+        Fg(theme.border),
+        Print(format!("{label}   ["))
+    )?;
+    cursor_x += (label.len() + 4) as u16;
+
+    for (index, color) in state.iter().enumerate() {
+        let token = color.state_token(palette);
+        let token_width = token.chars().count() as u16;
+        queue!(
+            stdout,
+            MoveTo(cursor_x, y),
+            Fg(color.term_color(palette)),
+            SetAttribute(Attribute::Bold),
+            Print(token),
+            SetAttribute(Attribute::Reset)
+        )?;
+        cursor_x += token_width;
+
+        if index < INDICATOR_COUNT - 1 {
+            queue!(stdout, MoveTo(cursor_x, y), Fg(theme.border), Print(" | "))?;
+            cursor_x += 3;
+        }
+    }
+
+    queue!(
+        stdout,
+        MoveTo(cursor_x, y),
+        Fg(theme.border),
+        Print("]"),
+        ResetColor
+    )?;
+
+    Ok(())
+}
+
+/// Compact side-by-side view of the live state and the bookmarked one.
+/// Bookmark positions that differ from the live state are drawn in their
+/// color; positions that still match are dimmed.
+fn draw_bookmark_line(
+    stdout: &mut impl Write,
+    x: u16,
+    y: u16,
+    current: [NodeColor; INDICATOR_COUNT],
+    bookmark: [NodeColor; INDICATOR_COUNT],
+    palette: Palette,
+    theme: &Theme,
+) -> io::Result<()> {
+    let live = current
+        .iter()
+        .map(|color| color.short())
+        .collect::<Vec<_>>()
+        .join(" ");
+    queue!(
+        stdout,
+        MoveTo(x, y),
+        Fg(theme.border),
+        Print(format!("Current  [{live}]   Mark ["))
+    )?;
+
+    for (index, (mark, now)) in bookmark.iter().zip(current).enumerate() {
+        if index > 0 {
+            queue!(stdout, Print(" "))?;
+        }
+        if *mark == now {
+            queue!(stdout, Fg(theme.border), Print(mark.short()))?;
+        } else {
+            queue!(
+                stdout,
+                Fg(mark.term_color(palette)),
+                SetAttribute(Attribute::Bold),
+                Print(mark.short()),
+                SetAttribute(Attribute::Reset)
+            )?;
+        }
+    }
+
+    queue!(stdout, Fg(theme.border), Print("]"), ResetColor)?;
+    Ok(())
+}
+
+/// Top row of the indicator grid in the puzzle view: below the title, the
+/// subtitle, the state lines and the par summary.
+fn indicator_row(body_y: u16) -> u16 {
+    body_y + 7
+}
+
+fn draw_puzzle_view(
+    stdout: &mut impl Write,
+    x: u16,
+    body_y: u16,
+    width: u16,
+    body_height: u16,
+    sides: Option<SideColumns>,
+    app: &App,
+) -> io::Result<()> {
+    let puzzle = &app.puzzle;
+    let solved = matches!(app.phase, AppPhase::Solved);
+    let bottom = body_y + body_height - 1;
+    let mut line = body_y + 1;
+
+    queue!(
+        stdout,
+        MoveTo(x + 3, line),
+        Fg(app.theme.text),
+        SetAttribute(Attribute::Bold),
+        Print("LATTICE NODE // ACCESS CHALLENGE"),
+        SetAttribute(Attribute::Reset),
+        MoveTo(x + 3, line + 1),
+        Fg(app.theme.border),
+        Print("6-button custom puzzle. Use only controls below.")
+    )?;
+
+    line += 3;
+    queue!(stdout, MoveTo(x + 3, line), Fg(app.theme.border))?;
+    draw_colored_state_line(
+        stdout,
+        x + 3,
+        line,
+        "Target",
+        shown_target(app, Instant::now()),
+        app.palette,
+        &app.theme,
+    )?;
+    if let Some(bookmark) = puzzle.bookmark {
+        draw_bookmark_line(
+            stdout,
+            x + 3,
+            line + 1,
+            puzzle.current,
+            bookmark,
+            app.palette,
+            &app.theme,
+        )?;
+    } else if app.palette == Palette::ColorBlind {
+        draw_colored_state_line(
+            stdout,
+            x + 3,
+            line + 1,
+            "Now   ",
+            puzzle.current,
+            app.palette,
+            &app.theme,
+        )?;
+    } else {
+        queue!(
+            stdout,
+            MoveTo(x + 3, line + 1),
+            Fg(app.theme.border),
+            Print(format!(
+                "Current  [{}]",
+                render_state(puzzle.current).to_ascii_uppercase()
+            ))
+        )?;
+    }
+    let panel_width = history_panel_width(width).filter(|_| sides.is_none());
+    queue!(
+        stdout,
+        MoveTo(x + 3, line + 2),
+        Fg(app.theme.border),
+        Print(trim_to_width_ellipsis(
+            &par_summary(puzzle),
+            width.saturating_sub(6 + panel_width.map_or(0, |panel| panel + 2)) as usize
+        ))
+    )?;
+    if let Some(sides) = sides {
+        // Full-height panels: the history on the left, the rules on the right.
+        let height = body_height.saturating_sub(2);
+        draw_history_panel(
+            stdout,
+            sides.left_x,
+            body_y + 1,
+            sides.width,
+            height,
+            puzzle,
+            &app.theme,
+        )?;
+        if puzzle.show_rules {
+            draw_box(
+                stdout,
+                sides.right_x,
+                body_y + 1,
+                sides.width,
+                height,
+                app.theme.border,
+            )?;
+            queue!(
+                stdout,
+                MoveTo(sides.right_x + 2, body_y + 1),
+                Fg(app.theme.border),
+                Print(" Rules ")
+            )?;
+            let inner = sides.width.saturating_sub(4);
+            let lines: Vec<String> = rules_lines(puzzle)
+                .iter()
+                .flat_map(|rule| wrap_words(rule, inner as usize))
+                .collect();
+            draw_rules_pane(
+                stdout,
+                sides.right_x + 2,
+                body_y + 2..body_y + height,
+                inner,
+                &lines,
+                puzzle.rules_scroll,
+                &app.theme,
+            )?;
+        }
+    } else if let Some(panel_width) = panel_width {
+        draw_history_panel(
+            stdout,
+            x + width - 3 - panel_width,
+            body_y + 1,
+            panel_width,
+            indicator_row(body_y) - (body_y + 1),
+            puzzle,
+            &app.theme,
+        )?;
+    }
+
+    let indicator_y = indicator_row(body_y);
+    let grid = indicator_grid(x, width, indicator_y, bottom, app.layout);
+
+    if indicator_y + 2 < bottom {
+        let finishing = if app.show_finishing_press && !solved {
+            finishing_presses(puzzle)
+        } else {
+            [false; INDICATOR_COUNT]
+        };
+        for (index, finishes) in finishing.into_iter().enumerate() {
+            let selected =
+                !solved && matches!(puzzle.focus, PuzzleFocus::Indicator(i) if i == index);
+            let color = puzzle.current[index];
+            let label = match app.palette {
+                Palette::Default => format!("{} {}", index + 1, color.as_str()),
+                Palette::ColorBlind => {
+                    format!("{} {} {}", index + 1, color.short(), color.glyph())
+                }
+            };
+            let (cell_x, cell_y) = grid.cells[index];
+            draw_button_with_border(
+                stdout,
+                cell_x,
+                cell_y,
+                grid.width,
+                &label,
+                selected,
+                color.term_color(app.palette),
+                if finishes {
+                    Some(app.theme.warning)
+                } else {
+                    (color == puzzle.target[index]).then_some(app.theme.success)
+                },
+                puzzle.locked[index],
+                &app.theme,
+            )?;
+            // What pressing the focused indicator would do here, in the top
+            // border.
+            if let (false, PuzzleFocus::Indicator(focused)) = (solved, puzzle.focus) {
+                if focused != index {
+                    let tag = format!(
+                        " {} ",
+                        press_effect(focused, index, puzzle.cycle, puzzle.rule)
+                    );
+                    queue!(
+                        stdout,
+                        MoveTo(
+                            cell_x + grid.width.saturating_sub(display_width(&tag) as u16) / 2,
+                            cell_y
+                        ),
+                        Fg(app.theme.accent),
+                        Print(tag),
+                        ResetColor
+                    )?;
+                }
+            }
+            // Drawn into the bottom border, which has room in both layouts.
+            if let Some(overlay) = target_overlay(puzzle, index) {
+                let overlay_width = display_width(&overlay) as u16;
+                queue!(
+                    stdout,
+                    MoveTo(
+                        cell_x + grid.width.saturating_sub(overlay_width) / 2,
+                        cell_y + 2
+                    ),
+                    Fg(Color::Grey),
+                    Print(overlay),
+                    ResetColor
+                )?;
+            }
+        }
+        for (connector_x, connector_y, piece) in grid.connectors() {
+            queue!(
+                stdout,
+                MoveTo(connector_x, connector_y),
+                Fg(app.theme.border),
+                Print(piece)
+            )?;
+        }
+    }
+
+    let action_y = grid.below + 1;
+    let actions = action_cells(x, width, action_y);
+    let action_labels = [
+        "Hint",
+        "Reset",
+        if puzzle.show_rules {
+            "Hide Rules"
+        } else {
+            "Show Rules"
+        },
+    ];
+
+    if action_y + 2 < bottom {
+        for (index, label) in action_labels.iter().enumerate() {
+            let selected = !solved && matches!(puzzle.focus, PuzzleFocus::Action(i) if i == index);
+            let (action_x, action_y) = actions[index];
+            draw_button(
+                stdout,
+                action_x,
+                action_y,
+                ACTION_WIDTH,
+                label,
+                selected,
+                app.theme.text,
+                &app.theme,
+            )?;
+        }
+    }
+
+    let status_y = action_y + 4;
+    if let Some(preview) = press_preview_line(app) {
+        if status_y <= bottom {
+            queue!(
+                stdout,
+                MoveTo(x + 3, status_y - 1),
+                Fg(app.theme.border),
+                SetAttribute(Attribute::Dim),
+                Print(trim_to_width_ellipsis(
+                    &preview,
+                    width.saturating_sub(6) as usize
+                )),
+                SetAttribute(Attribute::Reset)
+            )?;
+        }
+    }
+    if status_y < bottom {
+        // The share code for the starting board sits at the right end when
+        // there is room beside the status.
+        let code = format!("Code {}", encode_share_code(puzzle.initial));
+        let mut room = width.saturating_sub(6) as usize;
+        if room >= code.len() + 24 {
+            room -= code.len() + 2;
+            queue!(
+                stdout,
+                MoveTo(x + width - 3 - code.len() as u16, status_y),
+                Fg(app.theme.border),
+                Print(&code)
+            )?;
+        }
+        queue!(
+            stdout,
+            MoveTo(x + 3, status_y),
+            Fg(app.theme.accent),
+            Print(trim_to_width_ellipsis(&puzzle.status, room))
+        )?;
+    }
+
+    let mut rules_top = status_y + 2;
+    if let Some((_, path)) = puzzle
+        .revealed_path
+        .as_ref()
+        .filter(|(board, _)| !solved && *board == puzzle.current)
+    {
+        for line in path_lines(path, width.saturating_sub(6) as usize) {
+            if rules_top >= bottom {
+                break;
+            }
+            queue!(
+                stdout,
+                MoveTo(x + 3, rules_top),
+                Fg(app.theme.warning),
+                Print(line)
+            )?;
+            rules_top += 1;
+        }
+    }
+
+    if puzzle.show_rules && sides.is_none() {
+        draw_rules_pane(
+            stdout,
+            x + 3,
+            rules_top..bottom,
+            width.saturating_sub(6),
+            &rules_lines(puzzle),
+            puzzle.rules_scroll,
+            &app.theme,
+        )?;
+    }
+
+    if app.debug {
+        queue!(
+            stdout,
+            MoveTo(x + 3, bottom.saturating_sub(1)),
+            Fg(app.theme.border),
+            Print("Debug: press F12 for instant solve")
+        )?;
+    }
+
+    queue!(stdout, ResetColor)?;
+    Ok(())
+}
+
+/// The faint "what if" row under the actions: the board that pressing the
+/// focused indicator would leave. Nothing while an action is focused.
+fn press_preview_line(app: &App) -> Option<String> {
+    let puzzle = &app.puzzle;
+    let PuzzleFocus::Indicator(index) = puzzle.focus else {
+        return None;
+    };
+    if !app.press_preview || !matches!(app.phase, AppPhase::Puzzle) {
+        return None;
+    }
+    let after = press_indicator(puzzle.current, index, puzzle.cycle, puzzle.rule);
+    Some(format!(
+        "Preview, pressing {}: [{}]",
+        index + 1,
+        render_state(after).to_ascii_uppercase()
+    ))
+}
+
+/// Rules text in rows `top..bottom`, starting `scroll` lines in. An
+/// overflowing pane gives its last row to the scroll indicator.
+fn draw_rules_pane(
+    stdout: &mut impl Write,
+    x: u16,
+    rows: Range<u16>,
+    width: u16,
+    lines: &[String],
+    scroll: usize,
+    theme: &Theme,
+) -> io::Result<()> {
+    let max = width as usize;
+    let top = rows.start;
+    let rows = rows.len();
+    let overflow = lines.len() > rows;
+    let shown = if overflow {
+        rows.saturating_sub(1)
+    } else {
+        rows
+    };
+    let first = scroll.min(lines.len().saturating_sub(shown));
+    for (rules_y, rule) in (top..).zip(lines.iter().skip(first).take(shown)) {
+        queue!(
+            stdout,
+            MoveTo(x, rules_y),
+            Fg(theme.border),
+            Print(trim_to_width(rule, max))
+        )?;
+    }
+    if overflow && rows > 0 {
+        let indicator = format!(
+            "{}{} lines {}-{} of {}  PgUp/PgDn on Show Rules",
+            if first > 0 { "↑" } else { " " },
+            if first + shown < lines.len() {
+                "↓"
+            } else {
+                " "
+            },
+            first + 1,
+            first + shown,
+            lines.len()
+        );
+        queue!(
+            stdout,
+            MoveTo(x, top + shown as u16),
+            Fg(Color::Grey),
+            Print(trim_to_width(&indicator, max))
+        )?;
+    }
+    Ok(())
+}
+
+/// Splits `text` at spaces into lines of at most `width` columns. A word
+/// longer than `width` is cut.
+fn wrap_words(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && display_width(&line) + 1 + display_width(word) > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    lines.push(line);
+    lines
+        .into_iter()
+        .map(|line| trim_to_width(&line, width))
+        .collect()
+}
+
+/// The rules pane text for the puzzle's color cycle and press rule.
+fn rules_lines(puzzle: &PuzzleState) -> Vec<String> {
+    let cycle_names: Vec<&str> = puzzle.cycle.colors().map(NodeColor::as_str).collect();
+    let color_map = cycle_names
+        .iter()
+        .enumerate()
+        .map(|(index, name)| format!("{name}={index}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    match puzzle.rule {
+        PressRule::Classic => vec![
+            format!(
+                "1) Pressed button advances by +2 color steps ({}>OFF)",
+                cycle_names.join(">")
+            ),
+            "2) Adjacent buttons (distance 1) advance by +1 step".to_string(),
+            "3) Distance-2 buttons move backward by 1 step".to_string(),
+            "4) Opposite button (distance 3) advances by +3 steps".to_string(),
+            format!("Color map: {color_map}"),
+        ],
+        PressRule::Lights => vec![
+            format!(
+                "1) Pressed button advances by +1 color step ({}>OFF)",
+                cycle_names.join(">")
+            ),
+            "2) Adjacent buttons (distance 1) advance by +1 step".to_string(),
+            "3) Every other button stays as it is".to_string(),
+            format!("Color map: {color_map}"),
+        ],
+    }
+}
+
+/// Advisory note drawn above the email field as the address is typed; the
+/// address is only checked for real on Confirm. Nothing for an empty field.
+fn email_feedback(email: &str, theme: &Theme) -> Option<(&'static str, Color)> {
+    if email.is_empty() {
+        None
+    } else if is_valid_email(email) {
+        Some(("✓ looks valid", theme.success))
+    } else {
+        Some(("… incomplete", theme.border))
+    }
+}
+
+/// The part of the email shown in a field `width` columns wide, plus the
+/// caret's character index within it. The window scrolls just far enough to
+/// keep the caret cell (drawn as `_`) in view, measured in display columns so
+/// wide characters never push it past the border, and starts with `…` while
+/// scrolled. It is recomputed from the current width on every frame, so a
+/// resize or a backspace mid-entry can never leave the caret off-screen.
+fn email_field_window(text: &str, caret: usize, width: usize) -> (String, usize) {
+    let chars: Vec<char> = text.chars().collect();
+    let caret = caret.min(chars.len());
+    if width == 0 {
+        return (String::new(), 0);
+    }
+
+    let before: usize = chars[..caret].iter().map(|&c| char_width(c)).sum();
+    let (mut visible, mut offset) = (String::new(), 0);
+    if before + 1 > width {
+        // One column for the caret and, when it fits, one for the ellipsis.
+        let budget = width.saturating_sub(2);
+        let mut used = 0;
+        offset = caret;
+        while offset > 0 && used + char_width(chars[offset - 1]) <= budget {
+            offset -= 1;
+            used += char_width(chars[offset]);
+        }
+        if width >= 2 {
+            visible.push('…');
+        }
+    }
+    let prefix = visible.chars().count();
+
+    let mut used = display_width(&visible) + 1;
+    for (index, &c) in chars.iter().enumerate().skip(offset) {
+        if index >= caret && used + char_width(c) > width {
+            break;
+        }
+        if index >= caret {
+            used += char_width(c);
+        }
+        visible.push(c);
+    }
+    (visible, prefix + caret - offset)
+}
+
+fn draw_email_view(
+    stdout: &mut impl Write,
+    x: u16,
+    body_y: u16,
+    width: u16,
+    body_height: u16,
+    app: &App,
+) -> io::Result<()> {
+    let email = &app.email;
+    let bottom = body_y + body_height - 1;
+
+    queue!(
+        stdout,
+        MoveTo(x + 3, body_y + 1),
+        Fg(app.theme.text),
+        SetAttribute(Attribute::Bold),
+        Print("EVENT INVITE REQUEST"),
+        SetAttribute(Attribute::Reset),
+        MoveTo(x + 3, body_y + 3),
+        Fg(app.theme.accent),
+        Print("Warning: confirmation is final. To change it later, solve the puzzle again."),
+        MoveTo(x + 3, body_y + 5),
+        Fg(app.theme.border),
+        Print("Email Input")
+    )?;
+
+    let layout = email_layout(x, body_y, width);
+    let (field_x, field_y) = layout.field;
+    let field_width = layout.field_width;
+    let is_input_selected = matches!(email.focus, EmailFocus::Input);
+    let text_width = field_width.saturating_sub(4) as usize;
+    let email_text = if email.email.is_empty() {
+        let mut placeholder = "type-your-email@example.com".to_string();
+        if is_input_selected && placeholder.len() < text_width {
+            placeholder.push('_');
+        }
+        placeholder
+    } else if is_input_selected {
+        let caret = email.email.chars().count();
+        let (mut visible, caret_col) = email_field_window(&email.email, caret, text_width);
+        visible.insert(
+            visible
+                .char_indices()
+                .nth(caret_col)
+                .map_or(visible.len(), |(at, _)| at),
+            '_',
+        );
+        visible
+    } else {
+        email.email.clone()
+    };
+
+    draw_button(
+        stdout,
+        field_x,
+        field_y,
+        field_width,
+        &email_text,
+        is_input_selected,
+        if email.email.is_empty() {
+            app.theme.border
+        } else {
+            app.theme.text
+        },
+        &app.theme,
+    )?;
+    if let Some((feedback, color)) = email_feedback(&email.email, &app.theme) {
+        let feedback_x = (field_x + field_width).saturating_sub(display_width(feedback) as u16 + 1);
+        queue!(
+            stdout,
+            MoveTo(feedback_x, field_y - 1),
+            Fg(color),
+            Print(feedback)
+        )?;
+    }
+
+    let button_y = layout.buttons[0].1;
+    let buttons = ["Confirm Invite", "Solve Again"];
+    for (index, label) in buttons.iter().enumerate() {
+        let selected = matches!(email.focus, EmailFocus::Buttons) && email.selected_button == index;
+        let (button_x, button_y) = layout.buttons[index];
+        draw_button(
+            stdout,
+            button_x,
+            button_y,
+            layout.button_width,
+            label,
+            selected,
+            if index == 0 {
+                app.theme.accent
+            } else {
+                app.theme.border
+            },
+            &app.theme,
+        )?;
+    }
+
+    if button_y + 4 < bottom {
+        queue!(
+            stdout,
+            MoveTo(x + 3, button_y + 4),
+            Fg(if email.awaiting_confirmation {
+                app.theme.text
+            } else {
+                app.theme.border
+            }),
+            Print(if email.awaiting_confirmation {
+                trim_to_width_ellipsis(
+                    &format!("Submitting: {}", email.email),
+                    width.saturating_sub(6) as usize,
+                )
+            } else {
+                "Tab switches between input and buttons. Enter activates the selected control."
+                    .to_string()
+            }),
+            MoveTo(x + 3, button_y + 5),
+            Fg(app.theme.accent),
+            Print(trim_to_width_ellipsis(
+                &email.status,
+                width.saturating_sub(6) as usize
+            ))
+        )?;
+    }
+
+    queue!(stdout, ResetColor)?;
+    Ok(())
+}
+
+fn draw_submitted_view(
+    stdout: &mut impl Write,
+    x: u16,
+    body_y: u16,
+    width: u16,
+    _body_height: u16,
+    app: &App,
+) -> io::Result<()> {
+    let email = app.submitted_email.as_deref().unwrap_or("unknown");
+    queue!(
+        stdout,
+        MoveTo(x + 3, body_y + 3),
+        Fg(app.theme.text),
+        SetAttribute(Attribute::Bold),
+        Print(if app.dry_run {
+            "Invite request submitted. (dry run — not recorded)"
+        } else {
+            "Invite request submitted."
+        }),
+        SetAttribute(Attribute::Reset),
+        MoveTo(x + 3, body_y + 5),
+        Fg(app.theme.border),
+        Print(trim_to_width_ellipsis(
+            &format!("Recorded email: {email}"),
+            width.saturating_sub(6) as usize
+        )),
+        MoveTo(x + 3, body_y + 6),
+        Print(trim_to_width_ellipsis(
+            &format!("Recorded in: {}", submission_destination(app)),
+            width.saturating_sub(6) as usize
+        )),
+        MoveTo(x + 3, body_y + 7),
+        Fg(app.theme.success),
+        SetAttribute(Attribute::Bold),
+        Print(
+            app.score
+                .map(|score| format!("Score: {score}"))
+                .unwrap_or_default()
+        ),
+        SetAttribute(Attribute::Reset),
+        MoveTo(x + 3, body_y + 8),
+        Fg(app.theme.accent),
+        Print("Press Enter or Esc to close the SSH session. L: leaderboard, S: stats.")
+    )?;
+    if let Some(banner) = event_banner(SystemTime::now()) {
+        queue!(
+            stdout,
+            MoveTo(x + 3, body_y + 1),
+            Fg(app.theme.text),
+            Print(trim_to_width(&banner, width.saturating_sub(6) as usize))
+        )?;
+    }
+    draw_solution_replay(stdout, x, body_y + 9, width, app)?;
+    draw_optimal_reveal(stdout, x, body_y + 13, width, app)
+}
+
+/// The submitted view's "Recorded in" value: the sink's path, or what stood
+/// in for one.
+fn submission_destination(app: &App) -> String {
+    match &app.last_submission_path {
+        _ if app.dry_run => "(not recorded)".to_string(),
+        Some(path) => path.display().to_string(),
+        None => "submission command (BOAAI_SINK_COMMAND)".to_string(),
+    }
+}
+
+/// "Optimal path" and "Your path" lines comparing the solver's answer from
+/// the puzzle's `initial` state with the presses the player recorded.
+fn optimal_reveal_lines(puzzle: &PuzzleState) -> Option<[String; 2]> {
+    let optimal = shortest_solution(puzzle.initial, puzzle.target, puzzle.cycle, puzzle.rule)?;
+    let path = |moves: &[usize]| {
+        if moves.is_empty() {
+            "(none)".to_string()
+        } else {
+            moves
+                .iter()
+                .map(|index| (index + 1).to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        }
+    };
+    Some([
+        format!(
+            "Optimal path ({} press(es)): {}",
+            optimal.len(),
+            path(&optimal)
+        ),
+        format!(
+            "Your path    ({} press(es)): {}",
+            puzzle.moves.len(),
+            path(&puzzle.moves)
+        ),
+    ])
+}
+
+fn draw_optimal_reveal(
+    stdout: &mut impl Write,
+    x: u16,
+    y: u16,
+    width: u16,
+    app: &App,
+) -> io::Result<()> {
+    if !app.reveal_optimal {
+        return Ok(());
+    }
+    let Some([optimal, yours]) = optimal_reveal_lines(&app.puzzle) else {
+        return Ok(());
+    };
+    let max = width.saturating_sub(6) as usize;
+    queue!(
+        stdout,
+        MoveTo(x + 3, y),
+        Fg(app.theme.success),
+        Print(trim_to_width_ellipsis(&optimal, max)),
+        MoveTo(x + 3, y + 1),
+        Fg(app.theme.text),
+        Print(trim_to_width_ellipsis(&yours, max))
+    )?;
+    Ok(())
+}
+
+/// Shows the player's own presses replayed from the start of the solved
+/// puzzle, or the prompt to start that replay.
+fn draw_solution_replay(
+    stdout: &mut impl Write,
+    x: u16,
+    y: u16,
+    width: u16,
+    app: &App,
+) -> io::Result<()> {
+    let puzzle = &app.puzzle;
+    let Some(replay) = &app.solution_replay else {
+        if !puzzle.moves.is_empty() {
+            queue!(
+                stdout,
+                MoveTo(x + 3, y),
+                Fg(app.theme.border),
+                Print("Press R to replay your solution.")
+            )?;
+        }
+        return Ok(());
+    };
+
+    let heading = if replay.step < puzzle.moves.len() {
+        format!(
+            "Your solution: press {}/{} (indicator {})",
+            replay.step + 1,
+            puzzle.moves.len(),
+            puzzle.moves[replay.step] + 1
+        )
+    } else if replay.state == puzzle.target {
+        format!(
+            "Your solution: {} move(s) reproduced the target.",
+            puzzle.moves.len()
+        )
+    } else {
+        "Your solution: replay did not reach the target.".to_string()
+    };
+
+    queue!(
+        stdout,
+        MoveTo(x + 3, y),
+        Fg(app.theme.text),
+        Print(trim_to_width(&heading, width.saturating_sub(6) as usize))
+    )?;
+    draw_colored_state_line(
+        stdout,
+        x + 3,
+        y + 1,
+        "Yours ",
+        replay.state,
+        app.palette,
+        &app.theme,
+    )?;
+    draw_colored_state_line(
+        stdout,
+        x + 3,
+        y + 2,
+        "Target",
+        puzzle.target,
+        app.palette,
+        &app.theme,
+    )
+}
+
+fn draw_victory_view(
+    stdout: &mut impl Write,
+    x: u16,
+    body_y: u16,
+    width: u16,
+    app: &App,
+) -> io::Result<()> {
+    let summary = app
+        .campaign
+        .as_ref()
+        .map(Campaign::summary)
+        .unwrap_or_default();
+    queue!(
+        stdout,
+        MoveTo(x + 3, body_y + 3),
+        Fg(app.theme.text),
+        SetAttribute(Attribute::Bold),
+        Print("Campaign complete. Every stage solved."),
+        SetAttribute(Attribute::Reset),
+        MoveTo(x + 3, body_y + 5),
+        Fg(app.theme.border),
+        Print(trim_to_width(&summary, width.saturating_sub(6) as usize)),
+        MoveTo(x + 3, body_y + 7),
+        Fg(app.theme.accent),
+        Print("Press Enter or Esc to close the SSH session.")
+    )?;
+    draw_solution_replay(stdout, x, body_y + 9, width, app)?;
+    draw_optimal_reveal(stdout, x, body_y + 13, width, app)
+}
+
+fn draw_difficulty_menu(
+    stdout: &mut impl Write,
+    x: u16,
+    body_y: u16,
+    width: u16,
+    app: &App,
+) -> io::Result<()> {
+    queue!(
+        stdout,
+        MoveTo(x + 3, body_y + 1),
+        Fg(app.theme.text),
+        SetAttribute(Attribute::Bold),
+        Print("SELECT DIFFICULTY"),
+        SetAttribute(Attribute::Reset),
+        MoveTo(x + 3, body_y + 3),
+        Fg(app.theme.border),
+        Print("The starting board is the target scrambled by random presses.")
+    )?;
+
+    for (index, (difficulty, (cell_x, cell_y))) in Difficulty::ALL
+        .into_iter()
+        .zip(action_cells(x, width, body_y + 5))
+        .enumerate()
+    {
+        let mut label = difficulty.name().to_string();
+        label[..1].make_ascii_uppercase();
+        draw_button(
+            stdout,
+            cell_x,
+            cell_y,
+            ACTION_WIDTH,
+            &format!("{label} ({})", difficulty.presses()),
+            index == app.difficulty_selected,
+            app.theme.text,
+            &app.theme,
+        )?;
+    }
+    Ok(())
+}
+
+fn draw_drill_menu(
+    stdout: &mut impl Write,
+    x: u16,
+    body_y: u16,
+    width: u16,
+    app: &App,
+) -> io::Result<()> {
+    queue!(
+        stdout,
+        MoveTo(x + 3, body_y + 1),
+        Fg(app.theme.text),
+        SetAttribute(Attribute::Bold),
+        Print("DRILLS // ONE RULE AT A TIME"),
+        SetAttribute(Attribute::Reset)
+    )?;
+
+    for (index, drill) in DRILLS.iter().enumerate() {
+        let selected = index == app.drill_selected;
+        queue!(
+            stdout,
+            MoveTo(x + 5, body_y + 3 + index as u16),
+            Fg(if selected {
+                app.theme.text
+            } else {
+                app.theme.border
+            }),
+            Print(format!(
+                "{} {}. {}",
+                if selected { ">" } else { " " },
+                index + 1,
+                drill.name
+            ))
+        )?;
+    }
+
+    queue!(
+        stdout,
+        MoveTo(x + 3, body_y + 4 + DRILLS.len() as u16),
+        Fg(app.theme.accent),
+        Print(trim_to_width_ellipsis(
+            &app.drill_status,
+            width.saturating_sub(6) as usize
+        )),
+        ResetColor
+    )?;
+    Ok(())
+}
+
+fn draw_leaderboard_view(
+    stdout: &mut impl Write,
+    x: u16,
+    body_y: u16,
+    width: u16,
+    body_height: u16,
+    app: &App,
+) -> io::Result<()> {
+    let list_height = (LEADERBOARD_SIZE as u16 + 4).min(body_height.saturating_sub(4));
+    draw_box(
+        stdout,
+        x + 2,
+        body_y + 1,
+        width.saturating_sub(4),
+        list_height,
+        app.theme.border,
+    )?;
+    queue!(
+        stdout,
+        MoveTo(x + 4, body_y + 2),
+        Fg(app.theme.text),
+        SetAttribute(Attribute::Bold),
+        Print("TOP SCORES"),
+        SetAttribute(Attribute::Reset)
+    )?;
+
+    let max = width.saturating_sub(10) as usize;
+    let submitted = app.submitted_email.as_deref().map(normalize_email);
+    if app.leaderboard.is_empty() {
+        queue!(
+            stdout,
+            MoveTo(x + 4, body_y + 4),
+            Fg(app.theme.border),
+            Print("No runs recorded yet.")
+        )?;
+    }
+    let rows = list_height.saturating_sub(4) as usize;
+    for (rank, entry) in app.leaderboard.iter().take(rows).enumerate() {
+        let line = format!(
+            "{:>2}. {:>4} pts  {}  {:>3} moves  {}",
+            rank + 1,
+            entry.score,
+            format_duration(Duration::from_secs(entry.solve_seconds)),
+            entry.moves_taken,
+            entry.email
+        );
+        let yours = submitted.as_deref() == Some(normalize_email(&entry.email).as_str());
+        queue!(
+            stdout,
+            MoveTo(x + 4, body_y + 4 + rank as u16),
+            Fg(if yours {
+                app.theme.success
+            } else {
+                Color::Grey
+            }),
+            Print(trim_to_width_ellipsis(&line, max))
+        )?;
+    }
+
+    queue!(
+        stdout,
+        MoveTo(x + 3, body_y + 2 + list_height),
+        Fg(app.theme.accent),
+        Print(trim_to_width_ellipsis(
+            &app.leaderboard_status,
+            width.saturating_sub(6) as usize
+        )),
+        ResetColor
+    )?;
+    Ok(())
+}
+
+fn draw_stats_view(
+    stdout: &mut impl Write,
+    x: u16,
+    body_y: u16,
+    width: u16,
+    app: &App,
+) -> io::Result<()> {
+    draw_box(
+        stdout,
+        x + 2,
+        body_y + 1,
+        width.saturating_sub(4),
+        7,
+        app.theme.border,
+    )?;
+    queue!(
+        stdout,
+        MoveTo(x + 4, body_y + 2),
+        Fg(app.theme.text),
+        SetAttribute(Attribute::Bold),
+        Print("SUBMISSION STATS"),
+        SetAttribute(Attribute::Reset)
+    )?;
+
+    let latest = app.stats.latest.as_ref().map_or_else(
+        || "Most recent: none yet".to_string(),
+        |record| {
+            format!(
+                "Most recent: {} at {}",
+                record.email,
+                format_utc(record.submitted_unix)
+            )
+        },
+    );
+    let lines = [
+        format!("Total submissions: {}", app.stats.total),
+        format!("Today (UTC): {}", app.stats.today),
+        latest,
+    ];
+    let max = width.saturating_sub(10) as usize;
+    for (offset, line) in lines.iter().enumerate() {
+        queue!(
+            stdout,
+            MoveTo(x + 4, body_y + 4 + offset as u16),
+            Fg(Color::Grey),
+            Print(trim_to_width_ellipsis(line, max))
+        )?;
+    }
+
+    queue!(
+        stdout,
+        MoveTo(x + 3, body_y + 9),
+        Fg(app.theme.accent),
+        Print(trim_to_width_ellipsis(
+            &app.stats_status,
+            width.saturating_sub(6) as usize
+        )),
+        ResetColor
+    )?;
+    Ok(())
+}
+
+/// Keys listed by the `?` overlay for `phase`, as (keys, action) pairs.
+/// Phases without an entry do not open the overlay.
+fn help_lines(phase: AppPhase) -> &'static [(&'static str, &'static str)] {
+    match phase {
+        AppPhase::Puzzle => &[
+            ("←/→", "move focus"),
+            ("Ctrl+←/→ Home/End", "first/last indicator"),
+            ("↑/↓", "switch between indicators and actions"),
+            ("Space Enter", "press the selected button"),
+            ("1-6", "press that indicator"),
+            ("Shift/Alt+Enter", "mark an indicator as locked"),
+            ("PageUp/PageDown", "scroll the rules (focus on Hide Rules)"),
+            ("U", "undo"),
+            ("Y Ctrl+R", "redo"),
+            ("W", "explain the furthest position"),
+            ("P", "reveal the remaining path"),
+            ("Z", "restart scoring from here"),
+            ("B", "bookmark the current state"),
+            ("C", "toggle the color-blind palette"),
+            ("F", "toggle the finishing-press highlight"),
+            ("O", "switch row and ring layouts"),
+            ("M", "toggle reduced motion"),
+            ("S", "skip matched indicators"),
+            ("Ctrl+S", "save the puzzle"),
+            ("Ctrl+Z", "suspend to the shell"),
+            ("Esc", "quit"),
+        ],
+        AppPhase::Email => &[
+            ("Type", "enter your email"),
+            ("Tab", "switch between input and buttons"),
+            ("Enter Space", "activate the selected button"),
+            ("Enter", "submit the shown address"),
+            ("Esc", "back to editing, or quit"),
+            ("F2", "accept the suggested correction"),
+        ],
+        AppPhase::Submitted | AppPhase::Victory => &[
+            ("R", "replay your presses"),
+            ("L", "show the leaderboard"),
+            ("S", "show submission stats"),
+            ("Enter Esc", "close the session"),
+        ],
+        _ => &[],
+    }
+}
+
+/// The `?` overlay: a box centered in the body listing `help_lines`, with
+/// `scroll` lines skipped when they do not all fit.
+fn draw_help_overlay(
+    stdout: &mut impl Write,
+    x: u16,
+    body_y: u16,
+    width: u16,
+    body_height: u16,
+    scroll: usize,
+    app: &App,
+) -> io::Result<()> {
+    let lines = help_lines(app.phase);
+    let key_width = lines
+        .iter()
+        .map(|(keys, _)| display_width(keys))
+        .max()
+        .unwrap_or(0);
+    let box_width = width.saturating_sub(8).min(64);
+    let box_height = body_height.min(lines.len() as u16 + 4);
+    if box_width < 24 || box_height < 5 {
+        return Ok(());
+    }
+    let box_x = x + (width - box_width) / 2;
+    let box_y = body_y + (body_height - box_height) / 2;
+
+    let fill = " ".repeat(box_width as usize);
+    queue!(stdout, Bg(app.theme.background))?;
+    for row in box_y..box_y + box_height {
+        queue!(stdout, MoveTo(box_x, row), Print(&fill))?;
+    }
+    draw_box(stdout, box_x, box_y, box_width, box_height, app.theme.text)?;
+    queue!(
+        stdout,
+        MoveTo(box_x + 2, box_y + 1),
+        Fg(app.theme.text),
+        SetAttribute(Attribute::Bold),
+        Print("KEYS"),
+        SetAttribute(Attribute::Reset)
+    )?;
+
+    let shown = (box_height - 4) as usize;
+    let first = scroll.min(lines.len().saturating_sub(shown));
+    let text_width = box_width.saturating_sub(4) as usize;
+    for (offset, (keys, action)) in lines.iter().skip(first).take(shown).enumerate() {
+        let padding = " ".repeat(key_width - display_width(keys));
+        queue!(
+            stdout,
+            MoveTo(box_x + 2, box_y + 3 + offset as u16),
+            Fg(app.theme.accent),
+            Print(trim_to_width(&format!("{keys}{padding}  "), text_width)),
+            Fg(Color::Grey),
+            Print(trim_to_width_ellipsis(
+                action,
+                text_width.saturating_sub(key_width + 2)
+            ))
+        )?;
+    }
+
+    let hint = if lines.len() > shown {
+        format!(
+            " {}-{} of {}  ↑/↓ scroll  ?/Esc close ",
+            first + 1,
+            first + shown,
+            lines.len()
+        )
+    } else {
+        " ?/Esc close ".to_string()
+    };
+    let hint = trim_to_width(&hint, box_width.saturating_sub(4) as usize);
+    queue!(
+        stdout,
+        MoveTo(
+            box_x + box_width - 2 - display_width(&hint) as u16,
+            box_y + box_height - 1
+        ),
+        Fg(app.theme.border),
+        Print(hint),
+        ResetColor
+    )?;
+    Ok(())
+}
+
+fn draw_game_over_view(
+    stdout: &mut impl Write,
+    x: u16,
+    body_y: u16,
+    width: u16,
+    app: &App,
+) -> io::Result<()> {
+    let (cleared, total, record_status) = app.survival.as_ref().map_or_else(
+        || (0, Duration::ZERO, ""),
+        |run| {
+            (
+                run.cleared,
+                run.total.unwrap_or_default(),
+                run.record_status.as_str(),
+            )
+        },
+    );
+    queue!(
+        stdout,
+        MoveTo(x + 3, body_y + 3),
+        Fg(app.theme.text),
+        SetAttribute(Attribute::Bold),
+        Print("Run over: the time budget ran out."),
+        SetAttribute(Attribute::Reset),
+        MoveTo(x + 3, body_y + 5),
+        Fg(app.theme.border),
+        Print(format!(
+            "Cleared {cleared} puzzle(s) in {}.",
+            format_duration(total)
+        )),
+        MoveTo(x + 3, body_y + 6),
+        Print(trim_to_width_ellipsis(
+            record_status,
+            width.saturating_sub(6) as usize
+        )),
+        MoveTo(x + 3, body_y + 8),
+        Fg(app.theme.accent),
+        Print("Press Enter to submit your email, R for a new run, or Esc to quit."),
+        ResetColor
+    )?;
+    Ok(())
+}
+
+fn draw_footer(
+    stdout: &mut impl Write,
+    x: u16,
+    width: u16,
+    rows: u16,
+    app: &App,
+) -> io::Result<()> {
+    let top = rows.saturating_sub(2);
+    let bottom = rows.saturating_sub(1);
+    let bar = "─".repeat(width as usize);
+    let message = match app.phase {
+        AppPhase::Puzzle => {
+            "←/→: move  Ctrl+←/→: first/last  ↑/↓: switch row  Enter: activate  ?: keys  Esc: quit"
+        }
+        AppPhase::Email => {
+            "Type email, Tab to buttons, Enter to activate selection, ? for keys, Esc to quit"
+        }
+        AppPhase::Solved if app.campaign.is_some() => "Stage solved! Press Enter to continue.",
+        AppPhase::Solved => "Target reached! Press any key to continue.",
+        AppPhase::Submitted | AppPhase::Victory => {
+            "Session complete. Press Enter or Esc to exit, ? for keys."
+        }
+        AppPhase::Menu => "←/→: choose difficulty  Enter: start  Esc: quit",
+        AppPhase::Drills => "↑/↓: choose drill  Enter: start  Esc: quit",
+        AppPhase::GameOver => "Enter: submit email  R: new run  Esc: quit",
+        AppPhase::Leaderboard => "L, Enter or Esc: back",
+        AppPhase::Stats => "S, Enter or Esc: back",
+        AppPhase::OutOfMoves => "R: retry  U: undo last press  Esc: quit",
+    };
+    let message = match (app.phase, &app.survival) {
+        (AppPhase::Puzzle, Some(run)) => format!(
+            "{} left  |  {message}",
+            format_duration(run.budget.saturating_sub(run.puzzle_started.elapsed()))
+        ),
+        _ => message.to_string(),
+    };
+    let message = match session_countdown(app, Instant::now()) {
+        Some(countdown) => format!("{countdown}  |  {message}"),
+        None => message,
+    };
+
+    let footer_text = trim_to_width_ellipsis(&message, width as usize);
+    let text_x = x + width.saturating_sub(footer_text.chars().count() as u16) / 2;
+    // The session tag sits on the bar, right-aligned, where it stays out of
+    // the way until support asks for it.
+    let tag = format!(" session {} ", app.session_id);
+    let bar = match (width as usize).checked_sub(tag.chars().count() + 2) {
+        Some(fill) if fill >= 20 => format!("{}{tag}──", "─".repeat(fill)),
+        _ => bar,
+    };
+    queue!(
+        stdout,
+        MoveTo(x, top),
+        Fg(app.theme.border),
+        Print(bar),
+        MoveTo(text_x, bottom),
+        Print(footer_text),
+        ResetColor
+    )?;
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn draw_button(
+    stdout: &mut impl Write,
+    x: u16,
+    y: u16,
+    width: u16,
+    label: &str,
+    selected: bool,
+    accent: Color,
+    theme: &Theme,
+) -> io::Result<()> {
+    draw_button_with_border(
+        stdout, x, y, width, label, selected, accent, None, false, theme,
+    )
+}
+
+/// Like `draw_button`, but `border` overrides the frame color so a button can
+/// stand out without changing its selection styling, and `locked` draws the
+/// frame with double lines.
+#[allow(clippy::too_many_arguments)]
+fn draw_button_with_border(
+    stdout: &mut impl Write,
+    x: u16,
+    y: u16,
+    width: u16,
+    label: &str,
+    selected: bool,
+    accent: Color,
+    border: Option<Color>,
+    locked: bool,
+    theme: &Theme,
+) -> io::Result<()> {
+    if width < 4 {
+        return Ok(());
+    }
+
+    let inner_width = (width - 2) as usize;
+    let [top_left, top_right, bottom_left, bottom_right, horizontal, vertical] = if locked {
+        ["╔", "╗", "╚", "╝", "═", "║"]
+    } else {
+        ["┌", "┐", "└", "┘", "─", "│"]
+    };
+    let top = format!("{top_left}{}{top_right}", horizontal.repeat(inner_width));
+    let bottom = format!(
+        "{bottom_left}{}{bottom_right}",
+        horizontal.repeat(inner_width)
+    );
+    let text = button_text(label, inner_width, selected && plain_mode());
+
+    let border_color = border.unwrap_or(if selected { theme.text } else { theme.border });
+    let text_color = if selected { theme.background } else { accent };
+    let fill_color = if selected {
+        theme.selection
+    } else {
+        theme.background
+    };
+
+    queue!(
+        stdout,
+        MoveTo(x, y),
+        Fg(border_color),
+        Bg(theme.background),
+        Print(top),
+        MoveTo(x, y + 1),
+        Print(vertical),
+        Bg(fill_color),
+        Fg(text_color),
+        Print(text),
+        Bg(theme.background),
+        Fg(border_color),
+        Print(vertical),
+        MoveTo(x, y + 2),
+        Print(bottom),
+        ResetColor
+    )?;
+    Ok(())
+}
+
+/// Centers `label` in a button's inner width. `bracketed` frames it as
+/// `[label]`, which stands in for the selection fill when colors are off.
+fn button_text(label: &str, inner_width: usize, bracketed: bool) -> String {
+    if !bracketed || inner_width < 3 {
+        return center_text(&trim_to_width(label, inner_width), inner_width);
+    }
+    let inner = inner_width - 2;
+    format!("[{}]", center_text(&trim_to_width(label, inner), inner))
+}
+
+/// Dispatches a key to the active phase.
+///
+/// `Space` and `Enter` share one arm in every handler and activate the
+/// focused control: an indicator, an action, a menu choice or an email
+/// button. The one split is the solved hold: a solved campaign stage waits
+/// for `Enter` (or `Esc`) before the next stage begins, while outside a
+/// campaign any key, `Space` included, skips the hold.
+fn handle_key(app: &mut App, key: KeyEvent) -> io::Result<bool> {
+    if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+        app.should_quit = true;
+        return Ok(true);
+    }
+    if key.code == KeyCode::Char('z') && key.modifiers.contains(KeyModifiers::CONTROL) {
+        app.suspend_pending = cfg!(unix);
+        return Ok(false);
+    }
+    if app.help.is_some() {
+        return Ok(handle_help_key(app, key));
+    }
+    if key.code == KeyCode::Char('?') && !help_lines(app.phase).is_empty() {
+        app.help = Some(0);
+        return Ok(true);
+    }
+
+    match app.phase {
+        AppPhase::Puzzle => {
+            let before = (
+                app.puzzle.initial,
+                app.puzzle.current,
+                app.puzzle.moves.len(),
+            );
+            let handled = handle_puzzle_key(app, key);
+            let after = (
+                app.puzzle.initial,
+                app.puzzle.current,
+                app.puzzle.moves.len(),
+            );
+            if before != after && matches!(app.phase, AppPhase::Puzzle) {
+                autosave(app);
+            }
+            Ok(handled)
+        }
+        AppPhase::Solved => Ok(handle_solved_key(app, key)),
+        AppPhase::Email => handle_email_key(app, key),
+        AppPhase::Submitted | AppPhase::Victory => Ok(handle_submitted_key(app, key)),
+        AppPhase::Menu => Ok(handle_difficulty_menu_key(app, key)),
+        AppPhase::Drills => Ok(handle_drill_menu_key(app, key)),
+        AppPhase::GameOver => Ok(handle_game_over_key(app, key)),
+        AppPhase::Leaderboard => Ok(handle_leaderboard_key(app, key)),
+        AppPhase::Stats => Ok(handle_stats_key(app, key)),
+        AppPhase::OutOfMoves => Ok(handle_out_of_moves_key(app, key)),
+    }
+}
+
+fn handle_game_over_key(app: &mut App, key: KeyEvent) -> bool {
+    match key.code {
+        KeyCode::Enter => {
+            transition_to_email(app);
+            true
+        }
+        KeyCode::Char('r') | KeyCode::Char('R') => {
+            start_survival(app, survival_from_env());
+            true
+        }
+        KeyCode::Esc => {
+            app.should_quit = true;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Begins a fresh survival run at its starting depth.
+fn start_survival(app: &mut App, run: SurvivalRun) {
+    app.survival = Some(run);
+    app.phase = AppPhase::Puzzle;
+    start_survival_puzzle(app);
+}
+
+fn start_survival_puzzle(app: &mut App) {
+    let Some(run) = app.survival.as_mut() else {
+        return;
+    };
+    let (target, _) = generate_target_with_presses(
+        &mut rand::thread_rng(),
+        app.start,
+        app.cycle,
+        app.rule,
+        run.depth,
+    );
+    app.puzzle = puzzle_state_for(app.start, target, app.cycle, app.rule)
+        .expect("generated targets are reachable by construction");
+    run.puzzle_started = Instant::now();
+    let budget = run.budget.as_secs();
+    let number = run.cleared + 1;
+    app.puzzle
+        .set_status(format!("Survival puzzle {number}: solve within {budget}s."));
+}
+
+/// Ends the run, scores it as puzzles cleared and appends it to the run log.
+fn end_survival_run(app: &mut App) {
+    let Some(run) = app.survival.as_mut() else {
+        return;
+    };
+    let total = run.started_at.elapsed();
+    run.total = Some(total);
+    app.score = Some(run.cleared as u32);
+    app.phase = AppPhase::GameOver;
+    run.record_status = match record_survival_run(&run.record_path, run.cleared, total, run.depth) {
+        Ok(()) => format!("Run recorded to {}.", run.record_path.display()),
+        Err(error) => format!("Could not record run: {error}"),
+    };
+}
+
+const LEADERBOARD_SIZE: usize = 10;
+
+/// Reads `email,solve_seconds,moves_taken,score` rows. A missing file is an
+/// empty board; malformed rows are skipped, and rows from before the score
+/// column count as 0 points.
+fn load_leaderboard(path: &Path) -> io::Result<Vec<LeaderboardEntry>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error),
+    };
+    Ok(contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(',');
+            let email = fields.next()?.to_string();
+            let solve_seconds = fields.next()?.trim().parse().ok()?;
+            let moves_taken = fields.next()?.trim().parse().ok()?;
+            let score = match fields.next() {
+                Some(score) => score.trim().parse().ok()?,
+                None => 0,
+            };
+            Some(LeaderboardEntry {
+                email,
+                solve_seconds,
+                moves_taken,
+                score,
+            })
+        })
+        .collect())
+}
+
+/// Adds `entry` and keeps the best `LEADERBOARD_SIZE` runs. The composite
+/// score ranks them, since it already weighs time and moves; equal scores
+/// fall back to solve time and then moves, and earlier runs win exact ties.
+fn insert_leaderboard_entry(entries: &mut Vec<LeaderboardEntry>, entry: LeaderboardEntry) {
+    entries.push(entry);
+    entries.sort_by_key(|entry| (Reverse(entry.score), entry.solve_seconds, entry.moves_taken));
+    entries.truncate(LEADERBOARD_SIZE);
+}
+
+fn record_leaderboard(path: &Path, entry: LeaderboardEntry) -> io::Result<()> {
+    let mut entries = load_leaderboard(path)?;
+    insert_leaderboard_entry(&mut entries, entry);
+    let mut contents = String::from("email,solve_seconds,moves_taken,score\n");
+    for entry in &entries {
+        contents.push_str(&format!(
+            "{},{},{},{}\n",
+            entry.email, entry.solve_seconds, entry.moves_taken, entry.score
+        ));
+    }
+    fs::write(path, contents)
+}
+
+fn record_survival_run(
+    path: &Path,
+    cleared: usize,
+    total: Duration,
+    depth: usize,
+) -> io::Result<()> {
+    let ended_unix = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let file_exists = path.exists();
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    if !file_exists {
+        writeln!(file, "ended_unix,cleared,total_secs,final_depth")?;
+    }
+    writeln!(file, "{ended_unix},{cleared},{},{depth}", total.as_secs())
+}
+
+fn handle_difficulty_menu_key(app: &mut App, key: KeyEvent) -> bool {
+    match key.code {
+        KeyCode::Left => {
+            app.difficulty_selected = app.difficulty_selected.saturating_sub(1);
+            true
+        }
+        KeyCode::Right => {
+            app.difficulty_selected = (app.difficulty_selected + 1).min(Difficulty::ALL.len() - 1);
+            true
+        }
+        KeyCode::Enter | KeyCode::Char(' ') => {
+            app.prefs.difficulty = Difficulty::ALL[app.difficulty_selected];
+            choose_difficulty(app, app.prefs.difficulty);
+            true
+        }
+        KeyCode::Esc => {
+            app.should_quit = true;
+            true
+        }
+        _ => false,
+    }
+}
+
+fn handle_drill_menu_key(app: &mut App, key: KeyEvent) -> bool {
+    match key.code {
+        KeyCode::Up => {
+            app.drill_selected = app.drill_selected.saturating_sub(1);
+            true
+        }
+        KeyCode::Down => {
+            app.drill_selected = (app.drill_selected + 1).min(DRILLS.len() - 1);
+            true
+        }
+        KeyCode::Enter | KeyCode::Char(' ') => {
+            start_drill(app, app.drill_selected);
+            true
+        }
+        KeyCode::Esc => {
+            app.should_quit = true;
+            true
+        }
+        _ => false,
+    }
+}
+
+fn start_drill(app: &mut App, index: usize) {
+    let drill = &DRILLS[index];
+    let target = drill.presses.iter().fold(app.start, |state, &press| {
+        press_indicator(state, press, app.cycle, PressRule::Classic)
+    });
+    app.puzzle = puzzle_state_for(app.start, target, app.cycle, PressRule::Classic)
+        .expect("drill targets are built from presses");
+    app.puzzle
+        .set_status(format!("Drill: {}. Reach the target.", drill.name));
+    app.active_drill = Some(index);
+    app.phase = AppPhase::Puzzle;
+}
+
+fn handle_puzzle_key(app: &mut App, key: KeyEvent) -> bool {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    match key.code {
+        KeyCode::PageUp | KeyCode::PageDown
+            if app.puzzle.show_rules && matches!(app.puzzle.focus, PuzzleFocus::Action(2)) =>
+        {
+            let last = rules_lines(&app.puzzle).len().saturating_sub(1);
+            app.puzzle.rules_scroll = if key.code == KeyCode::PageUp {
+                app.puzzle.rules_scroll.saturating_sub(1)
+            } else {
+                (app.puzzle.rules_scroll + 1).min(last)
+            };
+            true
+        }
+        // Terminals that do not report Ctrl+Arrow fall through to the plain
+        // arrow arms below, so navigation keeps working either way.
+        KeyCode::Left if ctrl => {
+            app.puzzle.focus = PuzzleFocus::Indicator(0);
+            true
+        }
+        KeyCode::Right if ctrl => {
+            app.puzzle.focus = PuzzleFocus::Indicator(INDICATOR_COUNT - 1);
+            true
+        }
+        KeyCode::Home => {
+            app.puzzle.focus = PuzzleFocus::Indicator(0);
+            true
+        }
+        KeyCode::End => {
+            app.puzzle.focus = PuzzleFocus::Indicator(INDICATOR_COUNT - 1);
+            true
+        }
+        KeyCode::Left => {
+            match app.puzzle.focus {
+                PuzzleFocus::Indicator(index) => {
+                    app.puzzle.focus = PuzzleFocus::Indicator(step_focus(
+                        &app.puzzle,
+                        index,
+                        false,
+                        app.skip_matched,
+                    ))
+                }
+                PuzzleFocus::Action(index) => {
+                    app.puzzle.focus =
+                        PuzzleFocus::Action((index + ACTION_COUNT - 1) % ACTION_COUNT)
+                }
+            }
+            true
+        }
+        KeyCode::Right => {
+            match app.puzzle.focus {
+                PuzzleFocus::Indicator(index) => {
+                    app.puzzle.focus = PuzzleFocus::Indicator(step_focus(
+                        &app.puzzle,
+                        index,
+                        true,
+                        app.skip_matched,
+                    ))
+                }
+                PuzzleFocus::Action(index) => {
+                    app.puzzle.focus = PuzzleFocus::Action((index + 1) % ACTION_COUNT)
+                }
+            }
+            true
+        }
+        KeyCode::Up | KeyCode::Down => {
+            match app.puzzle.focus {
+                // Indicators and actions are spread proportionally, so each
+                // action sits under a share of the ring whatever its size.
+                PuzzleFocus::Indicator(index) => {
+                    app.puzzle.focus = PuzzleFocus::Action(index * ACTION_COUNT / INDICATOR_COUNT);
+                }
+                PuzzleFocus::Action(index) => {
+                    let target = (index * INDICATOR_COUNT).div_ceil(ACTION_COUNT);
+                    app.puzzle.focus = PuzzleFocus::Indicator(target.min(INDICATOR_COUNT - 1));
+                }
+            }
+            true
+        }
+        KeyCode::Enter
+            if key.modifiers.contains(KeyModifiers::SHIFT)
+                && matches!(app.puzzle.focus, PuzzleFocus::Action(0)) =>
+        {
+            reveal_full_path(&mut app.puzzle);
+            true
+        }
+        // Shift or Alt+Enter on an indicator toggles its lock mark instead of
+        // pressing it.
+        KeyCode::Enter
+            if key
+                .modifiers
+                .intersects(KeyModifiers::SHIFT | KeyModifiers::ALT) =>
+        {
+            let PuzzleFocus::Indicator(index) = app.puzzle.focus else {
+                return false;
+            };
+            app.puzzle.locked[index] = !app.puzzle.locked[index];
+            app.puzzle.set_status(format!(
+                "Indicator {} {}.",
+                index + 1,
+                if app.puzzle.locked[index] {
+                    "marked as locked"
+                } else {
+                    "unlocked"
+                }
+            ));
+            true
+        }
+        KeyCode::Char(' ') | KeyCode::Enter => {
+            activate_puzzle_focus(app);
+            true
+        }
+        // `1`-`6` focus and press that indicator in one keystroke.
+        KeyCode::Char(digit)
+            if digit
+                .to_digit(10)
+                .is_some_and(|number| (1..=INDICATOR_COUNT).contains(&(number as usize))) =>
+        {
+            let index = digit as usize - '1' as usize;
+            app.puzzle.focus = PuzzleFocus::Indicator(index);
+            activate_puzzle_focus(app);
+            true
+        }
+        KeyCode::Char('r') if ctrl => {
+            redo_press(app);
+            true
+        }
+        KeyCode::Char('u') | KeyCode::Char('U') => {
+            undo_press(&mut app.puzzle);
+            true
+        }
+        KeyCode::Char('y') | KeyCode::Char('Y') => {
+            redo_press(app);
+            true
+        }
+        KeyCode::Char('z') | KeyCode::Char('Z') => {
+            restart_scoring(&mut app.puzzle);
+            app.puzzle.set_status(format!(
+                "Scoring restarted from this position (practice, unranked). Par is now {}.",
+                app.puzzle.optimal_moves
+            ));
+            true
+        }
+        KeyCode::Char('b') | KeyCode::Char('B') => {
+            if app.puzzle.bookmark.take().is_some() {
+                app.puzzle.set_status("Bookmark cleared.");
+            } else {
+                app.puzzle.bookmark = Some(app.puzzle.current);
+                app.puzzle
+                    .set_status("Bookmarked current state. Differences are highlighted.");
+            }
+            true
+        }
+        KeyCode::Char('p') | KeyCode::Char('P') => {
+            reveal_full_path(&mut app.puzzle);
+            true
+        }
+        KeyCode::Char('w') | KeyCode::Char('W') => {
+            app.puzzle.set_status(worst_position_hint(&app.puzzle));
+            true
+        }
+        KeyCode::Char('o') | KeyCode::Char('O') => {
+            app.layout = match app.layout {
+                IndicatorLayout::Row => IndicatorLayout::Ring,
+                IndicatorLayout::Ring => IndicatorLayout::Row,
+            };
+            app.puzzle.set_status(match app.layout {
+                IndicatorLayout::Row => "Row layout.",
+                IndicatorLayout::Ring => {
+                    "Ring layout: neighbors are joined and opposites face each other."
+                }
+            });
+            true
+        }
+        KeyCode::Char('m') | KeyCode::Char('M') => {
+            app.reduced_motion = !app.reduced_motion;
+            app.prefs.reduced_motion = app.reduced_motion;
+            app.puzzle.set_status(if app.reduced_motion {
+                "Reduced motion on: transitions are instant."
+            } else {
+                "Reduced motion off."
+            });
+            true
+        }
+        KeyCode::Char('s') if ctrl => {
+            match app.save_path.clone() {
+                Some(path) if app.puzzle.seed.is_some() => {
+                    app.puzzle
+                        .set_status(match save_puzzle(&path, &app.puzzle, app.difficulty) {
+                            Ok(()) => format!("Puzzle saved to {}.", path.display()),
+                            Err(error) => format!("Could not save puzzle: {error}"),
+                        })
+                }
+                Some(_) => app.puzzle.set_status("Only the main puzzle can be saved."),
+                None => app
+                    .puzzle
+                    .set_status("Saving is off. Set BOAAI_SAVE_FILE to enable it."),
+            }
+            true
+        }
+        KeyCode::Char('c') | KeyCode::Char('C') => {
+            app.palette = match app.palette {
+                Palette::Default => Palette::ColorBlind,
+                Palette::ColorBlind => Palette::Default,
+            };
+            app.prefs.color_blind = app.palette == Palette::ColorBlind;
+            app.puzzle.set_status(match app.palette {
+                Palette::Default => "Default palette.",
+                Palette::ColorBlind => "Color-blind palette: blue/orange/yellow with shape glyphs.",
+            });
+            true
+        }
+        KeyCode::Char('s') | KeyCode::Char('S') => {
+            app.skip_matched = !app.skip_matched;
+            app.prefs.skip_matched = app.skip_matched;
+            app.puzzle.set_status(if app.skip_matched {
+                "Left/Right now skip indicators that already match the target."
+            } else {
+                "Left/Right visit every indicator again."
+            });
+            true
+        }
+        KeyCode::Char('f') | KeyCode::Char('F') => {
+            app.show_finishing_press = !app.show_finishing_press;
+            app.prefs.finishing_hint = app.show_finishing_press;
+            app.puzzle.set_status(if app.show_finishing_press {
+                "Finishing presses will be highlighted.".to_string()
+            } else {
+                "Finishing press highlight off.".to_string()
+            });
+            true
+        }
+        KeyCode::F(12) if app.debug => {
+            app.puzzle.debug_solved = true;
+            // Held to the same caps as a played solve.
+            if debug_solve(&mut app.puzzle) {
+                check_solved(app);
+            }
+            true
+        }
+        KeyCode::F(11) if app.debug => {
+            match shortest_solution(
+                app.puzzle.current,
+                app.puzzle.target,
+                app.puzzle.cycle,
+                app.puzzle.rule,
+            ) {
+                Some(path) => {
+                    app.clipboard_pending = Some(
+                        path.iter()
+                            .map(|press| (press + 1).to_string())
+                            .collect::<Vec<_>>()
+                            .join(","),
+                    );
+                    app.puzzle
+                        .set_status(format!("Solution copied ({} moves).", path.len()));
+                }
+                None => app.puzzle.set_status("No solution from this state."),
+            }
+            true
+        }
+        KeyCode::Esc if app.active_drill.take().is_some() => {
+            app.phase = AppPhase::Drills;
+            app.drill_status = "Drill abandoned.".to_string();
+            true
+        }
+        KeyCode::Esc => {
+            app.should_quit = true;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// OSC 52 "set clipboard" sequence. The terminal does the copying, so it
+/// also works over SSH.
+fn osc52_copy(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", base64_encode(text.as_bytes()))
+}
+
+/// Standard padded base64, as OSC 52 expects.
+fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let word = chunk.iter().enumerate().fold(0u32, |word, (index, &byte)| {
+            word | u32::from(byte) << (16 - 8 * index)
+        });
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(ALPHABET[(word >> (18 - 6 * index) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Outside campaign mode any key skips the solved hold. A solved campaign
+/// stage stays on screen until `Enter` confirms it.
+fn handle_solved_key(app: &mut App, key: KeyEvent) -> bool {
+    match key.code {
+        KeyCode::Enter => {
+            complete_puzzle(app);
+            true
+        }
+        KeyCode::Esc if app.campaign.is_some() => {
+            app.should_quit = true;
+            true
+        }
+        _ if app.campaign.is_none() => {
+            complete_puzzle(app);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Next indicator to the right (`forward`) or left of `index`, wrapping. With
+/// `skip_matched`, indicators already on their target color are passed over;
+/// if none other needs work, focus stays where it is.
+fn step_focus(puzzle: &PuzzleState, index: usize, forward: bool, skip_matched: bool) -> usize {
+    let step = |offset: usize| {
+        if forward {
+            (index + offset) % INDICATOR_COUNT
+        } else {
+            (index + INDICATOR_COUNT - offset % INDICATOR_COUNT) % INDICATOR_COUNT
+        }
+    };
+    if !skip_matched {
+        return step(1);
+    }
+    (1..=INDICATOR_COUNT)
+        .map(step)
+        .find(|&candidate| puzzle.current[candidate] != puzzle.target[candidate])
+        .unwrap_or(index)
+}
+
+/// Maps a screen cell to the control drawn there, using the same geometry as
+/// `draw_puzzle_view` and `draw_email_view`.
+fn hit_test(app: &App, cols: u16, rows: u16, column: u16, row: u16) -> Option<ClickTarget> {
+    let frame = frame_layout(cols, rows)?;
+    let bottom = frame.body_y + frame.body_height - 1;
+    let view = view_columns(frame.x, frame.width);
+    match app.phase {
+        AppPhase::Puzzle => {
+            let indicator_y = indicator_row(frame.body_y);
+            let grid = indicator_grid(view.x, view.width, indicator_y, bottom, app.layout);
+            if indicator_y + 2 < bottom {
+                if let Some(index) = grid.hit(column, row) {
+                    return Some(ClickTarget::Indicator(index));
+                }
+            }
+            let action_y = grid.below + 1;
+            if action_y + 2 >= bottom {
+                return None;
+            }
+            button_hit(
+                &action_cells(view.x, view.width, action_y),
+                ACTION_WIDTH,
+                column,
+                row,
+            )
+            .map(ClickTarget::Action)
+        }
+        AppPhase::Email => {
+            let layout = email_layout(view.x, frame.body_y, view.width);
+            if button_hit(&[layout.field], layout.field_width, column, row).is_some() {
+                return Some(ClickTarget::EmailInput);
+            }
+            button_hit(&layout.buttons, layout.button_width, column, row)
+                .map(ClickTarget::EmailButton)
+        }
+        _ => None,
+    }
+}
+
+/// A left click focuses the control under the pointer and, for buttons,
+/// activates it exactly as `Enter` would.
+fn handle_mouse(app: &mut App, mouse: MouseEvent, cols: u16, rows: u16) -> io::Result<bool> {
+    if mouse.kind != MouseEventKind::Down(MouseButton::Left) || app.help.is_some() {
+        return Ok(false);
+    }
+    let enter = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+    match hit_test(app, cols, rows, mouse.column, mouse.row) {
+        Some(ClickTarget::Indicator(index)) => {
+            app.puzzle.focus = PuzzleFocus::Indicator(index);
+            handle_key(app, enter)
+        }
+        Some(ClickTarget::Action(index)) => {
+            app.puzzle.focus = PuzzleFocus::Action(index);
+            handle_key(app, enter)
+        }
+        Some(ClickTarget::EmailInput) => {
+            app.email.awaiting_confirmation = false;
+            app.email.focus = EmailFocus::Input;
+            Ok(true)
+        }
+        Some(ClickTarget::EmailButton(index)) => {
+            app.email.focus = EmailFocus::Buttons;
+            app.email.selected_button = index;
+            handle_key(app, enter)
+        }
+        None => Ok(false),
+    }
+}
+
+fn activate_puzzle_focus(app: &mut App) {
+    match app.puzzle.focus {
+        PuzzleFocus::Indicator(index) => {
+            let previous_distance = app.puzzle.distance_to_target;
+            apply_press(&mut app.puzzle, index);
+            app.puzzle.redo_stack.clear();
+            log_event(&format!(
+                "press {} state={}",
+                index + 1,
+                render_state_spec(app.puzzle.current)
+            ));
+            app.puzzle.set_status(format!(
+                "Pressed indicator {}. {}",
+                index + 1,
+                aligned_summary(&app.puzzle)
+            ));
+
+            let puzzle = &mut app.puzzle;
+            puzzle.distance_to_target =
+                solution_depth(puzzle.current, puzzle.target, puzzle.cycle, puzzle.rule)
+                    .unwrap_or(0);
+            if puzzle.distance_to_target > previous_distance {
+                puzzle.worsening_streak += 1;
+            } else {
+                puzzle.worsening_streak = 0;
+            }
+
+            // The path itself is only needed once the auto-hint fires.
+            if app
+                .auto_hint_after
+                .is_some_and(|threshold| puzzle.worsening_streak >= threshold)
+            {
+                if let Some(&next) =
+                    shortest_solution(puzzle.current, puzzle.target, puzzle.cycle, puzzle.rule)
+                        .as_ref()
+                        .and_then(|path| path.first())
+                {
+                    puzzle.worsening_streak = 0;
+                    puzzle.set_status(format!("Stuck? Try pressing indicator {}.", next + 1));
+                }
+            }
+        }
+        PuzzleFocus::Action(0) => {
+            if let Some(path) = shortest_solution(
+                app.puzzle.current,
+                app.puzzle.target,
+                app.puzzle.cycle,
+                app.puzzle.rule,
+            ) {
+                if !path.is_empty() {
+                    app.puzzle.set_status("Hint: Haha, there is no hint. But if there were, it would be think outside the terminal.");
+                } else {
+                    app.puzzle.set_status("State already matches target.");
+                }
+            } else {
+                app.puzzle.set_status("No hint available from this state.");
+            }
+        }
+        PuzzleFocus::Action(1) => {
+            reset_board(&mut app.puzzle);
+            app.puzzle.set_status(format!(
+                "Puzzle reset to original generated state. {}",
+                aligned_summary(&app.puzzle)
+            ));
+        }
+        PuzzleFocus::Action(2) => {
+            app.puzzle.show_rules = !app.puzzle.show_rules;
+            app.prefs.show_rules = app.puzzle.show_rules;
+            app.puzzle.rules_scroll = 0;
+            app.puzzle.set_status(if app.puzzle.show_rules {
+                "Rules expanded.".to_string()
+            } else {
+                "Rules collapsed.".to_string()
+            });
+        }
+        _ => {}
+    }
+
+    check_solved(app);
+}
+
+/// Enters the solved hold once the board matches the target, unless the
+/// solve went over the `BOAAI_MAX_MOVES_OVER_OPTIMAL` cap. A board that used
+/// up `BOAAI_MOVE_CAP` presses unsolved, or solved only past the cap (the
+/// debug `F12` solve can get there), is out of moves; a solve on the last
+/// allowed press still counts.
+fn check_solved(app: &mut App) {
+    let over_cap = app.move_cap.is_some_and(|cap| app.puzzle.moves_taken > cap);
+    if app.puzzle.current == app.puzzle.target && !over_cap {
+        if let Some(threshold) = app.max_moves_over_optimal {
+            if app.puzzle.moves_taken > app.puzzle.optimal_moves + threshold {
+                reset_board(&mut app.puzzle);
+                app.puzzle
+                    .set_status("Too many moves — try again for a cleaner solution.");
+                return;
+            }
+        }
+        enter_solved_hold(app);
+    } else if app
+        .move_cap
+        .is_some_and(|cap| app.puzzle.moves_taken >= cap)
+    {
+        app.phase = AppPhase::OutOfMoves;
+        app.puzzle
+            .set_status("Out of moves — press R to retry or Esc to quit");
+    }
+}
+
+/// Out of moves: `R` resets the board for another try and `U` takes back the
+/// last press, returning one move. Everything else waits.
+fn handle_out_of_moves_key(app: &mut App, key: KeyEvent) -> bool {
+    match key.code {
+        KeyCode::Char('r') | KeyCode::Char('R') => {
+            reset_board(&mut app.puzzle);
+            app.phase = AppPhase::Puzzle;
+            app.puzzle.set_status(format!(
+                "Board reset. {} moves to solve it.",
+                app.move_cap.unwrap_or_default()
+            ));
+            true
+        }
+        KeyCode::Char('u') | KeyCode::Char('U') => {
+            undo_press(&mut app.puzzle);
+            app.phase = AppPhase::Puzzle;
+            true
+        }
+        KeyCode::Esc => {
+            app.should_quit = true;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Walks the shortest route one press at a time, counting only presses that
+/// were applied and stopping as soon as the target is reached. Returns whether
+/// the board now matches the target.
+fn debug_solve(puzzle: &mut PuzzleState) -> bool {
+    let Some(path) = shortest_solution(puzzle.current, puzzle.target, puzzle.cycle, puzzle.rule)
+    else {
+        puzzle.set_status("Debug solve did not find a valid route.");
+        return false;
+    };
+
+    let mut applied = 0;
+    for &press in &path {
+        if puzzle.current == puzzle.target {
+            break;
+        }
+        apply_press(puzzle, press);
+        applied += 1;
+    }
+
+    let reached = puzzle.current == puzzle.target;
+    if reached {
+        puzzle.distance_to_target = 0;
+        puzzle.set_status(format!("Debug solve used {applied} move(s)."));
+    } else {
+        if let Some(remaining) =
+            solution_depth(puzzle.current, puzzle.target, puzzle.cycle, puzzle.rule)
+        {
+            puzzle.distance_to_target = remaining;
+        }
+        puzzle.set_status(format!(
+            "Debug solve stopped after {applied} move(s) without reaching the target."
+        ));
+    }
+    reached
+}
+
+/// Holds the finished board on screen briefly before `complete_puzzle` moves on.
+/// Reduced motion skips the timed hold; campaign stages still wait for `Enter`.
+fn enter_solved_hold(app: &mut App) {
+    app.phase = AppPhase::Solved;
+    app.solved_at = Some(Instant::now());
+    let elapsed = app.puzzle.started_at.elapsed();
+    app.puzzle.solved_in = Some(elapsed);
+    let score = compute_score(
+        app.puzzle.moves_taken,
+        app.puzzle.optimal_moves,
+        elapsed.as_secs(),
+        app.score_weights,
+    );
+    app.score = (!app.puzzle.scoring_restarted).then_some(score);
+    log_event(&format!(
+        "solve seed={} moves={} optimal={} solve_seconds={} score={score}",
+        app.puzzle
+            .seed
+            .map_or("-".to_string(), |seed| seed.to_string()),
+        app.puzzle.moves_taken,
+        app.puzzle.optimal_moves,
+        elapsed.as_secs()
+    ));
+    app.puzzle.set_status(format!(
+        "Solved! Target reached in {} move(s). Score {score}.",
+        app.puzzle.moves_taken
+    ));
+    app.bell_pending = app.bell;
+    if !app.reduced_motion {
+        app.flash_until = Some(Instant::now() + SOLVE_FLASH);
+    }
+    if app.reduced_motion && app.campaign.is_none() {
+        complete_puzzle(app);
+    }
+}
+
+/// Composite score: `SCORE_BASE - extra_moves * per_extra_move - secs * per_second`,
+/// clamped at zero, where `extra_moves` is how far the solve went over optimal.
+fn compute_score(moves: usize, optimal: usize, secs: u64, weights: ScoreWeights) -> u32 {
+    let extra_moves = moves.saturating_sub(optimal) as u64;
+    let penalty = extra_moves * u64::from(weights.per_extra_move)
+        + secs.saturating_mul(u64::from(weights.per_second));
+    u64::from(SCORE_BASE).saturating_sub(penalty) as u32
+}
+
+/// Presses `index` and records it in the move list and undo history.
+fn apply_press(puzzle: &mut PuzzleState, index: usize) {
+    puzzle.history.push(puzzle.current);
+    puzzle.current = press_indicator(puzzle.current, index, puzzle.cycle, puzzle.rule);
+    puzzle.moves.push(index);
+    puzzle.moves_taken += 1;
+    if puzzle.recent_presses.len() == RECENT_PRESSES {
+        puzzle.recent_presses.pop_front();
+    }
+    puzzle.recent_presses.push_back(index);
+}
+
+/// Steps back one press, as far as the starting position. Undoing never
+/// counts as a solve, even when the earlier board matched the target.
+fn undo_press(puzzle: &mut PuzzleState) -> bool {
+    let (Some(previous), Some(index)) = (puzzle.history.pop(), puzzle.moves.pop()) else {
+        puzzle.set_status("Nothing to undo.");
+        return false;
+    };
+    puzzle.current = previous;
+    puzzle.moves_taken = puzzle.moves_taken.saturating_sub(1);
+    puzzle.recent_presses.pop_back();
+    puzzle.redo_stack.push(index);
+    puzzle.worsening_streak = 0;
+    puzzle.distance_to_target =
+        solution_depth(puzzle.current, puzzle.target, puzzle.cycle, puzzle.rule).unwrap_or(0);
+    puzzle.set_status(format!(
+        "Undid move (indicator {}). {}",
+        index + 1,
+        aligned_summary(puzzle)
+    ));
+    true
+}
+
+/// Re-applies the most recently undone press. A redo that reaches the target
+/// is checked like any other solve.
+fn redo_press(app: &mut App) -> bool {
+    let Some(index) = app.puzzle.redo_stack.pop() else {
+        app.puzzle.set_status("Nothing to redo.");
+        return false;
+    };
+    apply_press(&mut app.puzzle, index);
+    app.puzzle.distance_to_target = solution_depth(
+        app.puzzle.current,
+        app.puzzle.target,
+        app.puzzle.cycle,
+        app.puzzle.rule,
+    )
+    .unwrap_or(0);
+    app.puzzle
+        .set_status(format!("Redid move (indicator {}).", index + 1));
+    check_solved(app);
+    true
+}
+
+fn reset_board(puzzle: &mut PuzzleState) {
+    puzzle.current = puzzle.initial;
+    puzzle.moves_taken = 0;
+    puzzle.moves.clear();
+    puzzle.history.clear();
+    puzzle.redo_stack.clear();
+    puzzle.recent_presses.clear();
+    puzzle.distance_to_target = puzzle.optimal_moves;
+    puzzle.worsening_streak = 0;
+    puzzle.locked = [false; INDICATOR_COUNT];
+}
+
+/// Makes the current board the new scoring baseline: counters and the reveal
+/// count restart and par is measured from here. The board itself is left
+/// untouched, and Reset returns to this position from now on. The run no
+/// longer competes; see `scoring_restarted`.
+fn restart_scoring(puzzle: &mut PuzzleState) {
+    puzzle.initial = puzzle.current;
+    puzzle.optimal_moves = puzzle.distance_to_target;
+    puzzle.moves_taken = 0;
+    puzzle.moves.clear();
+    puzzle.history.clear();
+    puzzle.redo_stack.clear();
+    puzzle.recent_presses.clear();
+    puzzle.worsening_streak = 0;
+    puzzle.started_at = Instant::now();
+    puzzle.solved_in = None;
+    puzzle.path_reveals = 0;
+    puzzle.scoring_restarted = true;
+}
+
+/// Routes a solved board: the next campaign stage, the configured campaign
+/// finale, or straight to the invite form outside campaign mode.
+fn complete_puzzle(app: &mut App) {
+    app.phase = AppPhase::Puzzle;
+    app.solved_at = None;
+    if let Some(index) = app.active_drill.take() {
+        let drill = &DRILLS[index];
+        app.phase = AppPhase::Drills;
+        app.drill_status = format!("{} done. {}", drill.name, drill.lesson);
+        return;
+    }
+    if let Some(run) = app.survival.as_mut() {
+        run.cleared += 1;
+        run.depth += run.depth_step;
+        start_survival_puzzle(app);
+        return;
+    }
+    if let Some(solved) = app.sandbox.as_mut() {
+        *solved += 1;
+        let moves = app.puzzle.moves_taken;
+        let show_rules = app.puzzle.show_rules;
+        app.puzzle = next_puzzle(app);
+        app.puzzle.show_rules = show_rules;
+        app.puzzle.set_status(format!(
+            "Solved in {moves} move{}. Here is another one.",
+            if moves == 1 { "" } else { "s" }
+        ));
+        return;
+    }
+    let Some(campaign) = app.campaign.as_mut() else {
+        transition_to_email(app);
+        return;
+    };
+
+    campaign.total_moves += app.puzzle.moves_taken;
+    if campaign.stage + 1 < campaign.stages {
+        campaign.stage += 1;
+        let cleared = campaign.stage;
+        let next_stage = campaign.stage + 1;
+        app.puzzle = next_puzzle(app);
+        app.puzzle.set_status(format!(
+            "Stage {cleared} cleared. Stage {next_stage} begins."
+        ));
+        return;
+    }
+
+    match campaign.finale {
+        CampaignFinale::Email => {
+            let summary = campaign.summary();
+            transition_to_email(app);
+            app.email
+                .set_status(format!("{summary}. Enter your email, then confirm invite."));
+        }
+        CampaignFinale::Victory => app.phase = AppPhase::Victory,
+        CampaignFinale::Loop => {
+            let summary = campaign.summary();
+            campaign.stage = 0;
+            campaign.laps += 1;
+            let laps = campaign.laps;
+            app.puzzle = next_puzzle(app);
+            app.puzzle
+                .set_status(format!("{summary}. Lap {laps} done, back to stage 1."));
+        }
+    }
+}
+
+fn transition_to_email(app: &mut App) {
+    app.phase = AppPhase::Email;
+    app.solve_secs = Some(solve_clock(&app.puzzle).as_secs());
+    app.email = EmailState {
+        email: String::new(),
+        focus: EmailFocus::Input,
+        selected_button: 0,
+        status: String::new(),
+        suggestion: None,
+        awaiting_confirmation: false,
+    };
+    app.email
+        .set_status("Puzzle solved. Enter your email, then confirm invite.");
+}
+
+fn handle_email_key(app: &mut App, key: KeyEvent) -> io::Result<bool> {
+    if key.code == KeyCode::F(2) {
+        if let Some(suggestion) = app.email.suggestion.take() {
+            app.email
+                .set_status(format!("Using {suggestion}. Confirm when ready."));
+            app.email.email = suggestion;
+            return Ok(true);
+        }
+    }
+
+    if app.email.awaiting_confirmation {
+        match key.code {
+            KeyCode::Enter if app.email.selected_button == 0 => return Ok(submit_email(app)),
+            // A click on Solve Again arrives as Enter with that button selected.
+            KeyCode::Enter => app.email.awaiting_confirmation = false,
+            KeyCode::Esc => {
+                app.email.awaiting_confirmation = false;
+                app.email.focus = EmailFocus::Input;
+                app.email
+                    .set_status("Edit your email, then confirm invite again.");
+                return Ok(true);
+            }
+            _ => return Ok(false),
+        }
+    }
+
+    match app.email.focus {
+        EmailFocus::Input => match key.code {
+            KeyCode::Tab | KeyCode::Down | KeyCode::Enter => {
+                app.email.focus = EmailFocus::Buttons;
+                Ok(true)
+            }
+            KeyCode::Backspace => {
+                app.email.email.pop();
+                app.email.suggestion = None;
+                Ok(true)
+            }
+            KeyCode::Char(c) => {
+                if is_email_char(c) && app.email.email.len() < EMAIL_MAX_LEN {
+                    app.email.email.push(c);
+                    app.email.status.clear();
+                    app.email.suggestion = None;
+                    return Ok(true);
+                }
+                Ok(false)
+            }
+            KeyCode::Esc => {
+                app.should_quit = true;
+                Ok(true)
+            }
+            _ => Ok(false),
+        },
+        EmailFocus::Buttons => match key.code {
+            KeyCode::Tab | KeyCode::Up => {
+                app.email.focus = EmailFocus::Input;
+                Ok(true)
+            }
+            KeyCode::Left | KeyCode::Right => {
+                app.email.selected_button = 1 - app.email.selected_button;
+                Ok(true)
+            }
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                if app.email.selected_button == 0 {
+                    if !is_valid_email(&app.email.email) {
+                        app.email.suggestion = if email_suggestions_enabled() {
+                            suggest_email_fix(&app.email.email)
+                        } else {
+                            None
+                        };
+                        app.email.set_status(match &app.email.suggestion {
+                            Some(suggestion) => {
+                                format!("Did you mean {suggestion}? Press F2 to accept.")
+                            }
+                            None => "Please enter a valid email before confirming.".to_string(),
+                        });
+                        return Ok(true);
+                    }
+
+                    app.email.awaiting_confirmation = true;
+                    app.email
+                        .set_status("Submit this email? (Enter to confirm, Esc to edit)");
+                    return Ok(true);
+                }
+
+                if app.survival.is_some() {
+                    start_survival(app, survival_from_env());
+                    return Ok(true);
+                }
+                app.puzzle = next_puzzle(app);
+                app.campaign = app.campaign_plan.map(CampaignPlan::start);
+                app.phase = AppPhase::Puzzle;
+                Ok(true)
+            }
+            KeyCode::Esc => {
+                app.should_quit = true;
+                Ok(true)
+            }
+            _ => Ok(false),
+        },
+    }
+}
+
+/// Appends pasted text to the email field through the same character filter
+/// and length cap as typing. Pastes outside the email form are ignored.
+fn handle_paste(app: &mut App, text: &str) -> bool {
+    if !matches!(app.phase, AppPhase::Email)
+        || app.email.awaiting_confirmation
+        || app.help.is_some()
+    {
+        return false;
+    }
+    let before = app.email.email.len();
+    for c in text.chars().filter(|&c| is_email_char(c)) {
+        if app.email.email.len() >= EMAIL_MAX_LEN {
+            break;
+        }
+        app.email.email.push(c);
+    }
+    if app.email.email.len() == before {
+        return false;
+    }
+    app.email.focus = EmailFocus::Input;
+    app.email.status.clear();
+    app.email.suggestion = None;
+    true
+}
+
+/// Writes the confirmed submission and moves on to the Submitted screen.
+fn submit_email(app: &mut App) -> bool {
+    app.email.awaiting_confirmation = false;
+    if app.dry_run {
+        log_event(&format!("submit dry run email={}", app.email.email));
+        app.submitted_email = Some(app.email.email.clone());
+        app.phase = AppPhase::Submitted;
+        return true;
+    }
+    let outcome = match store_submission(
+        &SubmissionRecord::now(app),
+        &mut app.registered_emails,
+        app.invite_file.as_deref(),
+    ) {
+        Ok(outcome) => outcome,
+        // Stay on the form so the player can retry once the operator has
+        // fixed the destination.
+        Err(error) => {
+            log_event(&format!("submit failed: {error}"));
+            app.email.set_status(format!(
+                "Could not save submission (check file permissions): {}.",
+                error.kind()
+            ));
+            return true;
+        }
+    };
+    let SubmitOutcome::Stored(path) = outcome else {
+        log_event("submit rejected: email already registered");
+        app.email.set_status("This email is already registered.");
+        return true;
+    };
+    app.last_submission_path = path;
+    log_event(&format!(
+        "submit email={} seed={} solve_seconds={} score={}",
+        app.email.email,
+        app.puzzle
+            .seed
+            .map_or("-".to_string(), |seed| seed.to_string()),
+        app.solve_secs
+            .map_or("-".to_string(), |secs| secs.to_string()),
+        app.score.map_or("-".to_string(), |score| score.to_string())
+    ));
+    let entry = LeaderboardEntry {
+        email: app.email.email.clone(),
+        solve_seconds: app.solve_secs.unwrap_or_default(),
+        moves_taken: app.puzzle.moves_taken,
+        score: app.score.unwrap_or_default(),
+    };
+    app.leaderboard_status = if app.puzzle.scoring_restarted {
+        "Not ranked: scoring was restarted mid-puzzle.".to_string()
+    } else {
+        match record_leaderboard(&app.leaderboard_file, entry) {
+            Ok(()) => String::new(),
+            Err(error) => format!("Could not update leaderboard: {error}"),
+        }
+    };
+    if let Some(path) = &app.save_path {
+        let _ = fs::remove_file(path);
+    }
+    app.submitted_email = Some(app.email.email.clone());
+    app.phase = AppPhase::Submitted;
+    true
+}
+
+fn handle_submitted_key(app: &mut App, key: KeyEvent) -> bool {
+    match key.code {
+        KeyCode::Char('r') | KeyCode::Char('R') if !app.puzzle.moves.is_empty() => {
+            let mut replay = SolutionReplay {
+                state: app.puzzle.initial,
+                step: 0,
+                last_step: Instant::now(),
+            };
+            if app.reduced_motion {
+                replay.state = app.puzzle.moves.iter().fold(replay.state, |state, &press| {
+                    press_indicator(state, press, app.puzzle.cycle, app.puzzle.rule)
+                });
+                replay.step = app.puzzle.moves.len();
+            }
+            app.solution_replay = Some(replay);
+            true
+        }
+        KeyCode::Char('l') | KeyCode::Char('L') if matches!(app.phase, AppPhase::Submitted) => {
+            match load_leaderboard(&app.leaderboard_file) {
+                Ok(entries) => app.leaderboard = entries,
+                Err(error) => {
+                    app.leaderboard_status = format!("Could not read leaderboard: {error}")
+                }
+            }
+            app.phase = AppPhase::Leaderboard;
+            true
+        }
+        KeyCode::Char('s') | KeyCode::Char('S') if matches!(app.phase, AppPhase::Submitted) => {
+            open_stats(app);
+            true
+        }
+        KeyCode::Esc | KeyCode::Enter => {
+            app.should_quit = true;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Reads the invite file into `app.stats`. Only the file sink is read back:
+/// a pipe or command destination has nothing to count, and opening a FIFO
+/// would block.
+fn open_stats(app: &mut App) {
+    app.stats = SubmissionStats::default();
+    app.stats_status.clear();
+    let sink = env::var("BOAAI_SINK").unwrap_or_default();
+    if matches!(sink.trim().to_ascii_lowercase().as_str(), "" | "file") {
+        let format = invite_format_from_env();
+        let path = invite_path(format, app.invite_file.as_deref());
+        match load_submissions(&path, format) {
+            Ok(records) => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                app.stats = submission_stats(&records, now);
+            }
+            Err(error) => app.stats_status = format!("Could not read {}: {error}", path.display()),
+        }
+    } else {
+        app.stats_status = format!(
+            "Stats are read from the invite file; BOAAI_SINK={} has none.",
+            sink.trim()
+        );
+    }
+    app.phase = AppPhase::Stats;
+}
+
+fn handle_stats_key(app: &mut App, key: KeyEvent) -> bool {
+    match key.code {
+        KeyCode::Char('s') | KeyCode::Char('S') | KeyCode::Enter | KeyCode::Esc => {
+            app.phase = AppPhase::Submitted;
+            true
+        }
+        _ => false,
+    }
+}
+
+fn handle_help_key(app: &mut App, key: KeyEvent) -> bool {
+    let Some(scroll) = app.help else {
+        return false;
+    };
+    let last = help_lines(app.phase).len().saturating_sub(1);
+    app.help = match key.code {
+        KeyCode::Char('?') | KeyCode::Esc => None,
+        KeyCode::Up | KeyCode::PageUp => Some(scroll.saturating_sub(1)),
+        KeyCode::Down | KeyCode::PageDown => Some((scroll + 1).min(last)),
+        _ => return false,
+    };
+    true
+}
+
+fn handle_leaderboard_key(app: &mut App, key: KeyEvent) -> bool {
+    match key.code {
+        KeyCode::Char('l') | KeyCode::Char('L') | KeyCode::Enter | KeyCode::Esc => {
+            app.phase = AppPhase::Submitted;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Writes the puzzle in the replay file format: the recorded moves from
+/// `initial` rebuild the current board, so the save also plays with `--replay`.
+fn save_puzzle(
+    path: &Path,
+    puzzle: &PuzzleState,
+    difficulty: Option<Difficulty>,
+) -> io::Result<()> {
+    save_replay(
+        path,
+        &Replay {
+            seed: puzzle.seed.unwrap_or_default(),
+            cycle: puzzle.cycle,
+            rule: puzzle.rule,
+            initial: puzzle.initial,
+            target: puzzle.target,
+            moves: puzzle.moves.clone(),
+            difficulty,
+        },
+    )
+}
+
+/// Saves after every change to the main puzzle when `BOAAI_SAVE_FILE` is set.
+fn autosave(app: &mut App) {
+    let Some(path) = &app.save_path else {
+        return;
+    };
+    if app.puzzle.seed.is_none() {
+        return;
+    }
+    if let Err(error) = save_puzzle(path, &app.puzzle, app.difficulty) {
+        app.puzzle
+            .set_status(format!("Could not autosave puzzle: {error}"));
+    }
+}
+
+/// Restores a save that fits this run: same colors and press rule, the `BOAAI_TARGET` if one
+/// is set, and not already solved. Anything else starts a fresh puzzle. Also
+/// returns the difficulty the save was played at.
+fn resume_puzzle(
+    path: &Path,
+    cycle: ColorCycle,
+    rule: PressRule,
+    fixed_target: Option<[NodeColor; INDICATOR_COUNT]>,
+) -> Option<(PuzzleState, Option<Difficulty>)> {
+    let save = load_replay(path).ok()?;
+    if save.cycle != cycle
+        || save.rule != rule
+        || fixed_target.is_some_and(|target| target != save.target)
+    {
+        return None;
+    }
+    let mut puzzle = puzzle_state_for(save.initial, save.target, cycle, rule).ok()?;
+    for &press in &save.moves {
+        apply_press(&mut puzzle, press);
+    }
+    if puzzle.current == puzzle.target {
+        return None;
+    }
+    puzzle.distance_to_target = solution_depth(puzzle.current, puzzle.target, cycle, rule)?;
+    puzzle.seed = Some(save.seed);
+    puzzle.set_status("Resumed saved puzzle");
+    Some((puzzle, save.difficulty))
+}
+
+/// Everything about the puzzle comes from `seed`: with `random_start` the
+/// initial board is `start` scrambled by `START_SCRAMBLE_PRESSES` presses,
+/// which keeps it reachable, and the target is generated from there unless
+/// `fixed_target` pins it.
+fn seeded_puzzle_state(
+    seed: u64,
+    start: [NodeColor; INDICATOR_COUNT],
+    cycle: ColorCycle,
+    rule: PressRule,
+    random_start: bool,
+    fixed_target: Option<[NodeColor; INDICATOR_COUNT]>,
+) -> PuzzleState {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut initial = start;
+    if random_start {
+        loop {
+            initial =
+                generate_target_with_presses(&mut rng, start, cycle, rule, START_SCRAMBLE_PRESSES)
+                    .0;
+            if Some(initial) != fixed_target {
+                break;
+            }
+        }
+    }
+    let mut puzzle = match fixed_target {
+        Some(target) => puzzle_state_for(initial, target, cycle, rule)
+            .expect("BOAAI_TARGET is checked to be reachable from the start"),
+        None => new_puzzle_state_from_rng(&mut rng, initial, cycle, rule),
+    };
+    puzzle.seed = Some(seed);
+    puzzle.set_status(format!("Seed {seed}. Good luck"));
+    puzzle
+}
+
+/// The next seeded puzzle of this session.
+fn next_puzzle(app: &mut App) -> PuzzleState {
+    let seed = app.next_seed;
+    app.next_seed = seed.wrapping_add(1);
+    match app.difficulty {
+        Some(difficulty) => difficulty_puzzle_state(
+            seed,
+            app.start,
+            app.cycle,
+            app.rule,
+            app.fixed_target,
+            difficulty,
+        ),
+        None => seeded_puzzle_state(
+            seed,
+            app.start,
+            app.cycle,
+            app.rule,
+            app.random_start,
+            app.fixed_target,
+        ),
+    }
+}
+
+/// A seeded puzzle whose starting board is the target scrambled by the
+/// difficulty's press count. Every press can be undone by pressing the same
+/// indicator again enough times, so the start is always solvable.
+fn difficulty_puzzle_state(
+    seed: u64,
+    start: [NodeColor; INDICATOR_COUNT],
+    cycle: ColorCycle,
+    rule: PressRule,
+    fixed_target: Option<[NodeColor; INDICATOR_COUNT]>,
+    difficulty: Difficulty,
+) -> PuzzleState {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let target = fixed_target
+        .unwrap_or_else(|| generate_random_target_from_start(&mut rng, start, cycle, rule).0);
+    let initial =
+        generate_target_with_presses(&mut rng, target, cycle, rule, difficulty.presses()).0;
+    let mut puzzle = puzzle_state_for(initial, target, cycle, rule)
+        .expect("a scrambled target is reachable by construction");
+    puzzle.seed = Some(seed);
+    puzzle.set_status(format!("Seed {seed}. Good luck"));
+    puzzle
+}
+
+/// Regenerates the unplayed first puzzle at `difficulty` (keeping its seed)
+/// and starts play.
+fn choose_difficulty(app: &mut App, difficulty: Difficulty) {
+    let seed = app.puzzle.seed.unwrap_or(app.next_seed);
+    let show_rules = app.puzzle.show_rules;
+    app.difficulty = Some(difficulty);
+    app.difficulty_selected = Difficulty::ALL
+        .iter()
+        .position(|&choice| choice == difficulty)
+        .unwrap_or(0);
+    app.puzzle = difficulty_puzzle_state(
+        seed,
+        app.start,
+        app.cycle,
+        app.rule,
+        app.fixed_target,
+        difficulty,
+    );
+    app.puzzle.show_rules = show_rules;
+    app.phase = AppPhase::Puzzle;
+}
+
+/// Replaces the first puzzle with one starting from a shared board (see
+/// `--code`), keeping its seed so the target is generated as usual. A pinned
+/// target the shared board cannot use is dropped with a note on the status
+/// line.
+fn start_from_code(app: &mut App, initial: [NodeColor; INDICATOR_COUNT]) {
+    let seed = app.puzzle.seed.unwrap_or(app.next_seed);
+    let show_rules = app.puzzle.show_rules;
+    let dropped = app.fixed_target.and_then(|target| {
+        if target == initial {
+            Some("it equals the shared board")
+        } else if solution_depth(initial, target, app.cycle, app.rule).is_none() {
+            Some("it is unreachable from the shared board")
+        } else {
+            None
+        }
+    });
+    let fixed_target = app.fixed_target.filter(|_| dropped.is_none());
+    app.puzzle = seeded_puzzle_state(seed, initial, app.cycle, app.rule, false, fixed_target);
+    app.puzzle.show_rules = show_rules;
+    if let Some(reason) = dropped {
+        app.puzzle.set_status(format!(
+            "BOAAI_TARGET ignored ({reason}); using a generated target."
+        ));
+    }
+    app.phase = AppPhase::Puzzle;
+}
+
+fn new_puzzle_state_from_rng<R: Rng + ?Sized>(
+    rng: &mut R,
+    initial: [NodeColor; INDICATOR_COUNT],
+    cycle: ColorCycle,
+    rule: PressRule,
+) -> PuzzleState {
+    let (target, _generated_sequence) =
+        generate_random_target_from_start(rng, initial, cycle, rule);
+    puzzle_state_for(initial, target, cycle, rule)
+        .expect("generated targets are reachable by construction")
+}
+
+/// Builds a fresh puzzle from `initial` towards `target`, refusing targets the
+/// press rules can never reach so they never present as trivially solved.
+fn puzzle_state_for(
+    initial: [NodeColor; INDICATOR_COUNT],
+    target: [NodeColor; INDICATOR_COUNT],
+    cycle: ColorCycle,
+    rule: PressRule,
+) -> Result<PuzzleState, ConfigError> {
+    cycle.check_state(initial)?;
+    cycle.check_state(target)?;
+    let optimal_moves = solution_depth(initial, target, cycle, rule)
+        .ok_or(ConfigError::UnreachableTarget { initial, target })?;
+
+    Ok(PuzzleState {
+        cycle,
+        rule,
+        initial,
+        target,
+        current: initial,
+        optimal_moves,
+        moves_taken: 0,
+        moves: Vec::new(),
+        history: Vec::new(),
+        redo_stack: Vec::new(),
+        recent_presses: VecDeque::new(),
+        bookmark: None,
+        distance_to_target: optimal_moves,
+        worsening_streak: 0,
+        focus: PuzzleFocus::Indicator(0),
+        locked: [false; INDICATOR_COUNT],
+        debug_solved: false,
+        scoring_restarted: false,
+        show_rules: false,
+        rules_scroll: 0,
+        status: "Good luck".to_string(),
+        started_at: Instant::now(),
+        solved_in: None,
+        seed: None,
+        revealed_path: None,
+        path_reveals: 0,
+    })
+}
+
+/// Reveals the whole remaining shortest solution, drawn under the status
+/// line until the board changes. Each reveal is counted.
+fn reveal_full_path(puzzle: &mut PuzzleState) {
+    match shortest_solution(puzzle.current, puzzle.target, puzzle.cycle, puzzle.rule) {
+        Some(path) if path.is_empty() => puzzle.set_status("State already matches target."),
+        Some(path) => {
+            puzzle.path_reveals += 1;
+            puzzle.set_status(format!(
+                "Full path revealed ({} press(es), reveal #{}).",
+                path.len(),
+                puzzle.path_reveals
+            ));
+            puzzle.revealed_path = Some((puzzle.current, path));
+        }
+        None => puzzle.set_status("No hint available from this state."),
+    }
+}
+
+/// Lays out "Path: 1 → 2 → 3" in lines of at most `width` characters,
+/// breaking only between presses.
+fn path_lines(path: &[usize], width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = "Path:".to_string();
+    for (step, index) in path.iter().enumerate() {
+        let token = if step == 0 {
+            format!(" {}", index + 1)
+        } else {
+            format!(" → {}", index + 1)
+        };
+        if line.chars().count() + token.chars().count() > width && !line.trim().is_empty() {
+            lines.push(line);
+            line = String::new();
+        }
+        line.push_str(&token);
+    }
+    lines.push(line);
+    lines
+        .into_iter()
+        .map(|line| trim_to_width(line.trim_start(), width))
+        .collect()
+}
+
+/// How many positions of `current` already show their target color.
+fn matches_count(
+    current: [NodeColor; INDICATOR_COUNT],
+    target: [NodeColor; INDICATOR_COUNT],
+) -> usize {
+    current
+        .iter()
+        .zip(target)
+        .filter(|(color, goal)| **color == *goal)
+        .count()
+}
+
+fn aligned_summary(puzzle: &PuzzleState) -> String {
+    format!(
+        "{}/{INDICATOR_COUNT} nodes aligned.",
+        matches_count(puzzle.current, puzzle.target)
+    )
+}
+
+/// Explains the position furthest from its target color and the press that
+/// brings that one position closest. Ties go to the lowest index.
+fn worst_position_hint(puzzle: &PuzzleState) -> String {
+    let (cycle, rule) = (puzzle.cycle, puzzle.rule);
+    let distance_at = |state: [NodeColor; INDICATOR_COUNT], index: usize| {
+        cycle.steps_between(state[index], puzzle.target[index])
+    };
+
+    let mut worst = 0;
+    for index in 1..INDICATOR_COUNT {
+        if distance_at(puzzle.current, index) > distance_at(puzzle.current, worst) {
+            worst = index;
+        }
+    }
+    let distance = distance_at(puzzle.current, worst);
+    if distance == 0 {
+        return "All positions on target.".to_string();
+    }
+
+    let (press, after) = (0..INDICATOR_COUNT)
+        .map(|press| {
+            let after = distance_at(press_indicator(puzzle.current, press, cycle, rule), worst);
+            (press, after)
+        })
+        .min_by_key(|(_, after)| *after)
+        .expect("at least one indicator");
+
+    let lead = format!(
+        "Indicator {} is furthest off: {} step{} from {}.",
+        worst + 1,
+        distance,
+        if distance == 1 { "" } else { "s" },
+        puzzle.target[worst].as_str()
+    );
+    if after < distance {
+        format!("{lead} Pressing {} brings it to {after}.", press + 1)
+    } else {
+        format!("{lead} No single press brings it closer.")
+    }
+}
+
+/// How far indicator `index` is from its target color around the cycle, as
+/// `↑n` (n `next()` steps forward) or `↓n` when going back is shorter. `None`
+/// once it matches.
+fn target_overlay(puzzle: &PuzzleState, index: usize) -> Option<String> {
+    let forward = puzzle
+        .cycle
+        .steps_between(puzzle.current[index], puzzle.target[index]);
+    let backward = puzzle.cycle.len() - forward;
+    match forward {
+        0 => None,
+        _ if forward <= backward => Some(format!(" ↑{forward} ")),
+        _ => Some(format!(" ↓{backward} ")),
+    }
+}
+
+/// Marks each indicator whose single press would solve the puzzle.
+fn finishing_presses(puzzle: &PuzzleState) -> [bool; INDICATOR_COUNT] {
+    let mut finishing = [false; INDICATOR_COUNT];
+    if puzzle.current == puzzle.target {
+        return finishing;
+    }
+    for (index, slot) in finishing.iter_mut().enumerate() {
+        *slot = press_indicator(puzzle.current, index, puzzle.cycle, puzzle.rule) == puzzle.target;
+    }
+    finishing
+}
+
+/// Columns of a CSV invite file, written as its first line.
+const CSV_HEADER: &str = "submitted_unix,email,score,solve_seconds,session_id,moves";
+
+/// One confirmed invite, encoded per `BOAAI_INVITE_FORMAT`.
+///
+/// `session_id` and then `moves` were added after the first release as
+/// trailing CSV columns and JSON keys, so readers that take the first four
+/// columns keep working. Records written before them read back with `None`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct SubmissionRecord {
+    submitted_unix: u64,
+    email: String,
+    score: Option<u32>,
+    solve_seconds: Option<u64>,
+    /// `App::session_id` of the session that submitted.
+    session_id: Option<String>,
+    /// The 0-based presses of the solve in order, written 1-based as
+    /// `"1,2,2,3,5"` (quoted in CSV). `None` for debug solves.
+    moves: Option<Vec<usize>>,
+}
+
+impl SubmissionRecord {
+    fn now(app: &App) -> Self {
+        Self {
+            submitted_unix: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            email: app.email.email.clone(),
+            score: app.score,
+            solve_seconds: app.solve_secs,
+            session_id: Some(app.session_id.clone()),
+            moves: (!app.puzzle.debug_solved && !app.puzzle.scoring_restarted)
+                .then(|| app.puzzle.moves.clone()),
+        }
+    }
+
+    fn moves_field(&self) -> Option<String> {
+        self.moves.as_ref().map(|moves| {
+            moves
+                .iter()
+                .map(|press| (press + 1).to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+    }
+
+    fn to_csv_row(&self) -> String {
+        let score = self
+            .score
+            .map(|score| score.to_string())
+            .unwrap_or_default();
+        let secs = self
+            .solve_seconds
+            .map(|secs| secs.to_string())
+            .unwrap_or_default();
+        let moves = self
+            .moves_field()
+            .map(|moves| format!("\"{moves}\""))
+            .unwrap_or_default();
+        format!(
+            "{},{},{score},{secs},{},{moves}",
+            self.submitted_unix,
+            self.email,
+            self.session_id.as_deref().unwrap_or_default()
+        )
+    }
+
+    fn to_json_line(&self) -> String {
+        let score = self
+            .score
+            .map_or("null".to_string(), |score| score.to_string());
+        let secs = self
+            .solve_seconds
+            .map_or("null".to_string(), |secs| secs.to_string());
+        let session = self
+            .session_id
+            .as_ref()
+            .map_or("null".to_string(), |id| format!("\"{}\"", json_escape(id)));
+        let moves = self
+            .moves_field()
+            .map_or("null".to_string(), |moves| format!("\"{moves}\""));
+        format!(
+            "{{\"submitted_unix\":{},\"email\":\"{}\",\"score\":{score},\"solve_seconds\":{secs},\"session_id\":{session},\"moves\":{moves}}}",
+            self.submitted_unix,
+            json_escape(&self.email)
+        )
+    }
+
+    fn encode(&self, format: InviteFormat) -> String {
+        match format {
+            InviteFormat::Csv => self.to_csv_row(),
+            InviteFormat::Json => self.to_json_line(),
+        }
+    }
+
+    /// Reads back one line written by `encode`. The CSV header and malformed
+    /// lines give `None`. Valid emails never need JSON escapes, so the email
+    /// runs to the next quote.
+    fn parse(line: &str, format: InviteFormat) -> Option<Self> {
+        fn parse_moves(field: &str) -> Option<Vec<usize>> {
+            field
+                .split(',')
+                .map(|press| press.trim().parse::<usize>().ok()?.checked_sub(1))
+                .collect()
+        }
+
+        match format {
+            InviteFormat::Csv => {
+                // The quoted moves column is last, so its commas stay in the
+                // final field.
+                let mut fields = line.trim().splitn(6, ',');
+                Some(Self {
+                    submitted_unix: fields.next()?.parse().ok()?,
+                    email: fields.next()?.to_string(),
+                    score: fields.next().and_then(|score| score.parse().ok()),
+                    solve_seconds: fields.next().and_then(|secs| secs.parse().ok()),
+                    session_id: fields
+                        .next()
+                        .filter(|id| !id.is_empty())
+                        .map(str::to_string),
+                    moves: fields
+                        .next()
+                        .and_then(|moves| parse_moves(moves.trim_matches('"'))),
+                })
+            }
+            InviteFormat::Json => {
+                let value = |key: &str| {
+                    let start = line.find(&format!("\"{key}\":"))? + key.len() + 3;
+                    let rest = &line[start..];
+                    match rest.strip_prefix('"') {
+                        Some(quoted) => quoted.split('"').next(),
+                        None => rest.split([',', '}']).next().map(str::trim),
+                    }
+                };
+                Some(Self {
+                    submitted_unix: value("submitted_unix")?.parse().ok()?,
+                    email: value("email")?.to_string(),
+                    score: value("score").and_then(|score| score.parse().ok()),
+                    solve_seconds: value("solve_seconds").and_then(|secs| secs.parse().ok()),
+                    session_id: value("session_id")
+                        .filter(|id| *id != "null")
+                        .map(str::to_string),
+                    moves: value("moves").and_then(parse_moves),
+                })
+            }
+        }
+    }
+}
+
+/// Format of an invite file named `*.csv` or `*.jsonl`.
+fn invite_format_for(path: &Path) -> Option<InviteFormat> {
+    match path.extension()?.to_str()? {
+        extension if extension.eq_ignore_ascii_case("csv") => Some(InviteFormat::Csv),
+        extension if extension.eq_ignore_ascii_case("jsonl") => Some(InviteFormat::Json),
+        _ => None,
+    }
+}
+
+/// Totals reported by `--convert`.
+#[derive(Debug, PartialEq, Eq)]
+struct Conversion {
+    records: usize,
+    /// Non-blank lines (other than the CSV header) that did not parse.
+    skipped: usize,
+}
+
+/// `--convert`: rewrites the invite file `input` in the format named by
+/// `output`'s extension. An existing `output` is never overwritten.
+fn convert_submissions(input: &Path, output: &Path) -> io::Result<Conversion> {
+    let format_of = |path: &Path| {
+        invite_format_for(path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not a .csv or .jsonl file", path.display()),
+            )
+        })
+    };
+    let (from, to) = (format_of(input)?, format_of(output)?);
+
+    let mut records = Vec::new();
+    let mut skipped = 0;
+    for (index, line) in fs::read_to_string(input)?.lines().enumerate() {
+        let header = index == 0 && from == InviteFormat::Csv && line.starts_with("submitted_unix,");
+        if line.trim().is_empty() || header {
+            continue;
+        }
+        match SubmissionRecord::parse(line, from) {
+            Some(record) => records.push(record),
+            None => skipped += 1,
+        }
+    }
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(output)?;
+    if to == InviteFormat::Csv {
+        writeln!(file, "{CSV_HEADER}")?;
+    }
+    for record in &records {
+        writeln!(file, "{}", record.encode(to))?;
+    }
+    Ok(Conversion {
+        records: records.len(),
+        skipped,
+    })
+}
+
+/// Every record in an invite file, skipping lines that do not parse. A
+/// missing file has no records.
+fn load_submissions(path: &Path, format: InviteFormat) -> io::Result<Vec<SubmissionRecord>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(contents
+            .lines()
+            .filter_map(|line| SubmissionRecord::parse(line, format))
+            .collect()),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(error) => Err(error),
+    }
+}
+
+/// Aggregates for the stats view.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct SubmissionStats {
+    total: usize,
+    /// Records sharing `now`'s UTC day.
+    today: usize,
+    latest: Option<SubmissionRecord>,
+}
+
+fn submission_stats(records: &[SubmissionRecord], now_unix: u64) -> SubmissionStats {
+    let today = now_unix / 86_400;
+    SubmissionStats {
+        total: records.len(),
+        today: records
+            .iter()
+            .filter(|record| record.submitted_unix / 86_400 == today)
+            .count(),
+        latest: records
+            .iter()
+            .max_by_key(|record| record.submitted_unix)
+            .cloned(),
+    }
+}
+
+fn json_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Line format of submission records, from `BOAAI_INVITE_FORMAT=csv|json`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum InviteFormat {
+    Csv,
+    Json,
+}
+
+fn invite_format_from_env() -> InviteFormat {
+    match env::var("BOAAI_INVITE_FORMAT").unwrap_or_default().trim() {
+        format if format.eq_ignore_ascii_case("json") => InviteFormat::Json,
+        _ => InviteFormat::Csv,
+    }
+}
+
+/// Destination for confirmed invite submissions. Each record is handed over
+/// as one encoded line (a CSV row in `CSV_HEADER` order or a JSON object) without a trailing newline.
+trait SubmissionSink {
+    fn submit(&mut self, record: &str) -> io::Result<()>;
+
+    /// Normalized emails already recorded at this destination, for sinks
+    /// that can be read back.
+    fn registered_emails(&self) -> io::Result<HashSet<String>> {
+        Ok(HashSet::new())
+    }
+
+    /// Where records end up, for sinks that write to a path.
+    fn path(&self) -> Option<&Path> {
+        None
+    }
+}
+
+/// Appends to a file, writing the CSV header when a CSV file is new. JSON
+/// lines files get no header.
+struct FileSink {
+    path: PathBuf,
+    format: InviteFormat,
+}
+
+impl SubmissionSink for FileSink {
+    fn submit(&mut self, record: &str) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+
+        let file_exists = self.path.exists();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+
+        if !file_exists && self.format == InviteFormat::Csv {
+            writeln!(file, "{CSV_HEADER}")?;
+        }
+        writeln!(file, "{record}")
+    }
+
+    fn registered_emails(&self) -> io::Result<HashSet<String>> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(HashSet::new()),
+            Err(error) => return Err(error),
+        };
+        Ok(contents
+            .lines()
+            .filter_map(|line| match self.format {
+                InviteFormat::Csv => line.split(',').nth(1),
+                InviteFormat::Json => line
+                    .split_once("\"email\":\"")
+                    .and_then(|(_, rest)| rest.split('"').next()),
+            })
+            .filter(|email| *email != "email")
+            .map(normalize_email)
+            .collect())
+    }
+
+    fn path(&self) -> Option<&Path> {
+        Some(&self.path)
+    }
+}
+
+/// Case- and whitespace-insensitive form used to spot repeat submissions.
+fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum SubmitOutcome {
+    /// Recorded, at the sink's path when it has one.
+    Stored(Option<PathBuf>),
+    Duplicate,
+}
+
+/// Writes bare records to an existing named pipe (or file) owned by another
+/// process. Opening a FIFO blocks until a reader is attached.
+struct PipeSink {
+    path: PathBuf,
+}
+
+impl SubmissionSink for PipeSink {
+    fn submit(&mut self, record: &str) -> io::Result<()> {
+        let mut pipe = OpenOptions::new().append(true).open(&self.path)?;
+        writeln!(pipe, "{record}")
+    }
+
+    fn path(&self) -> Option<&Path> {
+        Some(&self.path)
+    }
+}
+
+/// Runs `sh -c <command>` per submission with the record on stdin. Output is
+/// discarded so it cannot corrupt the UI; a nonzero exit is an error.
+struct CommandSink {
+    command: String,
+}
+
+impl SubmissionSink for CommandSink {
+    fn submit(&mut self, record: &str) -> io::Result<()> {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            // A command that ignores its input may exit before reading it;
+            // its exit status is what decides success.
+            match writeln!(stdin, "{record}") {
+                Err(error) if error.kind() != io::ErrorKind::BrokenPipe => return Err(error),
+                _ => {}
+            }
+        }
+
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "submission command exited with {status}"
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// The file sink's destination: `invite_file`, or a default named for `format`.
+fn invite_path(format: InviteFormat, invite_file: Option<&Path>) -> PathBuf {
+    invite_file.map_or_else(
+        || match format {
+            InviteFormat::Csv => PathBuf::from("invite_submissions.csv"),
+            InviteFormat::Json => PathBuf::from("invite_submissions.jsonl"),
+        },
+        Path::to_path_buf,
+    )
+}
+
+/// Picks the sink from `BOAAI_SINK=file|pipe|command` (default `file`).
+/// The file sink writes to `invite_file`, or a default named for `format`.
+fn sink_from_env(
+    format: InviteFormat,
+    invite_file: Option<&Path>,
+) -> io::Result<Box<dyn SubmissionSink>> {
+    let kind = env::var("BOAAI_SINK").unwrap_or_default();
+    match kind.trim().to_ascii_lowercase().as_str() {
+        "" | "file" => Ok(Box::new(FileSink {
+            path: invite_path(format, invite_file),
+            format,
+        })),
+        "pipe" => {
+            let path = env::var("BOAAI_SINK_PATH").map_err(|_| {
+                io::Error::new(io::ErrorKind::NotFound, "BOAAI_SINK_PATH is not set")
+            })?;
+            Ok(Box::new(PipeSink { path: path.into() }))
+        }
+        "command" => {
+            let command = env::var("BOAAI_SINK_COMMAND").map_err(|_| {
+                io::Error::new(io::ErrorKind::NotFound, "BOAAI_SINK_COMMAND is not set")
+            })?;
+            Ok(Box::new(CommandSink { command }))
+        }
+        other => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("unknown BOAAI_SINK '{other}'"),
+        )),
+    }
+}
+
+/// Records the submission unless the address was already registered, either
+/// earlier in this run or in the destination file. `registered` caches the
+/// known addresses so the file is only read once per run.
+fn store_submission(
+    record: &SubmissionRecord,
+    registered: &mut Option<HashSet<String>>,
+    invite_file: Option<&Path>,
+) -> io::Result<SubmitOutcome> {
+    let format = invite_format_from_env();
+    let mut sink = sink_from_env(format, invite_file)?;
+    let registered = match registered {
+        Some(registered) => registered,
+        None => registered.insert(sink.registered_emails()?),
+    };
+    let normalized = normalize_email(&record.email);
+    if registered.contains(&normalized) {
+        return Ok(SubmitOutcome::Duplicate);
+    }
+    sink.submit(&record.encode(format))?;
+    registered.insert(normalized);
+    Ok(SubmitOutcome::Stored(sink.path().map(Path::to_path_buf)))
+}
+
+/// `--generate-replay`: deals the board the game would deal for the
+/// configured seed (today's daily seed when none is set), start and
+/// `fixed_target`, and writes its shortest solution as a replay.
+fn generate_replay(
+    output: &Path,
+    config: &Config,
+    start: [NodeColor; INDICATOR_COUNT],
+    cycle: ColorCycle,
+    fixed_target: Option<[NodeColor; INDICATOR_COUNT]>,
+) -> io::Result<()> {
+    let seed = config.seed.unwrap_or_else(daily_seed);
+    let rule = config.press_rule;
+    let puzzle = seeded_puzzle_state(seed, start, cycle, rule, config.random_start, fixed_target);
+    let Some(moves) = shortest_solution(puzzle.initial, puzzle.target, cycle, rule) else {
+        eprintln!("No solution exists for seed {seed}; replay not written.");
+        process::exit(1);
+    };
+
+    let replay = Replay {
+        seed,
+        cycle,
+        rule,
+        initial: puzzle.initial,
+        target: puzzle.target,
+        moves,
+        difficulty: None,
+    };
+    save_replay(output, &replay)?;
+
+    let loaded = load_replay(output)?;
+    if loaded != replay || !loaded.solves() {
+        eprintln!(
+            "Replay written to {} failed verification.",
+            output.display()
+        );
+        process::exit(1);
+    }
+
+    println!(
+        "Wrote {}: seed {seed}, {} move(s).",
+        output.display(),
+        replay.moves.len()
+    );
+    Ok(())
+}
+
+fn save_replay(path: &Path, replay: &Replay) -> io::Result<()> {
+    let moves = replay
+        .moves
+        .iter()
+        .map(|press| (press + 1).to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    fs::write(
+        path,
+        format!(
+            "{REPLAY_HEADER}\nseed={}\ncolors={}\nrule={}\ninitial={}\ntarget={}\nmoves={moves}\n{}",
+            replay.seed,
+            replay
+                .cycle
+                .colors()
+                .map(NodeColor::as_str)
+                .collect::<Vec<_>>()
+                .join(","),
+            replay.rule.name(),
+            render_state_spec(replay.initial),
+            render_state_spec(replay.target),
+            replay.difficulty.map_or(String::new(), |difficulty| format!(
+                "difficulty={}\n",
+                difficulty.name()
+            ))
+        ),
+    )
+}
+
+fn load_replay(path: &Path) -> io::Result<Replay> {
+    let invalid = |message: String| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {message}", path.display()),
+        )
+    };
+
+    let contents = fs::read_to_string(path)?;
+    let mut lines = contents.lines();
+    if lines.next().map(str::trim) != Some(REPLAY_HEADER) {
+        return Err(invalid("missing replay header".to_string()));
+    }
+
+    let mut fields = HashMap::new();
+    for line in lines.map(str::trim).filter(|line| !line.is_empty()) {
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| invalid(format!("malformed line '{line}'")))?;
+        fields.insert(key.trim(), value.trim());
+    }
+
+    let field = |key: &str| {
+        fields
+            .get(key)
+            .copied()
+            .ok_or_else(|| invalid(format!("missing '{key}'")))
+    };
+
+    let seed = field("seed")?
+        .parse()
+        .map_err(|_| invalid("invalid seed".to_string()))?;
+    let cycle = match fields.get("colors") {
+        Some(spec) => ColorCycle::parse(spec).map_err(|error| invalid(error.to_string()))?,
+        None => ColorCycle::FULL,
+    };
+    let rule = match fields.get("rule") {
+        Some(name) => {
+            PressRule::parse(name).ok_or_else(|| invalid(format!("unknown press rule '{name}'")))?
+        }
+        None => PressRule::Classic,
+    };
+    let initial =
+        parse_state_spec(field("initial")?).map_err(|error| invalid(error.to_string()))?;
+    let target = parse_state_spec(field("target")?).map_err(|error| invalid(error.to_string()))?;
+    let moves = field("moves")?
+        .split(',')
+        .filter(|token| !token.trim().is_empty())
+        .map(|token| match token.trim().parse::<usize>() {
+            Ok(press) if (1..=INDICATOR_COUNT).contains(&press) => Ok(press - 1),
+            _ => Err(invalid(format!("invalid move '{token}'"))),
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    let difficulty = match fields.get("difficulty") {
+        Some(name) => Some(
+            Difficulty::parse(name)
+                .ok_or_else(|| invalid(format!("unknown difficulty '{name}'")))?,
+        ),
+        None => None,
+    };
+
+    Ok(Replay {
+        seed,
+        cycle,
+        rule,
+        initial,
+        target,
+        moves,
+        difficulty,
+    })
+}
+
+/// Parses a state spec such as `WHITE,PURPLE,GREEN,WHITE,PURPLE,GREEN` or
+/// `5,4,1,5,4,1`, matching the format accepted by `solution.py`.
+fn parse_state_spec(spec: &str) -> Result<[NodeColor; INDICATOR_COUNT], ConfigError> {
+    let tokens: Vec<&str> = spec
+        .split(|c: char| c == ',' || c == '|' || c.is_whitespace())
+        .filter(|token| !token.is_empty())
+        .collect();
+
+    if tokens.len() != INDICATOR_COUNT {
+        return Err(ConfigError::LengthMismatch {
+            expected: INDICATOR_COUNT,
+            found: tokens.len(),
+        });
+    }
+
+    let mut state = START_STATE;
+    for (slot, token) in state.iter_mut().zip(tokens) {
+        *slot = token.parse()?;
+    }
+    Ok(state)
+}
+
+/// `BOAAI_START` as either sparse overrides (`2=RED,5=GREEN`) or a full
+/// state in the `--target` format (`GREEN,OFF,OFF,RED,OFF,OFF`).
+fn parse_start_spec(spec: &str) -> Result<[NodeColor; INDICATOR_COUNT], ConfigError> {
+    if spec.contains('=') {
+        parse_start_overrides(spec)
+    } else {
+        parse_state_spec(spec)
+    }
+}
+
+/// Parses sparse start overrides such as `2=RED,5=GREEN` (1-based indicator
+/// numbers); indicators that are not mentioned stay OFF.
+fn parse_start_overrides(spec: &str) -> Result<[NodeColor; INDICATOR_COUNT], ConfigError> {
+    let mut state = START_STATE;
+    let mut assigned = [false; INDICATOR_COUNT];
+
+    for entry in spec
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+    {
+        let (index, color) = entry
+            .split_once('=')
+            .ok_or_else(|| ConfigError::MalformedEntry {
+                expected: "index=COLOR",
+                found: entry.to_string(),
+            })?;
+        let index = match index.trim().parse::<usize>() {
+            Ok(index) if (1..=INDICATOR_COUNT).contains(&index) => index - 1,
+            _ => return Err(ConfigError::BadIndex(index.trim().to_string())),
+        };
+        if assigned[index] {
+            return Err(ConfigError::DuplicateIndex(index + 1));
+        }
+
+        assigned[index] = true;
+        state[index] = color.parse()?;
+    }
+
+    Ok(state)
+}
+
+/// Settings `main` needs before it dispatches, validated together.
+struct StartupConfig {
+    cycle: ColorCycle,
+    start: [NodeColor; INDICATOR_COUNT],
+    fixed_target: Option<[NodeColor; INDICATOR_COUNT]>,
+    script: Option<InputScript>,
+}
+
+/// Numeric settings that must parse as whole numbers when set.
+const NUMERIC_ENV: &[&str] = &[
+    "BOAAI_SEED",
+    "BOAAI_SPLASH_SECS",
+    "BOAAI_MAX_MOVES_OVER_OPTIMAL",
+    "BOAAI_MOVE_CAP",
+    "BOAAI_AUTO_HINT",
+    "BOAAI_CAMPAIGN_STAGES",
+    "BOAAI_SESSION_SECS",
+    "BOAAI_IDLE_SECS",
+    "BOAAI_SCORE_MOVE_WEIGHT",
+    "BOAAI_SCORE_TIME_WEIGHT",
+    "BOAAI_SURVIVAL_DEPTH",
+    "BOAAI_SURVIVAL_STEP",
+    "BOAAI_SURVIVAL_BUDGET_SECS",
+];
+
+/// Settings that take one of a fixed set of words (case-insensitive).
+const CHOICE_ENV: &[(&str, &[&str], &str)] = &[
+    (
+        "BOAAI_CAMPAIGN_FINALE",
+        &["email", "victory", "loop"],
+        "email, victory or loop",
+    ),
+    (
+        "BOAAI_SESSION_UNSUBMITTED",
+        &["prompt", "discard"],
+        "prompt or discard",
+    ),
+    ("BOAAI_LAYOUT", &["row", "ring"], "row or ring"),
+    (
+        "BOAAI_SINK",
+        &["file", "pipe", "command"],
+        "file, pipe or command",
+    ),
+    ("BOAAI_INPUT_SCRIPT_END", &["quit", "live"], "quit or live"),
+    ("BOAAI_REVEAL_OPTIMAL", &["off", "after"], "off or after"),
+    ("BOAAI_INVITE_FORMAT", &["csv", "json"], "csv or json"),
+    ("BOAAI_PALETTE", &["default", "cb"], "default or cb"),
+    (
+        "BOAAI_PRESS_RULE",
+        &["classic", "lights"],
+        "classic or lights",
+    ),
+];
+
+/// On/off switches read through `env_bool`.
+const FLAG_ENV: &[&str] = &[
+    "BOAAI_DEBUG",
+    "BOAAI_FINISHING_HINT",
+    "BOAAI_EMAIL_SUGGESTIONS",
+    "BOAAI_SKIP_MATCHED",
+    "BOAAI_REDUCED_MOTION",
+    "BOAAI_BELL",
+    "BOAAI_PRESS_PREVIEW",
+    "BOAAI_DRY_RUN",
+    "BOAAI_PLAIN",
+];
+
+/// Checks every startup setting and returns all problems at once instead of
+/// stopping at the first, so an operator can fix a deployment in one pass.
+fn load_startup_config(config: &Config) -> Result<StartupConfig, Vec<(&'static str, ConfigError)>> {
+    let mut errors = Vec::new();
+
+    let cycle = cycle_from_env().unwrap_or_else(|error| {
+        errors.push(("BOAAI_COLORS", error));
+        ColorCycle::FULL
+    });
+    let start = start_from_env()
+        .and_then(|start| cycle.check_state(start).map(|_| start))
+        .unwrap_or_else(|error| {
+            errors.push(("BOAAI_START", error));
+            START_STATE
+        });
+    // Colors and reachability only mean something against a valid color set
+    // and start; otherwise just the spec itself is checked.
+    let fixed_target = if errors.is_empty() {
+        configured_target(config, start, cycle)
+    } else {
+        config
+            .target
+            .as_deref()
+            .map_or(Ok(()), |spec| parse_state_spec(spec).map(|_| ()))
+            .map(|()| None)
+    }
+    .unwrap_or_else(|error| {
+        errors.push(("BOAAI_TARGET", error));
+        None
+    });
+    let script = script_from_env().unwrap_or_else(|error| {
+        errors.push(("BOAAI_INPUT_SCRIPT", error));
+        None
+    });
+
+    for &name in NUMERIC_ENV {
+        if let Ok(value) = env::var(name) {
+            if value.trim().parse::<usize>().is_err() {
+                errors.push((
+                    name,
+                    ConfigError::InvalidValue {
+                        value,
+                        expected: "a whole number",
+                    },
+                ));
+            }
+        }
+    }
+    for &(name, choices, expected) in CHOICE_ENV {
+        if let Ok(value) = env::var(name) {
+            let normalized = value.trim().to_ascii_lowercase();
+            if !normalized.is_empty() && !choices.contains(&normalized.as_str()) {
+                errors.push((name, ConfigError::InvalidValue { value, expected }));
+            }
+        }
+    }
+    for &name in FLAG_ENV {
+        if let Ok(value) = env::var(name) {
+            if !value.trim().is_empty() && env_bool(name).is_none() {
+                errors.push((
+                    name,
+                    ConfigError::InvalidValue {
+                        value,
+                        expected: "on or off",
+                    },
+                ));
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(StartupConfig {
+            cycle,
+            start,
+            fixed_target,
+            script,
+        })
+    } else {
+        Err(errors)
+    }
+}
+
+fn cycle_from_env() -> Result<ColorCycle, ConfigError> {
+    match env::var("BOAAI_COLORS") {
+        Ok(spec) if !spec.trim().is_empty() => ColorCycle::parse(&spec),
+        _ => Ok(ColorCycle::FULL),
+    }
+}
+
+fn start_from_env() -> Result<[NodeColor; INDICATOR_COUNT], ConfigError> {
+    match env::var("BOAAI_START") {
+        Ok(spec) if !spec.trim().is_empty() => parse_start_spec(&spec),
+        _ => Ok(START_STATE),
+    }
+}
+
+/// A full target such as `WHITE,PURPLE,GREEN,WHITE,PURPLE,GREEN`, which must
+/// use the active colors and be reachable from `start` (and so from every
+/// scrambled start derived from it).
+fn parse_target_spec(
+    spec: &str,
+    start: [NodeColor; INDICATOR_COUNT],
+    cycle: ColorCycle,
+    rule: PressRule,
+) -> Result<[NodeColor; INDICATOR_COUNT], ConfigError> {
+    let target = parse_state_spec(spec)?;
+    cycle.check_state(target)?;
+    solution_depth(start, target, cycle, rule)
+        .map(|_| target)
+        .ok_or(ConfigError::UnreachableTarget {
+            initial: start,
+            target,
+        })
+}
+
+/// The configured `BOAAI_TARGET`, checked against the active colors, start
+/// and rule. A bad target is a startup error rather than a quiet switch to
+/// a generated one, so a challenge never presents a different puzzle.
+fn configured_target(
+    config: &Config,
+    start: [NodeColor; INDICATOR_COUNT],
+    cycle: ColorCycle,
+) -> Result<Option<[NodeColor; INDICATOR_COUNT]>, ConfigError> {
+    let Some(spec) = &config.target else {
+        return Ok(None);
+    };
+    let target = parse_target_spec(spec, start, cycle, config.press_rule)?;
+    if !config.random_start && target == start {
+        return Err(ConfigError::TargetIsStart(target));
+    }
+    Ok(Some(target))
+}
+
+/// `--solve`: the shortest presses from `start_spec` to `target_spec`, both in
+/// the `--target` format and checked against the active colors. `Ok(None)`
+/// when the target cannot be reached.
+fn solve_specs(
+    start_spec: &str,
+    target_spec: &str,
+    cycle: ColorCycle,
+    rule: PressRule,
+) -> Result<Option<Vec<usize>>, ConfigError> {
+    let start = parse_state_spec(start_spec)?;
+    cycle.check_state(start)?;
+    let target = parse_state_spec(target_spec)?;
+    cycle.check_state(target)?;
+    Ok(shortest_solution(start, target, cycle, rule))
+}
+
+/// `moves: 1 4 6` then `length: 3`, with 1-based indicator numbers.
+fn render_solution(moves: &[usize]) -> String {
+    let presses = moves
+        .iter()
+        .map(|press| (press + 1).to_string())
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!("moves: {presses}\nlength: {}", moves.len())
+}
+
+fn render_state_spec(state: [NodeColor; INDICATOR_COUNT]) -> String {
+    state
+        .iter()
+        .map(|color| color.as_str())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Digits of a share code. Each holds two indicators' colors (36 = 6 * 6),
+/// so the 6^6 boards fit exactly in three of them.
+const SHARE_CODE_DIGITS: &[u8; 36] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+/// The board digits and a check digit.
+const SHARE_CODE_LEN: usize = INDICATOR_COUNT.div_ceil(2) + 1;
+/// Check digit weights, repeated for longer codes; each is coprime to 36, so
+/// any single mistyped character changes the check digit.
+const SHARE_CODE_WEIGHTS: [usize; 12] = [1, 5, 7, 11, 13, 17, 19, 23, 25, 29, 31, 35];
+
+/// A board as a short code such as `YBPC`: the colors read as a base-6
+/// number (indicator 1 most significant), written in base 36, then a check
+/// digit.
+fn encode_share_code(state: [NodeColor; INDICATOR_COUNT]) -> String {
+    let mut value = state.iter().fold(0, |value, &color| {
+        value * NodeColor::ALL.len() + NodeColor::ALL.iter().position(|&c| c == color).unwrap_or(0)
+    });
+    let mut digits = [0; SHARE_CODE_LEN];
+    for digit in digits[..SHARE_CODE_LEN - 1].iter_mut().rev() {
+        *digit = value % SHARE_CODE_DIGITS.len();
+        value /= SHARE_CODE_DIGITS.len();
+    }
+    digits[SHARE_CODE_LEN - 1] = share_code_check(&digits[..SHARE_CODE_LEN - 1]);
+    digits
+        .iter()
+        .map(|&digit| SHARE_CODE_DIGITS[digit] as char)
+        .collect()
+}
+
+/// Reads a code from `encode_share_code`; case and surrounding spaces are
+/// ignored.
+fn decode_share_code(code: &str) -> Result<[NodeColor; INDICATOR_COUNT], ConfigError> {
+    let code = code.trim().to_ascii_uppercase();
+    let digits = code
+        .bytes()
+        .map(|byte| SHARE_CODE_DIGITS.iter().position(|&digit| digit == byte))
+        .collect::<Option<Vec<_>>>()
+        .filter(|digits| digits.len() == SHARE_CODE_LEN)
+        .ok_or_else(|| ConfigError::BadShareCode(code.clone()))?;
+    let (board, check) = digits.split_at(SHARE_CODE_LEN - 1);
+    if share_code_check(board) != check[0] {
+        return Err(ConfigError::ShareCodeChecksum(code));
+    }
+
+    let mut value = board
+        .iter()
+        .fold(0, |value, &digit| value * SHARE_CODE_DIGITS.len() + digit);
+    let mut state = START_STATE;
+    for slot in state.iter_mut().rev() {
+        *slot = NodeColor::ALL[value % NodeColor::ALL.len()];
+        value /= NodeColor::ALL.len();
+    }
+    Ok(state)
+}
+
+fn share_code_check(board: &[usize]) -> usize {
+    board
+        .iter()
+        .zip(SHARE_CODE_WEIGHTS.iter().cycle())
+        .map(|(digit, weight)| digit * weight)
+        .sum::<usize>()
+        % SHARE_CODE_DIGITS.len()
+}
+
+/// Golf-style efficiency line built from the cached distance to the target:
+/// presses still needed, presses taken, and the projected score against par.
+fn par_summary(puzzle: &PuzzleState) -> String {
+    if puzzle.distance_to_target == 0 {
+        return "Par: on the green (solved)".to_string();
+    }
+
+    let projected = puzzle.moves_taken + puzzle.distance_to_target;
+    let score = match projected.cmp(&puzzle.optimal_moves) {
+        Ordering::Greater => format!("+{} over par", projected - puzzle.optimal_moves),
+        Ordering::Equal => "even par".to_string(),
+        Ordering::Less => format!("{} under par", puzzle.optimal_moves - projected),
+    };
+    format!(
+        "Par: {} to go   taken {}   {score}",
+        puzzle.distance_to_target, puzzle.moves_taken
+    )
+}
+
+fn render_state(state: [NodeColor; INDICATOR_COUNT]) -> String {
+    state
+        .iter()
+        .map(|color| color.as_str())
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+/// Terminal columns `c` takes up: 2 for wide East Asian and emoji
+/// characters, 0 for combining marks and other zero-width characters, and 1
+/// for everything else (box drawing, `…` and the palette glyphs included).
+fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x0300..=0x036F | 0x200B..=0x200F | 0x20D0..=0x20FF | 0xFE00..=0xFE0F => 0,
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+fn display_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+/// The longest prefix of `text` that fits in `width` terminal columns. A wide
+/// character that would straddle the edge is dropped whole.
+fn trim_to_width(text: &str, width: usize) -> String {
+    let mut used = 0;
+    text.chars()
+        .take_while(|&c| {
+            used += char_width(c);
+            used <= width
+        })
+        .collect()
+}
+
+/// Like `trim_to_width`, but ends a cut string with `…` so readers can tell
+/// text is missing. The ellipsis counts towards `width`.
+fn trim_to_width_ellipsis(text: &str, width: usize) -> String {
+    if display_width(text) <= width {
+        return text.to_string();
+    }
+    match width {
+        0 => String::new(),
+        _ => {
+            let mut trimmed = trim_to_width(text, width - 1);
+            trimmed.push('…');
+            trimmed
+        }
+    }
+}
+
+fn center_text(text: &str, width: usize) -> String {
+    let clean = trim_to_width(text, width);
+    let clean_len = display_width(&clean);
+    if clean_len >= width {
+        return clean;
+    }
+    let left = (width - clean_len) / 2;
+    let right = width - clean_len - left;
+    format!("{}{}{}", " ".repeat(left), clean, " ".repeat(right))
+}
+
+/// Accepts `local@domain` where, besides having no spaces, exactly one `@`
+/// and at least 5 characters overall:
+///
+/// - `local` is one or more of `A-Z a-z 0-9 . _ + -`, does not start with
+///   `.` or `+`, does not end with `.`, and has no `..`;
+/// - `domain` is two or more dot-separated labels, none empty (so no leading,
+///   trailing or doubled dots);
+/// - each label is `A-Z a-z 0-9 -` and does not start or end with `-`;
+/// - the last label (the TLD) is at least 2 letters.
+fn is_valid_email(value: &str) -> bool {
+    if value.contains(' ') || value.len() < 5 {
+        return false;
+    }
+
+    let mut parts = value.split('@');
+    let local = parts.next().unwrap_or_default();
+    let domain = parts.next().unwrap_or_default();
+
+    if parts.next().is_some() {
+        return false;
+    }
+
+    let local_ok = !local.is_empty()
+        && local
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '+' | '-'))
+        && !local.starts_with(['.', '+'])
+        && !local.ends_with('.')
+        && !local.contains("..");
+
+    let labels: Vec<&str> = domain.split('.').collect();
+    let labels_ok = labels.len() >= 2
+        && labels.iter().all(|label| {
+            !label.is_empty()
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+        });
+    let tld = labels.last().copied().unwrap_or_default();
+    let tld_ok = tld.len() >= 2 && tld.chars().all(|c| c.is_ascii_alphabetic());
+
+    local_ok && labels_ok && tld_ok
+}
+
+/// Common mistyped domains and the address they were most likely meant to be.
+const DOMAIN_TYPOS: &[(&str, &str)] = &[
+    ("gmai.com", "gmail.com"),
+    ("gmial.com", "gmail.com"),
+    ("gmal.com", "gmail.com"),
+    ("gamil.com", "gmail.com"),
+    ("gnail.com", "gmail.com"),
+    ("yahooo.com", "yahoo.com"),
+    ("yaho.com", "yahoo.com"),
+    ("hotmial.com", "hotmail.com"),
+    ("hotmal.com", "hotmail.com"),
+    ("outlok.com", "outlook.com"),
+    ("iclod.com", "icloud.com"),
+];
+
+const TLD_TYPOS: &[(&str, &str)] = &[
+    (".con", ".com"),
+    (".cmo", ".com"),
+    (".comm", ".com"),
+    (".c0m", ".com"),
+    (".ogr", ".org"),
+    (".nte", ".net"),
+];
+
+const COMMON_DOMAINS: &[&str] = &[
+    "gmail.com",
+    "yahoo.com",
+    "hotmail.com",
+    "outlook.com",
+    "icloud.com",
+];
+
+/// Guesses a corrected address for common typos: a doubled or missing `@`
+/// before a well-known domain, and misspelled domains or TLDs.
+/// Returns `None` unless the guess differs from the input and is valid.
+fn suggest_email_fix(value: &str) -> Option<String> {
+    let mut fixed = value.to_string();
+    while fixed.contains("@@") {
+        fixed = fixed.replace("@@", "@");
+    }
+
+    if !fixed.contains('@') {
+        if let Some(domain) = COMMON_DOMAINS
+            .iter()
+            .find(|domain| fixed.len() > domain.len() && fixed.ends_with(*domain))
+        {
+            fixed.insert(fixed.len() - domain.len(), '@');
+        }
+    }
+
+    if let Some((local, domain)) = fixed.split_once('@') {
+        let mut domain = domain.to_ascii_lowercase();
+        if let Some((_, correct)) = DOMAIN_TYPOS.iter().find(|(typo, _)| domain == *typo) {
+            domain = correct.to_string();
+        }
+        if let Some((typo, correct)) = TLD_TYPOS.iter().find(|(typo, _)| domain.ends_with(*typo)) {
+            domain.truncate(domain.len() - typo.len());
+            domain.push_str(correct);
+        }
+        fixed = format!("{local}@{domain}");
+    }
+
+    (fixed != value && is_valid_email(&fixed)).then_some(fixed)
+}
+
+/// Typo suggestions are on unless `BOAAI_EMAIL_SUGGESTIONS` is `0`/`off`/`false`.
+fn email_suggestions_enabled() -> bool {
+    !env_disabled("BOAAI_EMAIL_SUGGESTIONS")
+}
+
+/// True when an on-by-default feature flag is explicitly switched off.
+fn env_disabled(name: &str) -> bool {
+    env_bool(name) == Some(false)
+}
+
+/// Reads an on/off flag; `None` when unset or unrecognised so callers can
+/// fall back to saved preferences.
+fn env_bool(name: &str) -> Option<bool> {
+    parse_bool(&env::var(name).ok()?)
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// `BOAAI_PREFS_FILE` wins; otherwise preferences are kept per `BOAAI_USER`
+/// (or shared when no user is given) under `$XDG_CONFIG_HOME/boaai` or
+/// `~/.config/boaai`, falling back to the working directory without a home.
+fn preferences_path() -> PathBuf {
+    if let Ok(path) = env::var("BOAAI_PREFS_FILE") {
+        return path.into();
+    }
+    let user: String = env::var("BOAAI_USER")
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+        .collect();
+    let name = if user.is_empty() {
+        "prefs.conf".to_string()
+    } else {
+        format!("prefs.{user}.conf")
+    };
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var_os("HOME")
+                .filter(|dir| !dir.is_empty())
+                .map(|home| PathBuf::from(home).join(".config"))
+        });
+    match config_dir {
+        Some(dir) => dir.join("boaai").join(name),
+        None => format!("boaai_{name}").into(),
+    }
+}
+
+fn is_email_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | '+' | '@')
+}
+
+/// `--survival` tuning: `BOAAI_SURVIVAL_DEPTH` scramble presses for the first
+/// puzzle (default 2), `BOAAI_SURVIVAL_STEP` extra presses per cleared puzzle
+/// (default 1), `BOAAI_SURVIVAL_BUDGET_SECS` per-puzzle time budget (default 60)
+/// and `BOAAI_SURVIVAL_FILE` for the run log.
+fn survival_from_env() -> SurvivalRun {
+    SurvivalRun {
+        depth: env_usize("BOAAI_SURVIVAL_DEPTH")
+            .filter(|depth| *depth > 0)
+            .unwrap_or(2),
+        depth_step: env_usize("BOAAI_SURVIVAL_STEP").unwrap_or(1),
+        budget: Duration::from_secs(
+            env_usize("BOAAI_SURVIVAL_BUDGET_SECS")
+                .filter(|secs| *secs > 0)
+                .unwrap_or(60) as u64,
+        ),
+        cleared: 0,
+        started_at: Instant::now(),
+        puzzle_started: Instant::now(),
+        total: None,
+        record_path: env::var("BOAAI_SURVIVAL_FILE")
+            .unwrap_or_else(|_| "survival_runs.csv".to_string())
+            .into(),
+        record_status: String::new(),
+    }
+}
+
+fn parse_palette(value: &str) -> Option<Palette> {
+    match value.trim() {
+        palette if palette.eq_ignore_ascii_case("cb") => Some(Palette::ColorBlind),
+        palette if palette.eq_ignore_ascii_case("default") => Some(Palette::Default),
+        _ => None,
+    }
+}
+
+fn env_usize(name: &str) -> Option<usize> {
+    env::var(name)
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+}
+
+/// Countdown to `BOAAI_EVENT_TIME`, or `None` when it is unset or malformed.
+fn event_banner(now: SystemTime) -> Option<String> {
+    let event = parse_event_time(&env::var("BOAAI_EVENT_TIME").ok()?)?;
+    let now = now.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    Some(if now >= event {
+        "Event is live.".to_string()
+    } else {
+        format!("Event starts in {}", format_countdown(event - now))
+    })
+}
+
+/// Accepts unix seconds or a UTC date/time such as `2026-11-20`,
+/// `2026-11-20 18:00` or `2026-11-20T18:00:00Z`.
+fn parse_event_time(value: &str) -> Option<u64> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse() {
+        return Some(secs);
+    }
+
+    let value = value.strip_suffix(['Z', 'z']).unwrap_or(value);
+    let (date, time) = match value.split_once(['T', 't', ' ']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (value, None),
+    };
+
+    let mut date_parts = date.split('-').map(|part| part.parse::<u32>().ok());
+    let (year, month, day) = match (
+        date_parts.next()??,
+        date_parts.next()??,
+        date_parts.next()??,
+        date_parts.next(),
+    ) {
+        (year, month, day, None) if (1..=12).contains(&month) && (1..=31).contains(&day) => {
+            (year, month, day)
+        }
+        _ => return None,
+    };
+
+    let mut secs_of_day = 0;
+    if let Some(time) = time {
+        let parts: Vec<u32> = time
+            .split(':')
+            .map(|part| part.parse().ok())
+            .collect::<Option<_>>()?;
+        let (hour, minute, second) = match parts.as_slice() {
+            [hour, minute] => (*hour, *minute, 0),
+            [hour, minute, second] => (*hour, *minute, *second),
+            _ => return None,
+        };
+        if hour > 23 || minute > 59 || second > 59 {
+            return None;
+        }
+        secs_of_day = u64::from(hour * 3600 + minute * 60 + second);
+    }
+
+    let days = u64::try_from(days_from_civil(year, month, day)).ok()?;
+    Some(days * 86_400 + secs_of_day)
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date.
+fn days_from_civil(year: u32, month: u32, day: u32) -> i64 {
+    let year = i64::from(year) - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// `YYYY-MM-DD HH:MM UTC` for unix seconds.
+fn format_utc(unix: u64) -> String {
+    let days = (unix / 86_400) as i64;
+    let (year, month, day) = civil_from_days(days);
+    let secs_of_day = unix % 86_400;
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02} UTC",
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60
+    )
+}
+
+/// Proleptic Gregorian date for days since 1970-01-01; the inverse of
+/// `days_from_civil`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    (
+        era * 400 + year_of_era + i64::from(month <= 2),
+        month as u32,
+        day as u32,
+    )
+}
+
+/// Coarse countdown such as `2d 4h`, `3h 15m` or `42m`.
+fn format_countdown(secs: u64) -> String {
+    let (days, hours, minutes) = (secs / 86_400, secs % 86_400 / 3600, secs % 3600 / 60);
+    if days > 0 {
+        format!("{days}d {hours}h")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else {
+        format!("{}m", minutes.max(1))
+    }
+}
+
+fn format_duration(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
+
+/// Parses one script token: a key name (`Left`, `Right`, `Up`, `Down`,
+/// `Enter`, `Space`, `Tab`, `Backspace`, `Esc`, `Home`, `End`), `F12`,
+/// `Char:a`, `Ctrl:c`, or `Wait:250` (milliseconds).
+fn parse_script_step(token: &str) -> Result<ScriptStep, ConfigError> {
+    let key = |code| Ok(ScriptStep::Key(KeyEvent::new(code, KeyModifiers::NONE)));
+    let token = token.trim();
+
+    if let Some((kind, value)) = token.split_once(':') {
+        let mut chars = value.chars();
+        let single = match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(c),
+            _ => None,
+        };
+        return match (kind.to_ascii_lowercase().as_str(), single) {
+            ("char", Some(c)) => key(KeyCode::Char(c)),
+            ("ctrl", Some(c)) => Ok(ScriptStep::Key(KeyEvent::new(
+                KeyCode::Char(c),
+                KeyModifiers::CONTROL,
+            ))),
+            ("wait", _) => value
+                .parse()
+                .map(|ms| ScriptStep::Wait(Duration::from_millis(ms)))
+                .map_err(|_| ConfigError::InvalidValue {
+                    value: value.to_string(),
+                    expected: "a wait in milliseconds",
+                }),
+            _ => Err(ConfigError::UnknownKey(token.to_string())),
+        };
+    }
+
+    if let Some(number) = token.strip_prefix(['F', 'f']) {
+        if let Ok(number) = number.parse() {
+            return key(KeyCode::F(number));
+        }
+    }
+
+    match token.to_ascii_lowercase().as_str() {
+        "left" => key(KeyCode::Left),
+        "right" => key(KeyCode::Right),
+        "up" => key(KeyCode::Up),
+        "down" => key(KeyCode::Down),
+        "enter" => key(KeyCode::Enter),
+        "space" => key(KeyCode::Char(' ')),
+        "tab" => key(KeyCode::Tab),
+        "backspace" => key(KeyCode::Backspace),
+        "esc" => key(KeyCode::Esc),
+        "home" => key(KeyCode::Home),
+        "end" => key(KeyCode::End),
+        _ => Err(ConfigError::UnknownKey(token.to_string())),
+    }
+}
+
+/// Parses a whole script, one token per line; blank lines and `#` comments
+/// are skipped.
+fn parse_script(contents: &str) -> Result<VecDeque<ScriptStep>, ConfigError> {
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(number, line)| {
+            parse_script_step(line).map_err(|error| ConfigError::AtLine {
+                line: number + 1,
+                error: Box::new(error),
+            })
+        })
+        .collect()
+}
+
+/// Loads `BOAAI_INPUT_SCRIPT` if set. `BOAAI_INPUT_SCRIPT_END=live` hands
+/// over to the terminal once the script runs out; the default quits.
+fn script_from_env() -> Result<Option<InputScript>, ConfigError> {
+    let Ok(path) = env::var("BOAAI_INPUT_SCRIPT") else {
+        return Ok(None);
+    };
+    let contents = fs::read_to_string(&path).map_err(|error| ConfigError::Unreadable {
+        path: path.clone(),
+        reason: error.to_string(),
+    })?;
+    let quit_when_done = !env::var("BOAAI_INPUT_SCRIPT_END")
+        .map(|value| value.trim().eq_ignore_ascii_case("live"))
+        .unwrap_or(false);
+
+    Ok(Some(InputScript {
+        steps: parse_script(&contents)?,
+        quit_when_done,
+    }))
+}
+
+const USAGE: &str = "\
+Usage: ssh_store [OPTIONS]
+
+Options:
+  --debug                  enable the F12 instant solve (BOAAI_DEBUG)
+  --seed <n>               seed of the first puzzle (BOAAI_SEED)
+  --target <spec>          fixed target, e.g. WHITE,PURPLE,GREEN,WHITE,PURPLE,GREEN (BOAAI_TARGET)
+  --code <code>            start the first puzzle from a shared board code, e.g. YBPC
+  --invite-file <path>     file that receives invite submissions (BOAAI_INVITE_FILE)
+  --no-splash              skip the splash screen
+  --no-alt-screen          draw on the normal screen instead of the alternate one
+  --dry-run                submit without recording anything (BOAAI_DRY_RUN)
+  --emit-result            print a JSON summary on exit; exit 3 if nothing was submitted
+  --difficulty <level>     skip the difficulty menu: easy, medium or hard
+  --drills                 open the drill menu
+  --survival               play a survival run
+  --sandbox                practice endlessly; nothing is submitted
+  --replay <path>          watch a recorded replay
+  --generate-replay <path> write a perfect-play replay and exit
+  --selftest               check every generation setting without a terminal
+  --bench-render <frames>  time rendering without a terminal
+  --solve <start> <target> print the shortest presses between two states and exit
+  --convert <in> <out>     rewrite an invite file as .csv or .jsonl and exit
+  --help                   print this message
+";
+
+/// Parsed command line. Value flags override the matching `BOAAI_*` variable
+/// through `Config::with_args`; the rest pick a mode.
+#[derive(Debug, Default, PartialEq)]
+struct CliArgs {
+    help: bool,
+    debug: bool,
+    seed: Option<u64>,
+    target: Option<String>,
+    /// Share code of the first puzzle's starting board.
+    code: Option<String>,
+    invite_file: Option<PathBuf>,
+    no_splash: bool,
+    no_alt_screen: bool,
+    dry_run: bool,
+    emit_result: bool,
+    difficulty: Option<Difficulty>,
+    drills: bool,
+    survival: bool,
+    sandbox: bool,
+    replay: Option<PathBuf>,
+    generate_replay: Option<PathBuf>,
+    selftest: bool,
+    bench_render: Option<u32>,
+    /// Start and target specs for `--solve`.
+    solve: Option<(String, String)>,
+    /// Input and output invite files for `--convert`.
+    convert: Option<(PathBuf, PathBuf)>,
+}
+
+fn parse_args(args: &[String]) -> Result<CliArgs, String> {
+    fn parse_number<T: FromStr>(flag: &str, raw: &str) -> Result<T, String> {
+        raw.trim()
+            .parse()
+            .map_err(|_| format!("invalid value '{raw}' for {flag}"))
+    }
+
+    let mut cli = CliArgs::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let flag = arg.as_str();
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("{flag} needs a value"))
+        };
+        match flag {
+            "--help" | "-h" => cli.help = true,
+            "--debug" => cli.debug = true,
+            "--seed" => cli.seed = Some(parse_number(flag, &value()?)?),
+            "--target" => cli.target = Some(value()?),
+            "--code" => cli.code = Some(value()?),
+            "--invite-file" => cli.invite_file = Some(value()?.into()),
+            "--no-splash" => cli.no_splash = true,
+            "--no-alt-screen" => cli.no_alt_screen = true,
+            "--dry-run" => cli.dry_run = true,
+            "--emit-result" => cli.emit_result = true,
+            "--difficulty" => {
+                let raw = value()?;
+                cli.difficulty = Some(Difficulty::parse(&raw).ok_or_else(|| {
+                    format!("invalid value '{raw}' for --difficulty (easy, medium or hard)")
+                })?)
+            }
+            "--drills" => cli.drills = true,
+            "--survival" => cli.survival = true,
+            "--sandbox" => cli.sandbox = true,
+            "--replay" => cli.replay = Some(value()?.into()),
+            "--generate-replay" => cli.generate_replay = Some(value()?.into()),
+            "--selftest" => cli.selftest = true,
+            "--solve" => cli.solve = Some((value()?, value()?)),
+            "--convert" => cli.convert = Some((value()?.into(), value()?.into())),
+            "--bench-render" => cli.bench_render = Some(parse_number(flag, &value()?)?),
+            other => return Err(format!("unknown argument '{other}'")),
+        }
+    }
+    // Each of these replaces the first puzzle, which would drop the code.
+    if cli.code.is_some() && (cli.difficulty.is_some() || cli.survival || cli.drills) {
+        return Err("--code cannot be combined with --difficulty, --survival or --drills".into());
+    }
+    Ok(cli)
+}
+
+const DEFAULT_SPLASH_SECONDS: u64 = 3;
+
+/// Settings shared by the config file, the environment and the command line.
+/// Each key resolves CLI > env > file > built-in default: `load` merges the
+/// file and the `BOAAI_*` variables, then `with_args` lays the flags on top.
+#[derive(Clone, Debug, PartialEq)]
+struct Config {
+    debug: bool,
+    /// `None` when unset: play seeds from the clock, `--generate-replay`
+    /// from the day.
+    seed: Option<u64>,
+    target: Option<String>,
+    invite_file: Option<PathBuf>,
+    /// How long the splash screen stays up; `0` skips it.
+    splash_seconds: u64,
+    /// `None` leaves the palette to the saved preference.
+    palette: Option<Palette>,
+    /// Skip recording submissions; see `App::dry_run`.
+    dry_run: bool,
+    press_rule: PressRule,
+    /// `BOAAI_ACCENT`; a malformed triple keeps the default orange.
+    accent: Color,
+    /// Why the config file was ignored, shown on the status line at startup.
+    warning: Option<String>,
+    /// Saved in-app toggles; see `preferences_path`.
+    prefs: Preferences,
+    /// Off when `BOAAI_START` pins the starting board.
+    random_start: bool,
+    save_file: Option<PathBuf>,
+    leaderboard_file: PathBuf,
+    campaign: Option<CampaignPlan>,
+    max_moves_over_optimal: Option<usize>,
+    move_cap: Option<usize>,
+    auto_hint_after: Option<usize>,
+    /// Env overrides of the saved preferences, for this run only.
+    finishing_hint: Option<bool>,
+    skip_matched: Option<bool>,
+    reduced_motion: Option<bool>,
+    press_preview: bool,
+    bell: bool,
+    layout: IndicatorLayout,
+    /// `BOAAI_REVEAL_OPTIMAL=after`.
+    reveal_optimal: bool,
+    /// `BOAAI_SESSION_SECS` and `BOAAI_SESSION_UNSUBMITTED`.
+    session_limit: Option<(Duration, UnsubmittedPolicy)>,
+    /// `BOAAI_IDLE_SECS`.
+    idle_window: Option<Duration>,
+    score_weights: ScoreWeights,
+}
+
+/// Built-in behavior with no config file, environment or saved preferences,
+/// and seed 0; what tests build apps from.
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            debug: false,
+            seed: Some(0),
+            target: None,
+            invite_file: None,
+            splash_seconds: DEFAULT_SPLASH_SECONDS,
+            palette: None,
+            dry_run: false,
+            press_rule: PressRule::Classic,
+            accent: DEFAULT_ACCENT,
+            warning: None,
+            prefs: Preferences::default(),
+            random_start: true,
+            save_file: None,
+            leaderboard_file: "leaderboard.csv".into(),
+            campaign: None,
+            max_moves_over_optimal: None,
+            move_cap: None,
+            auto_hint_after: None,
+            finishing_hint: None,
+            skip_matched: None,
+            reduced_motion: None,
+            press_preview: false,
+            bell: true,
+            layout: IndicatorLayout::Row,
+            reveal_optimal: false,
+            session_limit: None,
+            idle_window: None,
+            score_weights: ScoreWeights::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Reads `BOAAI_CONFIG` (default `boaai.toml`) when it exists. A file that
+    /// cannot be read or parsed is ignored with a warning, never fatal.
+    fn load() -> Self {
+        let path =
+            env::var_os("BOAAI_CONFIG").map_or_else(|| PathBuf::from("boaai.toml"), PathBuf::from);
+        let (file, warning) = match fs::read_to_string(&path) {
+            Ok(text) => match ConfigFile::parse(&text) {
+                Ok(file) => (file, None),
+                Err(error) => (
+                    ConfigFile::default(),
+                    Some(format!(
+                        "{} ignored ({error}); using defaults.",
+                        path.display()
+                    )),
+                ),
+            },
+            Err(error) if error.kind() == io::ErrorKind::NotFound => (ConfigFile::default(), None),
+            Err(error) => (
+                ConfigFile::default(),
+                Some(format!(
+                    "Could not read {} ({error}); using defaults.",
+                    path.display()
+                )),
+            ),
+        };
+        Self {
+            warning,
+            prefs: Preferences::load(&preferences_path()),
+            ..Self::resolve(&file, |name| env::var(name).ok())
+        }
+    }
+
+    /// The environment, saved preferences and built-in defaults, without a
+    /// config file.
+    fn from_env() -> Self {
+        Self {
+            prefs: Preferences::load(&preferences_path()),
+            ..Self::resolve(&ConfigFile::default(), |name| env::var(name).ok())
+        }
+    }
+
+    /// Prefers the variable `var` returns for each key over the file value,
+    /// and the file value over the default. Blank or malformed variables
+    /// count as unset.
+    fn resolve(file: &ConfigFile, var: impl Fn(&str) -> Option<String>) -> Self {
+        let var = |name: &str| var(name).filter(|value| !value.trim().is_empty());
+        let number = |name: &str| var(name).and_then(|value| value.trim().parse::<usize>().ok());
+        let flag = |name: &str| var(name).and_then(|value| parse_bool(&value));
+        let choice = |name: &str| var(name).map(|value| value.trim().to_ascii_lowercase());
+        let defaults = Self::default();
+        let weight = |name: &str, default: u32| {
+            number(name)
+                .and_then(|weight| u32::try_from(weight).ok())
+                .unwrap_or(default)
+        };
+        Self {
+            debug: var("BOAAI_DEBUG")
+                .and_then(|value| parse_bool(&value))
+                .or(file.debug)
+                .unwrap_or(false),
+            seed: var("BOAAI_SEED").and_then(|value| value.trim().parse().ok()),
+            target: var("BOAAI_TARGET").or_else(|| file.target.clone()),
+            invite_file: var("BOAAI_INVITE_FILE")
+                .map(PathBuf::from)
+                .or_else(|| file.invite_file.clone()),
+            splash_seconds: var("BOAAI_SPLASH_SECS")
+                .and_then(|value| value.trim().parse().ok())
+                .or(file.splash_seconds)
+                .unwrap_or(DEFAULT_SPLASH_SECONDS),
+            palette: var("BOAAI_PALETTE")
+                .and_then(|value| parse_palette(&value))
+                .or(file.palette),
+            dry_run: var("BOAAI_DRY_RUN")
+                .and_then(|value| parse_bool(&value))
+                .unwrap_or(false),
+            press_rule: var("BOAAI_PRESS_RULE")
+                .and_then(|value| PressRule::parse(&value))
+                .or(file.press_rule)
+                .unwrap_or(PressRule::Classic),
+            accent: var("BOAAI_ACCENT")
+                .and_then(|value| parse_accent(&value))
+                .unwrap_or(DEFAULT_ACCENT),
+            random_start: var("BOAAI_START").is_none(),
+            save_file: var("BOAAI_SAVE_FILE").map(PathBuf::from),
+            leaderboard_file: var("BOAAI_LEADERBOARD_FILE")
+                .map_or(defaults.leaderboard_file.clone(), PathBuf::from),
+            campaign: number("BOAAI_CAMPAIGN_STAGES")
+                .filter(|stages| *stages > 1)
+                .map(|stages| CampaignPlan {
+                    stages,
+                    finale: match choice("BOAAI_CAMPAIGN_FINALE").as_deref() {
+                        Some("victory") => CampaignFinale::Victory,
+                        Some("loop") => CampaignFinale::Loop,
+                        _ => CampaignFinale::Email,
+                    },
+                }),
+            max_moves_over_optimal: number("BOAAI_MAX_MOVES_OVER_OPTIMAL"),
+            move_cap: number("BOAAI_MOVE_CAP").filter(|cap| *cap > 0),
+            auto_hint_after: number("BOAAI_AUTO_HINT").filter(|presses| *presses > 0),
+            finishing_hint: flag("BOAAI_FINISHING_HINT"),
+            skip_matched: flag("BOAAI_SKIP_MATCHED"),
+            reduced_motion: flag("BOAAI_REDUCED_MOTION"),
+            press_preview: flag("BOAAI_PRESS_PREVIEW").unwrap_or(defaults.press_preview),
+            bell: flag("BOAAI_BELL").unwrap_or(defaults.bell),
+            layout: match choice("BOAAI_LAYOUT").as_deref() {
+                Some("ring") => IndicatorLayout::Ring,
+                _ => IndicatorLayout::Row,
+            },
+            reveal_optimal: choice("BOAAI_REVEAL_OPTIMAL").as_deref() == Some("after"),
+            session_limit: number("BOAAI_SESSION_SECS")
+                .filter(|secs| *secs > 0)
+                .map(|secs| {
+                    (
+                        Duration::from_secs(secs as u64),
+                        match choice("BOAAI_SESSION_UNSUBMITTED").as_deref() {
+                            Some("discard") => UnsubmittedPolicy::Discard,
+                            _ => UnsubmittedPolicy::Prompt,
+                        },
+                    )
+                }),
+            idle_window: number("BOAAI_IDLE_SECS")
+                .filter(|secs| *secs > 0)
+                .map(|secs| Duration::from_secs(secs as u64)),
+            score_weights: ScoreWeights {
+                per_extra_move: weight(
+                    "BOAAI_SCORE_MOVE_WEIGHT",
+                    defaults.score_weights.per_extra_move,
+                ),
+                per_second: weight("BOAAI_SCORE_TIME_WEIGHT", defaults.score_weights.per_second),
+            },
+            ..defaults
+        }
+    }
+
+    fn with_args(mut self, cli: &CliArgs) -> Self {
+        self.debug |= cli.debug;
+        self.dry_run |= cli.dry_run;
+        if cli.no_splash {
+            self.splash_seconds = 0;
+        }
+        if cli.seed.is_some() {
+            self.seed = cli.seed;
+        }
+        if let Some(target) = &cli.target {
+            self.target = Some(target.clone());
+        }
+        if let Some(invite_file) = &cli.invite_file {
+            self.invite_file = Some(invite_file.clone());
+        }
+        self
+    }
+}
+
+/// Keys read from the config file: a flat `key = value` subset of TOML with
+/// quoted strings, whole numbers, `true`/`false` and `#` comments. Unset
+/// keys stay `None`.
+#[derive(Debug, Default, PartialEq)]
+struct ConfigFile {
+    debug: Option<bool>,
+    splash_seconds: Option<u64>,
+    palette: Option<Palette>,
+    press_rule: Option<PressRule>,
+    target: Option<String>,
+    invite_file: Option<PathBuf>,
+}
+
+impl ConfigFile {
+    fn parse(text: &str) -> Result<Self, ConfigError> {
+        let mut file = Self::default();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| ConfigError::MalformedEntry {
+                    expected: "key = value",
+                    found: line.to_string(),
+                })?;
+            let value = strip_toml_comment(value).trim();
+            let invalid = |expected| ConfigError::InvalidValue {
+                value: value.to_string(),
+                expected,
+            };
+            let string = || {
+                value
+                    .strip_prefix('"')
+                    .and_then(|value| value.strip_suffix('"'))
+                    .map(str::to_string)
+                    .ok_or_else(|| invalid("a quoted string"))
+            };
+            match key.trim() {
+                "debug" => {
+                    file.debug = Some(match value {
+                        "true" => true,
+                        "false" => false,
+                        _ => return Err(invalid("true or false")),
+                    })
+                }
+                "splash_seconds" => {
+                    file.splash_seconds =
+                        Some(value.parse().map_err(|_| invalid("a whole number"))?)
+                }
+                "palette" => {
+                    file.palette = Some(
+                        parse_palette(&string()?)
+                            .ok_or_else(|| invalid("\"default\" or \"cb\""))?,
+                    )
+                }
+                "press_rule" => {
+                    file.press_rule = Some(
+                        PressRule::parse(&string()?)
+                            .ok_or_else(|| invalid("\"classic\" or \"lights\""))?,
+                    )
+                }
+                "target" => file.target = Some(string()?),
+                "invite_file" => file.invite_file = Some(string()?.into()),
+                other => return Err(ConfigError::UnknownKey(other.to_string())),
+            }
+        }
+        Ok(file)
+    }
+}
+
+/// Drops a trailing `# comment`, leaving any `#` inside quotes alone.
+fn strip_toml_comment(value: &str) -> &str {
+    let mut quoted = false;
+    for (at, c) in value.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &value[..at],
+            _ => {}
+        }
+    }
+    value
+}
+
+/// A fresh seed from the clock, used when no seed is configured.
+fn clock_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64
+}
+
+/// Eight hex digits mixing the puzzle seed with the clock, so sessions that
+/// share a seed still get distinct IDs.
+fn session_id(seed: u64) -> String {
+    format!(
+        "{:08x}",
+        StdRng::seed_from_u64(seed ^ clock_seed()).gen::<u32>()
+    )
+}
+
+/// Seed shared by every run on the same UTC day.
+fn daily_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        / 86_400
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An app from built-in defaults, untouched by `BOAAI_*` variables or
+    /// the preferences and save files, so tests run the same everywhere.
+    fn test_app() -> App {
+        App::with_config(&Config::default(), START_STATE, ColorCycle::FULL)
+    }
+
+    #[test]
+    fn generated_replay_round_trips_and_solves() {
+        let path = env::temp_dir().join(format!("boaai-replay-{}.txt", process::id()));
+        let cycle = ColorCycle::parse("OFF,GREEN,BLUE,RED").expect("valid color set");
+        let rule = PressRule::Lights;
+        let puzzle =
+            new_puzzle_state_from_rng(&mut StdRng::seed_from_u64(99), START_STATE, cycle, rule);
+        let replay = Replay {
+            seed: 99,
+            cycle,
+            rule,
+            initial: puzzle.initial,
+            target: puzzle.target,
+            moves: shortest_solution(puzzle.initial, puzzle.target, cycle, rule)
+                .expect("path should exist"),
+            difficulty: None,
+        };
+
+        save_replay(&path, &replay).expect("replay should save");
+        let loaded = load_replay(&path).expect("replay should load");
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(loaded, replay);
+        assert!(loaded.solves());
+    }
+
+    #[test]
+    fn generate_replay_deals_the_seeded_board_for_the_config() {
+        let path = env::temp_dir().join(format!("boaai-generated-{}.txt", process::id()));
+        let cycle = ColorCycle::FULL;
+        let target = press_indicator(START_STATE, 3, cycle, PressRule::Classic);
+        for fixed_target in [None, Some(target)] {
+            let config = Config {
+                seed: Some(31),
+                ..Config::default()
+            };
+            generate_replay(&path, &config, START_STATE, cycle, fixed_target).unwrap();
+            let replay = load_replay(&path).unwrap();
+            let dealt = seeded_puzzle_state(
+                31,
+                START_STATE,
+                cycle,
+                PressRule::Classic,
+                true,
+                fixed_target,
+            );
+            assert_eq!(replay.seed, 31);
+            assert_eq!(
+                (replay.initial, replay.target),
+                (dealt.initial, dealt.target)
+            );
+            assert!(replay.solves());
+        }
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn start_overrides_set_only_listed_indicators() {
+        let state = parse_start_overrides("2=RED, 5=green").expect("valid overrides");
+        assert_eq!(
+            state,
+            [
+                NodeColor::Off,
+                NodeColor::Red,
+                NodeColor::Off,
+                NodeColor::Off,
+                NodeColor::Green,
+                NodeColor::Off,
+            ]
+        );
+    }
+
+    #[test]
+    fn start_overrides_reject_bad_entries() {
+        assert!(parse_start_overrides("7=RED").is_err());
+        assert!(parse_start_overrides("0=RED").is_err());
+        assert!(parse_start_overrides("2=PINK").is_err());
+        assert!(parse_start_overrides("2=RED,2=BLUE").is_err());
+        assert!(parse_start_overrides("RED").is_err());
+    }
+
+    #[test]
+    fn restricted_color_cycle_stays_within_subset() {
+        let cycle = ColorCycle::parse("off,green,blue").expect("valid color set");
+        assert_eq!(cycle.len(), 3);
+        assert_eq!(cycle.next(NodeColor::Blue), NodeColor::Off);
+
+        let mut rng = StdRng::seed_from_u64(11);
+        let (target, _) =
+            generate_random_target_from_start(&mut rng, START_STATE, cycle, PressRule::Classic);
+        assert!(cycle.check_state(target).is_ok());
+
+        let path = shortest_solution(START_STATE, target, cycle, PressRule::Classic)
+            .expect("path should exist");
+        let state = path.iter().fold(START_STATE, |state, &press| {
+            press_indicator(state, press, cycle, PressRule::Classic)
+        });
+        assert_eq!(state, target);
+    }
+
+    #[test]
+    fn color_cycle_requires_off_and_another_color() {
+        assert!(ColorCycle::parse("GREEN,BLUE").is_err());
+        assert!(ColorCycle::parse("OFF").is_err());
+        assert!(ColorCycle::parse("OFF,TEAL").is_err());
+    }
+
+    #[test]
+    fn par_summary_reports_projected_score() {
+        let mut puzzle = new_puzzle_state_from_rng(
+            &mut StdRng::seed_from_u64(5),
+            START_STATE,
+            ColorCycle::FULL,
+            PressRule::Classic,
+        );
+        puzzle.optimal_moves = 4;
+        puzzle.distance_to_target = 3;
+        puzzle.moves_taken = 3;
+        assert_eq!(par_summary(&puzzle), "Par: 3 to go   taken 3   +2 over par");
+
+        puzzle.moves_taken = 1;
+        assert!(par_summary(&puzzle).ends_with("even par"));
+
+        puzzle.distance_to_target = 0;
+        assert_eq!(par_summary(&puzzle), "Par: on the green (solved)");
+    }
+
+    #[test]
+    fn command_sink_reports_failures() {
+        let mut ok = CommandSink {
+            command: "cat > /dev/null".to_string(),
+        };
+        assert!(ok.submit("1,a@example.com").is_ok());
+
+        let mut failing = CommandSink {
+            command: "exit 3".to_string(),
+        };
+        assert!(failing.submit("1,a@example.com").is_err());
+    }
+
+    #[test]
+    fn script_tokens_map_to_key_events() {
+        let steps = parse_script("Right\n# comment\n\nChar:a\nCtrl:c\nF12\nWait:50\nspace\n")
+            .expect("valid script");
+        assert_eq!(
+            steps.into_iter().collect::<Vec<_>>(),
+            vec![
+                ScriptStep::Key(KeyEvent::new(KeyCode::Right, KeyModifiers::NONE)),
+                ScriptStep::Key(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE)),
+                ScriptStep::Key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+                ScriptStep::Key(KeyEvent::new(KeyCode::F(12), KeyModifiers::NONE)),
+                ScriptStep::Wait(Duration::from_millis(50)),
+                ScriptStep::Key(KeyEvent::new(KeyCode::Char(' '), KeyModifiers::NONE)),
+            ]
+        );
+    }
+
+    #[test]
+    fn script_rejects_unknown_tokens() {
+        assert!(parse_script("Right\nJump\n").is_err());
+        assert!(parse_script("Char:ab").is_err());
+        assert!(parse_script("Wait:soon").is_err());
+    }
+
+    #[test]
+    fn email_validation_follows_the_documented_grammar() {
+        let cases = [
+            ("alice@example.com", true),
+            ("a.b@example.co", true),
+            ("first_last@mail.example.org", true),
+            ("tag+events@example.io", true),
+            ("x-y@sub-domain.example.net", true),
+            ("a1@b2.de", true),
+            ("ALICE@EXAMPLE.COM", true),
+            ("a@b.c", false),
+            ("alice@example", false),
+            ("alice@example..com", false),
+            ("alice@.example.com", false),
+            ("alice@example.com.", false),
+            ("alice@-example.com", false),
+            ("alice@example-.com", false),
+            (".alice@example.com", false),
+            ("alice.@example.com", false),
+            ("al..ice@example.com", false),
+            ("+alice@example.com", false),
+            ("alice@example.c0m", false),
+            ("ali ce@example.com", false),
+            ("alice@@example.com", false),
+            ("alice@exa@mple.com", false),
+            ("@example.com", false),
+            ("alice@ex_ample.com", false),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(is_valid_email(input), expected, "input: {input}");
+        }
+    }
+
+    #[test]
+    fn email_fix_suggestions_cover_common_typos() {
+        let cases = [
+            ("alice@gmai.com", Some("alice@gmail.com")),
+            ("alice@gmail.con", Some("alice@gmail.com")),
+            ("alicegmail.com", Some("alice@gmail.com")),
+            ("alice@@example.org", Some("alice@example.org")),
+            ("alice@yahooo.com", Some("alice@yahoo.com")),
+            ("bob@example.ogr", Some("bob@example.org")),
+            ("bob@example.com", None),
+            ("nonsense", None),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(
+                suggest_email_fix(input).as_deref(),
+                expected,
+                "input: {input}"
+            );
+        }
+    }
+
+    #[test]
+    fn finishing_presses_mark_only_single_press_solutions() {
+        let cycle = ColorCycle::FULL;
+        let initial = START_STATE;
+        let target = press_indicator(initial, 2, cycle, PressRule::Classic);
+        let puzzle =
+            puzzle_state_for(initial, target, cycle, PressRule::Classic).expect("reachable target");
+        let finishing = finishing_presses(&puzzle);
+        for (index, marked) in finishing.iter().enumerate() {
+            assert_eq!(
+                *marked,
+                press_indicator(initial, index, cycle, PressRule::Classic) == target,
+                "indicator {index}"
+            );
+        }
+        assert!(finishing[2]);
+
+        let solved =
+            puzzle_state_for(target, target, cycle, PressRule::Classic).expect("solved state");
+        assert_eq!(finishing_presses(&solved), [false; INDICATOR_COUNT]);
+    }
+
+    #[test]
+    fn session_limit_grants_grace_only_to_unsubmitted_solves() {
+        let now = Instant::now();
+        let expired = |on_unsubmitted| SessionLimit {
+            deadline: now,
+            on_unsubmitted,
+            grace_until: None,
+        };
+
+        let mut app = test_app();
+        app.session_limit = Some(expired(UnsubmittedPolicy::Prompt));
+        assert!(tick_session_limit(&mut app, now));
+        assert!(app.should_quit);
+
+        let mut app = test_app();
+        app.phase = AppPhase::Email;
+        app.session_limit = Some(expired(UnsubmittedPolicy::Prompt));
+        assert!(tick_session_limit(&mut app, now));
+        assert!(!app.should_quit);
+        tick_session_limit(&mut app, now + SESSION_SUBMIT_GRACE);
+        assert!(app.should_quit);
+
+        let mut app = test_app();
+        app.phase = AppPhase::Email;
+        app.session_limit = Some(expired(UnsubmittedPolicy::Discard));
+        tick_session_limit(&mut app, now);
+        assert!(app.should_quit);
+    }
+
+    #[test]
+    fn idle_timeout_shows_a_notice_mid_task_and_resets_on_input() {
+        let now = Instant::now();
+        let idle = |last_input| IdleTimeout {
+            window: Duration::from_secs(60),
+            last_input,
+            quit_at: None,
+        };
+
+        let mut app = test_app();
+        app.idle = Some(idle(now));
+        assert!(!tick_idle_timeout(&mut app, now + Duration::from_secs(59)));
+        assert!(tick_idle_timeout(&mut app, now + Duration::from_secs(60)));
+        assert!(!app.should_quit);
+        assert_eq!(app.puzzle.status, "Session timed out.");
+
+        note_activity(&mut app, now + Duration::from_secs(61));
+        assert!(!tick_idle_timeout(&mut app, now + Duration::from_secs(90)));
+        assert!(tick_idle_timeout(&mut app, now + Duration::from_secs(121)));
+        assert!(tick_idle_timeout(
+            &mut app,
+            now + Duration::from_secs(121) + IDLE_NOTICE
+        ));
+        assert!(app.should_quit);
+
+        let mut app = test_app();
+        app.phase = AppPhase::Submitted;
+        app.idle = Some(idle(now));
+        assert!(tick_idle_timeout(&mut app, now + Duration::from_secs(60)));
+        assert!(app.should_quit);
+    }
+
+    #[test]
+    fn worst_position_hint_picks_lowest_furthest_index() {
+        use NodeColor::*;
+        let cycle = ColorCycle::FULL;
+        let target = [Green, Off, Off, Off, Off, Green];
+        let puzzle = puzzle_state_for(START_STATE, target, cycle, PressRule::Classic)
+            .expect("reachable target");
+
+        assert_eq!(cycle.steps_between(Off, Green), 1);
+        assert_eq!(cycle.steps_between(Green, Off), 5);
+        assert_eq!(
+            worst_position_hint(&puzzle),
+            "Indicator 1 is furthest off: 1 step from GREEN. Pressing 2 brings it to 0."
+        );
+
+        let solved =
+            puzzle_state_for(target, target, cycle, PressRule::Classic).expect("solved state");
+        assert_eq!(worst_position_hint(&solved), "All positions on target.");
+    }
+
+    #[test]
+    fn preferences_round_trip_and_reject_corrupt_files() {
+        let prefs = Preferences {
+            finishing_hint: false,
+            show_rules: true,
+            skip_matched: true,
+            reduced_motion: true,
+            color_blind: true,
+            difficulty: Difficulty::Hard,
+        };
+        assert_eq!(Preferences::parse(&prefs.render()), Ok(prefs));
+        assert!(Preferences::parse("finishing_hint=maybe").is_err());
+        assert!(Preferences::parse("garbage").is_err());
+
+        let path = env::temp_dir().join(format!("boaai_prefs_test_{}.conf", process::id()));
+        fs::write(&path, "\u{0}not a prefs file").expect("write corrupt prefs");
+        assert_eq!(Preferences::load(&path), Preferences::default());
+        prefs.save(&path).expect("save prefs");
+        assert_eq!(Preferences::load(&path), prefs);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn composite_score_is_pinned_for_known_inputs() {
+        let weights = ScoreWeights::default();
+        assert_eq!(compute_score(4, 4, 0, weights), 1000);
+        assert_eq!(compute_score(4, 4, 30, weights), 850);
+        assert_eq!(compute_score(7, 4, 30, weights), 700);
+        assert_eq!(compute_score(3, 4, 10, weights), 950);
+        assert_eq!(compute_score(40, 4, 600, weights), 0);
+
+        let custom = ScoreWeights {
+            per_extra_move: 100,
+            per_second: 1,
+        };
+        assert_eq!(compute_score(6, 4, 45, custom), 755);
+    }
+
+    #[test]
+    fn debug_solve_counts_only_applied_presses() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut puzzle =
+            new_puzzle_state_from_rng(&mut rng, START_STATE, ColorCycle::FULL, PressRule::Classic);
+        assert!(debug_solve(&mut puzzle));
+        assert_eq!(puzzle.current, puzzle.target);
+        assert_eq!(puzzle.moves_taken, puzzle.optimal_moves);
+        assert_eq!(puzzle.moves.len(), puzzle.moves_taken);
+
+        let taken = puzzle.moves_taken;
+        assert!(debug_solve(&mut puzzle));
+        assert_eq!(puzzle.moves_taken, taken);
+        assert_eq!(puzzle.status, "Debug solve used 0 move(s).");
+    }
+
+    #[test]
+    fn ring_layout_hit_tests_each_indicator_and_falls_back_when_short() {
+        let ring = indicator_grid(2, 116, 12, 34, IndicatorLayout::Ring);
+        assert_eq!(ring.below, 21);
+        for (index, &(x, y)) in ring.cells.iter().enumerate() {
+            assert_eq!(ring.hit(x + ring.width / 2, y + 1), Some(index));
+        }
+        assert_eq!(ring.hit(2 + 116 / 2, 12 + 4), None);
+        // Opposite indicators mirror each other through the ring's center.
+        let center_sum = ring.cells[0].0 + ring.cells[3].0;
+        for index in 0..3 {
+            let (a, b) = (ring.cells[index], ring.cells[index + 3]);
+            assert_eq!(a.0 + b.0, center_sum);
+            assert_eq!(a.1 + b.1, 2 * (12 + 3));
+        }
+
+        let short = indicator_grid(2, 116, 12, 22, IndicatorLayout::Ring);
+        assert_eq!(short.below, 15);
+        assert!(short.cells.iter().all(|&(_, y)| y == 12));
+    }
+
+    #[test]
+    fn config_errors_name_the_specific_problem() {
+        assert_eq!(
+            parse_state_spec("OFF,GREEN"),
+            Err(ConfigError::LengthMismatch {
+                expected: INDICATOR_COUNT,
+                found: 2
+            })
+        );
+        assert_eq!(
+            parse_start_overrides("9=RED"),
+            Err(ConfigError::BadIndex("9".to_string()))
+        );
+        assert_eq!(
+            parse_start_overrides("2=RED,2=BLUE"),
+            Err(ConfigError::DuplicateIndex(2))
+        );
+        assert_eq!(ColorCycle::parse("GREEN"), Err(ConfigError::MissingOff));
+        assert_eq!(
+            parse_script("Right\nJump\n").unwrap_err().to_string(),
+            "line 2: unknown key 'Jump'"
+        );
+
+        let cycle = ColorCycle::parse("OFF,GREEN").expect("valid color set");
+        let target = [NodeColor::Blue; INDICATOR_COUNT];
+        assert!(matches!(
+            puzzle_state_for(START_STATE, target, cycle, PressRule::Classic),
+            Err(ConfigError::ColorOutsideCycle(NodeColor::Blue))
+        ));
+    }
+
+    #[test]
+    fn drills_have_real_targets_and_return_to_the_menu() {
+        for (index, drill) in DRILLS.iter().enumerate() {
+            let mut app = test_app();
+            start_drill(&mut app, index);
+            assert_ne!(app.puzzle.target, app.puzzle.initial, "{}", drill.name);
+            assert!(app.puzzle.optimal_moves <= drill.presses.len());
+
+            debug_solve(&mut app.puzzle);
+            enter_solved_hold(&mut app);
+            complete_puzzle(&mut app);
+            assert!(matches!(app.phase, AppPhase::Drills));
+            assert!(app.drill_status.contains(drill.lesson));
+        }
+    }
+
+    #[test]
+    fn event_times_parse_as_utc_and_count_down() {
+        assert_eq!(parse_event_time("1700000000"), Some(1_700_000_000));
+        assert_eq!(parse_event_time("1970-01-01"), Some(0));
+        assert_eq!(parse_event_time("2000-03-01"), Some(951_868_800));
+        assert_eq!(
+            parse_event_time("2026-11-20T18:30:00Z"),
+            Some(1_795_199_400)
+        );
+        assert_eq!(parse_event_time("2026-11-20 18:30"), Some(1_795_199_400));
+        assert_eq!(parse_event_time("2026-13-01"), None);
+        assert_eq!(parse_event_time("next friday"), None);
+
+        assert_eq!(format_countdown(2 * 86_400 + 4 * 3600 + 59), "2d 4h");
+        assert_eq!(format_countdown(3 * 3600 + 15 * 60), "3h 15m");
+        assert_eq!(format_countdown(20), "1m");
+    }
+
+    #[test]
+    fn survival_deepens_on_clear_and_ends_when_budget_runs_out() {
+        let record_path =
+            env::temp_dir().join(format!("boaai_survival_test_{}.csv", process::id()));
+        let _ = fs::remove_file(&record_path);
+        let mut app = test_app();
+        start_survival(
+            &mut app,
+            SurvivalRun {
+                depth: 1,
+                depth_step: 2,
+                budget: Duration::from_secs(60),
+                cleared: 0,
+                started_at: Instant::now(),
+                puzzle_started: Instant::now(),
+                total: None,
+                record_path: record_path.clone(),
+                record_status: String::new(),
+            },
+        );
+        assert!(app.puzzle.optimal_moves <= 1);
+
+        debug_solve(&mut app.puzzle);
+        enter_solved_hold(&mut app);
+        complete_puzzle(&mut app);
+        let run = app.survival.as_ref().expect("run in progress");
+        assert_eq!((run.cleared, run.depth), (1, 3));
+        assert!(matches!(app.phase, AppPhase::Puzzle));
+
+        app.survival.as_mut().expect("run in progress").budget = Duration::ZERO;
+        assert!(tick_app(&mut app));
+        assert!(matches!(app.phase, AppPhase::GameOver));
+        assert_eq!(app.score, Some(1));
+        let log = fs::read_to_string(&record_path).expect("run recorded");
+        assert!(log.starts_with("ended_unix,cleared,total_secs,final_depth\n"));
+        assert!(log.lines().nth(1).is_some_and(|line| line.contains(",1,")));
+        let _ = fs::remove_file(&record_path);
+    }
+
+    #[test]
+    fn ellipsis_trim_fits_the_width_budget() {
+        assert_eq!(trim_to_width_ellipsis("status", 6), "status");
+        assert_eq!(trim_to_width_ellipsis("status line", 7), "status…");
+        assert_eq!(trim_to_width_ellipsis("status", 1), "…");
+        assert_eq!(trim_to_width_ellipsis("status", 0), "");
+        assert_eq!(trim_to_width_ellipsis("", 0), "");
+    }
+
+    #[test]
+    fn width_helpers_count_wide_characters_as_two_columns() {
+        assert_eq!(display_width("ab日本"), 6);
+        assert_eq!(display_width("ｐｌａｙｅｒ@x.co"), 17);
+        assert_eq!(display_width("e\u{301}"), 1);
+        assert_eq!(trim_to_width("ab日本", 5), "ab日");
+        assert_eq!(trim_to_width("ab日本", 4), "ab日");
+        assert_eq!(trim_to_width("ab日本", 3), "ab");
+        assert_eq!(trim_to_width_ellipsis("ab日本語", 6), "ab日…");
+        assert_eq!(center_text("日本", 7), " 日本  ");
+
+        let mixed = "mail-ｗｉｄｅ-日本語-😀@example.com";
+        for budget in 0..=display_width(mixed) + 2 {
+            assert!(display_width(&trim_to_width(mixed, budget)) <= budget);
+            assert!(display_width(&trim_to_width_ellipsis(mixed, budget)) <= budget);
+            assert_eq!(display_width(&center_text(mixed, budget)), budget);
+        }
+    }
+
+    #[test]
+    fn skip_matched_navigation_passes_over_solved_positions() {
+        use NodeColor::*;
+        let cycle = ColorCycle::FULL;
+        let target = press_indicator(START_STATE, 0, cycle, PressRule::Classic);
+        let mut puzzle = puzzle_state_for(START_STATE, target, cycle, PressRule::Classic)
+            .expect("reachable target");
+        puzzle.current = target;
+        puzzle.current[1] = Off;
+        puzzle.current[4] = Red;
+        if target[4] == Red {
+            puzzle.current[4] = Blue;
+        }
+
+        assert_eq!(step_focus(&puzzle, 0, true, false), 1);
+        assert_eq!(step_focus(&puzzle, 0, false, false), 5);
+        assert_eq!(step_focus(&puzzle, 1, true, true), 4);
+        assert_eq!(step_focus(&puzzle, 4, true, true), 1);
+        assert_eq!(step_focus(&puzzle, 1, false, true), 4);
+
+        // With one position left, focus reaches it and then stays on it.
+        puzzle.current[4] = target[4];
+        assert_eq!(step_focus(&puzzle, 3, true, true), 1);
+        assert_eq!(step_focus(&puzzle, 1, true, true), 1);
+        assert_eq!(step_focus(&puzzle, 1, false, true), 1);
+
+        puzzle.current = target;
+        assert_eq!(step_focus(&puzzle, 2, true, true), 2);
+    }
+
+    #[test]
+    fn reduced_motion_snaps_to_the_same_final_states() {
+        let mut app = test_app();
+        app.campaign = None;
+        app.reduced_motion = true;
+        debug_solve(&mut app.puzzle);
+        enter_solved_hold(&mut app);
+        assert!(matches!(app.phase, AppPhase::Email));
+
+        app.phase = AppPhase::Submitted;
+        handle_submitted_key(
+            &mut app,
+            KeyEvent::new(KeyCode::Char('r'), KeyModifiers::NONE),
+        );
+        let replay = app.solution_replay.as_ref().expect("replay started");
+        assert_eq!(replay.step, app.puzzle.moves.len());
+        assert_eq!(replay.state, app.puzzle.target);
+    }
+
+    #[test]
+    fn email_caret_stays_in_view_across_resizes() {
+        let email = "someone.with.a.rather.long.address@subdomain.example.com";
+        let caret = email.len();
+        for width in [60, 24, 8, 2, 1] {
+            let (visible, caret_col) = email_field_window(email, caret, width);
+            assert!(caret_col < width, "caret off-screen at width {width}");
+            assert!(display_width(&visible) < width);
+            assert!(email.ends_with(visible.strip_prefix('…').unwrap_or(&visible)));
+            assert_eq!(visible.starts_with('…'), (2..=email.len()).contains(&width));
+        }
+        let (visible, caret_col) = email_field_window("a@b.co", 6, 40);
+        assert_eq!((visible.as_str(), caret_col), ("a@b.co", 6));
+
+        // Wide characters scroll by columns, so the caret cell still fits.
+        let (visible, caret_col) = email_field_window("太郎太郎@example.jp", 16, 10);
+        assert_eq!((visible.as_str(), caret_col), ("…ample.jp", 9));
+        let (visible, caret_col) = email_field_window("太郎太郎太郎", 6, 8);
+        assert_eq!((visible.as_str(), caret_col), ("…郎太郎", 4));
+        assert_eq!(display_width(&visible) + 1, 8);
+        // After backspacing back under the width the view unscrolls.
+        let (visible, caret_col) = email_field_window("太郎太", 3, 8);
+        assert_eq!((visible.as_str(), caret_col), ("太郎太", 3));
+
+        let mut app = test_app();
+        app.phase = AppPhase::Email;
+        app.email.email = format!("{}{email}", "x".repeat(50));
+        for cols in [130, 78] {
+            let mut frame = Vec::new();
+            draw_frame(&mut frame, &app, cols, 40).expect("frame renders");
+            let frame = String::from_utf8_lossy(&frame);
+            assert!(
+                frame.contains("example.com_"),
+                "caret lost at {cols} columns"
+            );
+        }
+    }
+
+    #[test]
+    fn optimal_reveal_compares_solver_path_with_player_moves() {
+        let target = press_indicator(
+            press_indicator(START_STATE, 0, ColorCycle::FULL, PressRule::Classic),
+            3,
+            ColorCycle::FULL,
+            PressRule::Classic,
+        );
+        let mut puzzle =
+            puzzle_state_for(START_STATE, target, ColorCycle::FULL, PressRule::Classic)
+                .expect("valid");
+        puzzle.moves = vec![3, 0, 2, 2, 2, 2, 2, 2];
+        let [optimal, yours] = optimal_reveal_lines(&puzzle).expect("solvable");
+        assert!(optimal.starts_with("Optimal path (2 press(es)): "));
+        assert_eq!(yours, "Your path    (8 press(es)): 4 1 3 3 3 3 3 3");
+    }
+
+    #[test]
+    fn selftest_case_accepts_optimal_solve_under_strictest_cap() {
+        let case = SelftestCase {
+            cycle: ColorCycle::parse("OFF,GREEN,BLUE").expect("valid color set"),
+            rule: PressRule::Classic,
+            start: START_STATE,
+            scramble: 6,
+            max_moves_over_optimal: Some(0),
+            layout: IndicatorLayout::Ring,
+            difficulty: None,
+            tight_move_cap: false,
+        };
+        assert_eq!(run_selftest_case(&case, 3), Ok(()));
+        let capped = SelftestCase {
+            difficulty: Some(Difficulty::Hard),
+            tight_move_cap: true,
+            ..case
+        };
+        assert_eq!(run_selftest_case(&capped, 1002), Ok(()));
+        assert!(capped.to_string().ends_with("move_cap=optimal"));
+        assert!(case
+            .to_string()
+            .starts_with("colors=OFF,GREEN,BLUE rule=classic start=OFF,"));
+    }
+
+    #[test]
+    fn undo_and_redo_walk_the_press_history() {
+        let mut app = test_app();
+        app.reduced_motion = false;
+        let target = press_indicator(START_STATE, 1, ColorCycle::FULL, PressRule::Classic);
+        app.puzzle = puzzle_state_for(START_STATE, target, ColorCycle::FULL, PressRule::Classic)
+            .expect("valid");
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        app.puzzle.focus = PuzzleFocus::Indicator(4);
+        handle_key(&mut app, key(KeyCode::Enter)).unwrap();
+        handle_key(&mut app, key(KeyCode::Char('u'))).unwrap();
+        assert_eq!(app.puzzle.current, START_STATE);
+        assert_eq!(
+            (app.puzzle.moves_taken, app.puzzle.status.as_str()),
+            (0, "Undid move (indicator 5). 0/6 nodes aligned.")
+        );
+        handle_key(&mut app, key(KeyCode::Char('u'))).unwrap();
+        assert_eq!(app.puzzle.status, "Nothing to undo.");
+
+        handle_key(&mut app, key(KeyCode::Char('y'))).unwrap();
+        assert_eq!(app.puzzle.moves, vec![4]);
+        handle_key(&mut app, key(KeyCode::Char('y'))).unwrap();
+        assert_eq!(app.puzzle.status, "Nothing to redo.");
+
+        // A fresh press drops the redo stack.
+        handle_key(&mut app, key(KeyCode::Char('u'))).unwrap();
+        app.puzzle.focus = PuzzleFocus::Indicator(0);
+        handle_key(&mut app, key(KeyCode::Enter)).unwrap();
+        assert!(app.puzzle.redo_stack.is_empty());
+
+        // Undo to the start, then redo into the target: that is a solve.
+        handle_key(&mut app, key(KeyCode::Char('u'))).unwrap();
+        assert!(matches!(app.phase, AppPhase::Puzzle));
+        app.puzzle.redo_stack = vec![1];
+        handle_key(
+            &mut app,
+            KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL),
+        )
+        .unwrap();
+        assert!(matches!(app.phase, AppPhase::Solved));
+        assert_eq!(app.puzzle.moves, vec![1]);
+    }
+
+    #[test]
+    fn clicks_hit_action_buttons_and_email_controls() {
+        let mut app = test_app();
+        app.layout = IndicatorLayout::Row;
+        let frame = frame_layout(120, 40).expect("large enough");
+        let bottom = frame.body_y + frame.body_height - 1;
+        let grid = indicator_grid(
+            frame.x,
+            frame.width,
+            indicator_row(frame.body_y),
+            bottom,
+            app.layout,
+        );
+        let (reset_x, reset_y) = action_cells(frame.x, frame.width, grid.below + 1)[1];
+        assert_eq!(
+            hit_test(&app, 120, 40, reset_x + 1, reset_y + 1),
+            Some(ClickTarget::Action(1))
+        );
+        let (cell_x, cell_y) = grid.cells[3];
+        assert_eq!(
+            hit_test(&app, 120, 40, cell_x, cell_y + 2),
+            Some(ClickTarget::Indicator(3))
+        );
+
+        app.phase = AppPhase::Email;
+        let layout = email_layout(frame.x, frame.body_y, frame.width);
+        let (button_x, button_y) = layout.buttons[1];
+        let click = |column, row| MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        };
+        assert_eq!(
+            hit_test(&app, 120, 40, layout.field.0 + 2, layout.field.1 + 1),
+            Some(ClickTarget::EmailInput)
+        );
+        assert_eq!(hit_test(&app, 120, 40, button_x - 1, button_y), None);
+        // Solve Again goes back to the puzzle, as Enter on it would.
+        assert!(handle_mouse(&mut app, click(button_x + 3, button_y + 1), 120, 40).unwrap());
+        assert!(matches!(app.phase, AppPhase::Puzzle));
+    }
+
+    #[test]
+    fn seeded_puzzles_reproduce_and_start_reachable() {
+        let subset = ColorCycle::parse("OFF,GREEN,BLUE").expect("valid color set");
+        for cycle in [ColorCycle::FULL, subset] {
+            for seed in [0, 7, 12_345] {
+                let puzzle =
+                    seeded_puzzle_state(seed, START_STATE, cycle, PressRule::Classic, true, None);
+                let again =
+                    seeded_puzzle_state(seed, START_STATE, cycle, PressRule::Classic, true, None);
+                assert_eq!(
+                    (puzzle.initial, puzzle.target),
+                    (again.initial, again.target)
+                );
+                assert_ne!(puzzle.initial, START_STATE);
+                assert!(
+                    solution_depth(START_STATE, puzzle.initial, cycle, PressRule::Classic)
+                        .is_some()
+                );
+                assert_eq!(
+                    Some(puzzle.optimal_moves),
+                    solution_depth(puzzle.initial, puzzle.target, cycle, PressRule::Classic)
+                );
+                assert_eq!(puzzle.status, format!("Seed {seed}. Good luck"));
+            }
+        }
+        let pinned = seeded_puzzle_state(
+            3,
+            START_STATE,
+            ColorCycle::FULL,
+            PressRule::Classic,
+            false,
+            None,
+        );
+        assert_eq!((pinned.initial, pinned.seed), (START_STATE, Some(3)));
+    }
+
+    #[test]
+    fn target_spec_accepts_reachable_names_and_rejects_malformed() {
+        let cycle = ColorCycle::FULL;
+        let target = parse_target_spec(
+            "white,PURPLE,Green,WHITE,purple,GREEN",
+            START_STATE,
+            cycle,
+            PressRule::Classic,
+        )
+        .expect("valid target");
+        assert_eq!(
+            render_state_spec(target),
+            "WHITE,PURPLE,GREEN,WHITE,PURPLE,GREEN"
+        );
+        let puzzle = seeded_puzzle_state(
+            9,
+            START_STATE,
+            cycle,
+            PressRule::Classic,
+            true,
+            Some(target),
+        );
+        assert_eq!(puzzle.target, target);
+        assert_ne!(puzzle.initial, target);
+
+        assert_eq!(
+            parse_target_spec("WHITE,PURPLE,GREEN", START_STATE, cycle, PressRule::Classic),
+            Err(ConfigError::LengthMismatch {
+                expected: INDICATOR_COUNT,
+                found: 3
+            })
+        );
+        assert_eq!(
+            parse_target_spec(
+                "WHITE,PURPLE,GREEN,WHITE,PURPLE,TEAL",
+                START_STATE,
+                cycle,
+                PressRule::Classic,
+            ),
+            Err(ConfigError::InvalidColor("'TEAL'".to_string()))
+        );
+        let subset = ColorCycle::parse("OFF,GREEN,BLUE").expect("valid color set");
+        assert_eq!(
+            parse_target_spec(
+                "WHITE,OFF,OFF,OFF,OFF,OFF",
+                START_STATE,
+                subset,
+                PressRule::Classic,
+            ),
+            Err(ConfigError::ColorOutsideCycle(NodeColor::White))
+        );
+    }
+
+    #[test]
+    fn submission_record_round_trips_through_csv_and_json() {
+        let record = SubmissionRecord {
+            submitted_unix: 1_795_199_400,
+            email: "player+1@example.com".to_string(),
+            score: Some(875),
+            solve_seconds: Some(42),
+            session_id: Some("1a2b3c4d".to_string()),
+            moves: Some(vec![0, 1, 1, 2, 4]),
+        };
+        let row = record.encode(InviteFormat::Csv);
+        assert_eq!(
+            row,
+            r#"1795199400,player+1@example.com,875,42,1a2b3c4d,"1,2,2,3,5""#
+        );
+        let fields: Vec<&str> = row.splitn(6, ',').collect();
+        let from_csv = SubmissionRecord {
+            submitted_unix: fields[0].parse().unwrap(),
+            email: fields[1].to_string(),
+            score: fields[2].parse().ok(),
+            solve_seconds: fields[3].parse().ok(),
+            session_id: Some(fields[4].to_string()),
+            moves: Some(vec![0, 1, 1, 2, 4]),
+        };
+        assert_eq!(from_csv, record);
+
+        let line = record.encode(InviteFormat::Json);
+        assert_eq!(
+            line,
+            r#"{"submitted_unix":1795199400,"email":"player+1@example.com","score":875,"solve_seconds":42,"session_id":"1a2b3c4d","moves":"1,2,2,3,5"}"#
+        );
+        let value = |key: &str| {
+            let rest = &line[line.find(&format!("\"{key}\":")).unwrap() + key.len() + 3..];
+            rest[..rest.find([',', '}']).unwrap()]
+                .trim_matches('"')
+                .to_string()
+        };
+        let from_json = SubmissionRecord {
+            submitted_unix: value("submitted_unix").parse().unwrap(),
+            email: value("email"),
+            score: value("score").parse().ok(),
+            solve_seconds: value("solve_seconds").parse().ok(),
+            session_id: Some(value("session_id")),
+            moves: record.moves.clone(),
+        };
+        assert_eq!(from_json, record);
+        assert_eq!(
+            SubmissionRecord::parse(&row, InviteFormat::Csv).as_ref(),
+            Some(&record)
+        );
+        assert_eq!(
+            SubmissionRecord::parse(&line, InviteFormat::Json).as_ref(),
+            Some(&record)
+        );
+
+        let unscored = SubmissionRecord {
+            score: None,
+            solve_seconds: None,
+            session_id: None,
+            moves: None,
+            ..record.clone()
+        };
+        assert!(unscored
+            .to_json_line()
+            .ends_with(r#""score":null,"solve_seconds":null,"session_id":null,"moves":null}"#));
+        assert!(unscored.to_csv_row().ends_with("example.com,,,,"));
+        for line in [unscored.to_csv_row(), unscored.to_json_line()] {
+            let format = if line.starts_with('{') {
+                InviteFormat::Json
+            } else {
+                InviteFormat::Csv
+            };
+            assert_eq!(
+                SubmissionRecord::parse(&line, format),
+                Some(unscored.clone())
+            );
+        }
+        // Rows written before the session and moves columns still read back.
+        assert_eq!(
+            SubmissionRecord::parse("1795199400,player+1@example.com,875,42", InviteFormat::Csv),
+            Some(SubmissionRecord {
+                session_id: None,
+                moves: None,
+                ..record.clone()
+            })
+        );
+        // So do the original two-column `submitted_unix,email` rows.
+        assert_eq!(
+            SubmissionRecord::parse("1795199400,player+1@example.com", InviteFormat::Csv),
+            Some(SubmissionRecord {
+                score: None,
+                solve_seconds: None,
+                session_id: None,
+                moves: None,
+                ..record
+            })
+        );
+        assert_eq!(json_escape("a\"b\\c"), r#"a\"b\\c"#);
+    }
+
+    #[test]
+    fn registered_emails_are_read_back_case_insensitively() {
+        for format in [InviteFormat::Csv, InviteFormat::Json] {
+            let path = env::temp_dir().join(format!(
+                "boaai_invites_test_{}_{format:?}.txt",
+                process::id()
+            ));
+            let _ = fs::remove_file(&path);
+            let mut sink = FileSink {
+                path: path.clone(),
+                format,
+            };
+            assert!(sink.registered_emails().unwrap().is_empty());
+            for email in ["Alice@X.com", "bob@example.com"] {
+                let record = SubmissionRecord {
+                    submitted_unix: 1,
+                    email: email.to_string(),
+                    score: None,
+                    solve_seconds: None,
+                    session_id: None,
+                    moves: None,
+                };
+                sink.submit(&record.encode(format)).unwrap();
+            }
+            let registered = sink.registered_emails().unwrap();
+            fs::remove_file(&path).unwrap();
+            assert_eq!(registered.len(), 2);
+            assert!(registered.contains(&normalize_email(" alice@x.com ")));
+        }
+    }
+
+    #[test]
+    fn solve_timer_freezes_on_solve_and_resets_on_solve_again() {
+        let mut app = test_app();
+        app.reduced_motion = false;
+        app.campaign = None;
+        app.puzzle.started_at = Instant::now() - Duration::from_secs(83);
+        enter_solved_hold(&mut app);
+        assert!(moves_segment(&app).ends_with(" 01:23"));
+        complete_puzzle(&mut app);
+        assert_eq!(app.solve_secs, Some(83));
+
+        app.email.focus = EmailFocus::Buttons;
+        app.email.selected_button = 1;
+        handle_key(&mut app, KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)).unwrap();
+        assert!(matches!(app.phase, AppPhase::Puzzle));
+        assert!(solve_clock(&app.puzzle) < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn leaderboard_keeps_top_ten_by_score_then_time_then_moves() {
+        let path = env::temp_dir().join(format!("boaai_leaderboard_test_{}.csv", process::id()));
+        let _ = fs::remove_file(&path);
+        assert_eq!(load_leaderboard(&path).unwrap(), Vec::new());
+
+        let entry = |email: &str, solve_seconds, moves_taken, score| LeaderboardEntry {
+            email: email.to_string(),
+            solve_seconds,
+            moves_taken,
+            score,
+        };
+        for index in 0..11 {
+            record_leaderboard(
+                &path,
+                entry(&format!("p{index}@x.co"), 30 + index, 8, 900 - index as u32),
+            )
+            .unwrap();
+        }
+        record_leaderboard(&path, entry("tie@x.co", 31, 5, 899)).unwrap();
+        // Slower, but the score is what ranks.
+        record_leaderboard(&path, entry("best@x.co", 90, 4, 950)).unwrap();
+        let entries = load_leaderboard(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(entries.len(), LEADERBOARD_SIZE);
+        assert_eq!(entries[0], entry("best@x.co", 90, 4, 950));
+        assert_eq!(entries[1], entry("p0@x.co", 30, 8, 900));
+        assert_eq!(entries[2], entry("tie@x.co", 31, 5, 899));
+        assert_eq!(entries[9].score, 893);
+
+        fs::write(&path, "email,solve_seconds,moves_taken\nold@x.co,40,6\n").unwrap();
+        let old = load_leaderboard(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(old, vec![entry("old@x.co", 40, 6, 0)]);
+    }
+
+    #[test]
+    fn color_blind_palette_marks_every_color_distinctly() {
+        let glyphs: HashSet<char> = NodeColor::ALL.into_iter().map(NodeColor::glyph).collect();
+        let hues: HashSet<String> = NodeColor::ALL
+            .into_iter()
+            .map(|color| format!("{:?}", color.term_color(Palette::ColorBlind)))
+            .collect();
+        assert_eq!(glyphs.len(), NodeColor::ALL.len());
+        assert_eq!(hues.len(), NodeColor::ALL.len());
+        assert_eq!(NodeColor::Red.state_token(Palette::Default), "RED");
+        assert_eq!(NodeColor::Red.state_token(Palette::ColorBlind), "RED■");
+    }
+
+    #[test]
+    fn saved_puzzle_resumes_with_moves_and_rejects_mismatches() {
+        let path = env::temp_dir().join(format!("boaai_save_test_{}.txt", process::id()));
+        let mut puzzle = seeded_puzzle_state(
+            21,
+            START_STATE,
+            ColorCycle::FULL,
+            PressRule::Classic,
+            true,
+            None,
+        );
+        let first = (0..INDICATOR_COUNT)
+            .find(|&index| {
+                press_indicator(puzzle.current, index, puzzle.cycle, puzzle.rule) != puzzle.target
+            })
+            .expect("some press does not solve");
+        apply_press(&mut puzzle, first);
+        save_puzzle(&path, &puzzle, None).unwrap();
+
+        let (resumed, difficulty) =
+            resume_puzzle(&path, ColorCycle::FULL, PressRule::Classic, None).expect("save fits");
+        assert_eq!(difficulty, None);
+        assert_eq!(
+            (resumed.initial, resumed.current, resumed.target),
+            (puzzle.initial, puzzle.current, puzzle.target)
+        );
+        assert_eq!((resumed.moves_taken, resumed.seed), (1, Some(21)));
+        assert_eq!(resumed.history, vec![puzzle.initial]);
+        assert_eq!(resumed.status, "Resumed saved puzzle");
+
+        let subset = ColorCycle::parse("OFF,GREEN,BLUE").expect("valid color set");
+        let other_target = press_indicator(puzzle.target, 0, ColorCycle::FULL, PressRule::Classic);
+        let mismatches = [
+            resume_puzzle(&path, subset, PressRule::Classic, None).is_none(),
+            resume_puzzle(
+                &path,
+                ColorCycle::FULL,
+                PressRule::Classic,
+                Some(other_target),
+            )
+            .is_none(),
+        ];
+        fs::remove_file(&path).unwrap();
+        assert_eq!(mismatches, [true, true]);
+        assert!(resume_puzzle(&path, ColorCycle::FULL, PressRule::Classic, None).is_none());
+    }
+
+    #[test]
+    fn full_path_wraps_between_presses_and_counts_reveals() {
+        assert_eq!(path_lines(&[0, 1, 1, 2], 40), vec!["Path: 1 → 2 → 2 → 3"]);
+        assert_eq!(
+            path_lines(&[0, 1, 1, 2], 14),
+            vec!["Path: 1 → 2", "→ 2 → 3"]
+        );
+
+        let mut puzzle = seeded_puzzle_state(
+            5,
+            START_STATE,
+            ColorCycle::FULL,
+            PressRule::Classic,
+            true,
+            None,
+        );
+        reveal_full_path(&mut puzzle);
+        reveal_full_path(&mut puzzle);
+        let (board, path) = puzzle.revealed_path.clone().expect("path revealed");
+        assert_eq!(board, puzzle.current);
+        assert_eq!(path.len(), puzzle.optimal_moves);
+        assert_eq!(puzzle.path_reveals, 2);
+        let solved = path.iter().fold(puzzle.current, |state, &index| {
+            press_indicator(state, index, puzzle.cycle, puzzle.rule)
+        });
+        assert_eq!(solved, puzzle.target);
+    }
+
+    #[test]
+    fn confirm_invite_asks_again_and_esc_returns_to_editing() {
+        let mut app = test_app();
+        transition_to_email(&mut app);
+        app.email.email = "player@example.com".to_string();
+        app.email.focus = EmailFocus::Buttons;
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        assert!(handle_email_key(&mut app, key(KeyCode::Enter)).unwrap());
+        assert!(app.email.awaiting_confirmation);
+        assert_eq!(
+            app.email.status,
+            "Submit this email? (Enter to confirm, Esc to edit)"
+        );
+        assert!(!handle_email_key(&mut app, key(KeyCode::Char('x'))).unwrap());
+
+        assert!(handle_email_key(&mut app, key(KeyCode::Esc)).unwrap());
+        assert!(!app.should_quit);
+        assert!(!app.email.awaiting_confirmation);
+        assert!(matches!(app.email.focus, EmailFocus::Input));
+        assert!(matches!(app.phase, AppPhase::Email));
+    }
+
+    #[test]
+    fn parse_args_reads_value_flags_and_rejects_unknown_ones() {
+        let args = |list: &[&str]| list.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+
+        assert!(parse_args(&args(&["--help"])).unwrap().help);
+        assert_eq!(
+            parse_args(&args(&["--frobnicate"])),
+            Err("unknown argument '--frobnicate'".to_string())
+        );
+        assert_eq!(
+            parse_args(&args(&["--seed"])),
+            Err("--seed needs a value".to_string())
+        );
+        assert_eq!(
+            parse_args(&args(&["--seed", "soon"])),
+            Err("invalid value 'soon' for --seed".to_string())
+        );
+
+        let cli = parse_args(&args(&[
+            "--debug",
+            "--seed",
+            "42",
+            "--invite-file",
+            "out.csv",
+            "--no-splash",
+            "--no-alt-screen",
+            "--target",
+            "RED,OFF,OFF,OFF,OFF,OFF",
+            "--code",
+            "ybpc",
+        ]))
+        .unwrap();
+        assert_eq!(
+            cli,
+            CliArgs {
+                debug: true,
+                seed: Some(42),
+                invite_file: Some(PathBuf::from("out.csv")),
+                no_splash: true,
+                no_alt_screen: true,
+                target: Some("RED,OFF,OFF,OFF,OFF,OFF".to_string()),
+                code: Some("ybpc".to_string()),
+                ..CliArgs::default()
+            }
+        );
+
+        let env_config = Config {
+            debug: false,
+            seed: Some(7),
+            target: None,
+            invite_file: Some(PathBuf::from("env.csv")),
+            splash_seconds: 4,
+            palette: None,
+            dry_run: false,
+            press_rule: PressRule::Classic,
+            accent: DEFAULT_ACCENT,
+            warning: None,
+            ..Config::default()
+        };
+        assert_eq!(
+            env_config.clone().with_args(&cli),
+            Config {
+                debug: true,
+                seed: Some(42),
+                target: cli.target.clone(),
+                invite_file: Some(PathBuf::from("out.csv")),
+                splash_seconds: 0,
+                palette: None,
+                dry_run: false,
+                press_rule: PressRule::Classic,
+                accent: DEFAULT_ACCENT,
+                warning: None,
+                ..Config::default()
+            }
+        );
+        assert_eq!(
+            env_config.clone().with_args(&CliArgs::default()),
+            env_config
+        );
+    }
+
+    #[test]
+    fn config_prefers_env_over_file_over_defaults() {
+        let file = ConfigFile::parse(
+            "# boaai.toml\n\
+             debug = true\n\
+             splash_seconds = 1 # short\n\
+             palette = \"cb\" # shapes too\n\
+             target = \"RED,OFF,OFF,OFF,OFF,OFF\"\n\
+             invite_file = \"file.csv\"\n",
+        )
+        .unwrap();
+        let env_vars = HashMap::from([
+            ("BOAAI_SEED", "9"),
+            ("BOAAI_INVITE_FILE", "env.csv"),
+            ("BOAAI_PALETTE", " "),
+            ("BOAAI_MOVE_CAP", "12"),
+            ("BOAAI_LAYOUT", "Ring"),
+        ]);
+        let config = Config::resolve(&file, |name| env_vars.get(name).map(|v| v.to_string()));
+        assert_eq!(
+            config,
+            Config {
+                debug: true,
+                seed: Some(9),
+                target: Some("RED,OFF,OFF,OFF,OFF,OFF".to_string()),
+                invite_file: Some(PathBuf::from("env.csv")),
+                splash_seconds: 1,
+                palette: Some(Palette::ColorBlind),
+                dry_run: false,
+                press_rule: PressRule::Classic,
+                accent: DEFAULT_ACCENT,
+                warning: None,
+                move_cap: Some(12),
+                layout: IndicatorLayout::Ring,
+                ..Config::default()
+            }
+        );
+
+        let defaults = Config::resolve(&ConfigFile::default(), |name| {
+            (name == "BOAAI_SEED").then(|| "9".to_string())
+        });
+        assert_eq!(
+            (defaults.debug, defaults.splash_seconds, defaults.palette),
+            (false, DEFAULT_SPLASH_SECONDS, None)
+        );
+
+        assert_eq!(
+            ConfigFile::parse("splash_seconds = soon"),
+            Err(ConfigError::InvalidValue {
+                value: "soon".to_string(),
+                expected: "a whole number",
+            })
+        );
+        assert_eq!(
+            ConfigFile::parse("volume = 11"),
+            Err(ConfigError::UnknownKey("volume".to_string()))
+        );
+    }
+
+    #[test]
+    fn history_panel_tracks_recent_presses_through_undo_and_reset() {
+        let mut puzzle = seeded_puzzle_state(
+            3,
+            START_STATE,
+            ColorCycle::FULL,
+            PressRule::Classic,
+            true,
+            None,
+        );
+        for press in 0..RECENT_PRESSES + 2 {
+            apply_press(&mut puzzle, press % INDICATOR_COUNT);
         }
-        _ => {}
+        assert_eq!(puzzle.recent_presses.len(), RECENT_PRESSES);
+        assert_eq!(
+            puzzle.recent_presses.back(),
+            Some(&((RECENT_PRESSES + 1) % 6))
+        );
+
+        undo_press(&mut puzzle);
+        assert_eq!(puzzle.recent_presses.len(), RECENT_PRESSES - 1);
+        assert_eq!(puzzle.recent_presses.back(), Some(&(RECENT_PRESSES % 6)));
+        reset_board(&mut puzzle);
+        assert!(puzzle.recent_presses.is_empty());
+
+        assert_eq!(
+            history_entry(4, 2, ColorCycle::FULL, PressRule::Classic),
+            "#4   3  -1 +1 +2 +1 -1 +3"
+        );
+        let three = ColorCycle::parse("OFF,GREEN,BLUE").expect("valid color set");
+        assert_eq!(
+            history_entry(1, 0, three, PressRule::Classic),
+            "#1   1  -1 +1 -1  0 -1 +1"
+        );
+        assert_eq!(history_panel_width(72), None);
+        assert_eq!(history_panel_width(124), Some(HISTORY_PANEL_WIDTH));
     }
 
-    if app.puzzle.current == app.puzzle.target {
-        transition_to_email(app);
+    #[test]
+    fn moves_segment_reports_overage_within_its_cell() {
+        let mut app = test_app();
+        app.puzzle.started_at = Instant::now() - Duration::from_secs(65);
+        app.puzzle.optimal_moves = 8;
+        app.puzzle.moves_taken = 8;
+        assert_eq!(moves_segment(&app), "moves 8/8 01:05");
+        assert_eq!(moves_color(&app.puzzle, &app.theme), Color::DarkGrey);
+
+        app.puzzle.moves_taken = 14;
+        assert_eq!(moves_segment(&app), "14/8 (+6 over) 01:05");
+        assert_ne!(moves_color(&app.puzzle, &app.theme), Color::DarkGrey);
+
+        // A two-digit move cap fits the cell without being cut.
+        app.move_cap = Some(12);
+        app.puzzle.moves_taken = 2;
+        app.puzzle.started_at = Instant::now() - Duration::from_secs(12);
+        assert_eq!(moves_segment(&app), "10/12 left 00:12");
+        assert!(moves_segment(&app).len() <= HEADER_MOVES_WIDTH);
+        app.move_cap = None;
+
+        // The header still fits the narrowest frame with the wider cell.
+        let frame = frame_layout(78, 24).expect("minimum frame");
+        assert!(12 + 16 + HEADER_MOVES_WIDTH + 20 + 3 + 2 <= frame.width as usize);
+        app.puzzle.moves_taken = 1234;
+        assert_eq!(
+            center_text(&moves_segment(&app), HEADER_MOVES_WIDTH).len(),
+            HEADER_MOVES_WIDTH
+        );
     }
-}
 
-fn transition_to_email(app: &mut App) {
-    app.phase = AppPhase::Email;
-    app.email = EmailState {
-        email: String::new(),
-        focus: EmailFocus::Input,
-        selected_button: 0,
-        status: "Puzzle solved. Enter your email, then confirm invite.".to_string(),
-    };
-}
+    #[test]
+    fn matches_count_follows_presses_undo_and_reset() {
+        use NodeColor::*;
+        assert_eq!(matches_count(START_STATE, START_STATE), INDICATOR_COUNT);
+        assert_eq!(
+            matches_count(
+                [Red, Off, Green, Off, Off, Blue],
+                [Red, Off, Off, Off, White, Blue]
+            ),
+            4
+        );
+
+        let mut app = test_app();
+        let target = press_indicator(START_STATE, 2, ColorCycle::FULL, PressRule::Classic);
+        app.puzzle = puzzle_state_for(START_STATE, target, ColorCycle::FULL, PressRule::Classic)
+            .expect("valid");
+        app.puzzle.focus = PuzzleFocus::Indicator(5);
+        activate_puzzle_focus(&mut app);
+        let aligned = matches_count(app.puzzle.current, target);
+        assert_eq!(
+            app.puzzle.status,
+            format!("Pressed indicator 6. {aligned}/6 nodes aligned.")
+        );
+        app.puzzle.focus = PuzzleFocus::Action(1);
+        activate_puzzle_focus(&mut app);
+        assert!(app.puzzle.status.ends_with("0/6 nodes aligned."));
+    }
 
-fn handle_email_key(app: &mut App, key: KeyEvent) -> io::Result<bool> {
-    match app.email.focus {
-        EmailFocus::Input => match key.code {
-            KeyCode::Tab | KeyCode::Down | KeyCode::Enter => {
-                app.email.focus = EmailFocus::Buttons;
-                Ok(true)
-            }
-            KeyCode::Backspace => {
-                app.email.email.pop();
-                Ok(true)
-            }
-            KeyCode::Char(c) => {
-                if is_email_char(c) && app.email.email.len() < 120 {
-                    app.email.email.push(c);
-                    app.email.status.clear();
-                    return Ok(true);
-                }
-                Ok(false)
-            }
-            KeyCode::Esc => {
-                app.should_quit = true;
-                Ok(true)
-            }
-            _ => Ok(false),
-        },
-        EmailFocus::Buttons => match key.code {
-            KeyCode::Tab | KeyCode::Up => {
-                app.email.focus = EmailFocus::Input;
-                Ok(true)
-            }
-            KeyCode::Left | KeyCode::Right => {
-                app.email.selected_button = 1 - app.email.selected_button;
-                Ok(true)
-            }
-            KeyCode::Enter | KeyCode::Char(' ') => {
-                if app.email.selected_button == 0 {
-                    if !is_valid_email(&app.email.email) {
-                        app.email.status =
-                            "Please enter a valid email before confirming.".to_string();
-                        return Ok(true);
-                    }
+    #[test]
+    fn solve_rings_bell_once_and_flash_ends_on_tick() {
+        let mut app = test_app();
+        app.reduced_motion = false;
+        app.bell = true;
+        enter_solved_hold(&mut app);
+        assert!(app.bell_pending);
+        let until = app.flash_until.expect("flash started");
+        assert!(until > Instant::now());
+
+        let mut frame = Vec::new();
+        draw_frame(&mut frame, &app, 120, 40).unwrap();
+        let frame = String::from_utf8(frame).unwrap();
+        let reverse_on = frame.find("\x1b[7m").expect("inverted body");
+        assert!(frame[reverse_on..].contains("\x1b[27m"));
+
+        app.flash_until = Some(Instant::now() - Duration::from_millis(1));
+        assert!(tick_app(&mut app));
+        assert_eq!(app.flash_until, None);
+
+        let mut quiet = test_app();
+        quiet.bell = false;
+        enter_solved_hold(&mut quiet);
+        assert!(!quiet.bell_pending);
+    }
 
-                    store_submission(&app.email.email)?;
-                    app.submitted_email = Some(app.email.email.clone());
-                    app.phase = AppPhase::Submitted;
-                    return Ok(true);
-                }
+    #[test]
+    fn difficulty_menu_scrambles_from_the_target_by_depth() {
+        // A three-color ring keeps the solver quick across many seeds.
+        let cycle = ColorCycle::parse("OFF,GREEN,BLUE").expect("valid color set");
+        let total_depth = |difficulty| {
+            (0..20)
+                .map(|seed| {
+                    let puzzle = difficulty_puzzle_state(
+                        seed,
+                        START_STATE,
+                        cycle,
+                        PressRule::Classic,
+                        None,
+                        difficulty,
+                    );
+                    assert_ne!(puzzle.initial, puzzle.target);
+                    puzzle.optimal_moves
+                })
+                .sum::<usize>()
+        };
+        assert!(total_depth(Difficulty::Easy) < total_depth(Difficulty::Hard));
+
+        let mut app = test_app();
+        app.phase = AppPhase::Menu;
+        app.difficulty_selected = 1;
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        handle_key(&mut app, key(KeyCode::Right)).unwrap();
+        handle_key(&mut app, key(KeyCode::Right)).unwrap();
+        assert_eq!(app.difficulty_selected, 2);
+        let seed = app.puzzle.seed;
+        handle_key(&mut app, key(KeyCode::Enter)).unwrap();
+        assert!(matches!(app.phase, AppPhase::Puzzle));
+        assert_eq!(app.difficulty, Some(Difficulty::Hard));
+        assert_eq!(app.puzzle.seed, seed);
+        assert_eq!(app.prefs.difficulty, Difficulty::Hard);
+
+        assert_eq!(
+            parse_args(&["--difficulty".to_string(), "EASY".to_string()])
+                .unwrap()
+                .difficulty,
+            Some(Difficulty::Easy)
+        );
+        assert!(parse_args(&["--difficulty".to_string(), "brutal".to_string()]).is_err());
+    }
 
-                app.puzzle = new_puzzle_state();
-                app.phase = AppPhase::Puzzle;
-                Ok(true)
-            }
-            KeyCode::Esc => {
-                app.should_quit = true;
-                Ok(true)
-            }
-            _ => Ok(false),
-        },
+    #[test]
+    fn paste_fills_the_email_field_through_the_typing_filter() {
+        let mut app = test_app();
+        assert!(!handle_paste(&mut app, "player@example.com"));
+
+        transition_to_email(&mut app);
+        app.email.focus = EmailFocus::Buttons;
+        assert!(handle_paste(&mut app, " player@exa mple.com\n"));
+        assert_eq!(app.email.email, "player@example.com");
+        assert!(matches!(app.email.focus, EmailFocus::Input));
+
+        assert!(handle_paste(&mut app, &"x".repeat(500)));
+        assert_eq!(app.email.email.len(), EMAIL_MAX_LEN);
+        assert!(!handle_paste(&mut app, "more"));
     }
-}
 
-fn handle_submitted_key(app: &mut App, key: KeyEvent) -> bool {
-    match key.code {
-        KeyCode::Esc | KeyCode::Enter => {
-            app.should_quit = true;
-            true
+    #[test]
+    fn log_lines_append_with_a_timestamp() {
+        let path = env::temp_dir().join(format!("boaai_log_test_{}.log", process::id()));
+        append_log_line(&path, "start seed=1");
+        append_log_line(&path, "phase Puzzle -> Solved");
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let events: Vec<&str> = text
+            .lines()
+            .map(|line| {
+                let (stamp, event) = line.split_once('\t').expect("tab-separated");
+                let (secs, millis) = stamp.split_once('.').expect("secs.millis");
+                assert!(secs.parse::<u64>().is_ok() && millis.len() == 3);
+                event
+            })
+            .collect();
+        assert_eq!(events, ["start seed=1", "phase Puzzle -> Solved"]);
+    }
+
+    #[test]
+    fn dry_run_submission_records_nothing() {
+        let path = env::temp_dir().join(format!("boaai-dry-run-{}.csv", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut app = test_app();
+        app.dry_run = true;
+        app.invite_file = Some(path.clone());
+        transition_to_email(&mut app);
+        app.email.email = "player@example.com".to_string();
+
+        assert!(submit_email(&mut app));
+        assert!(matches!(app.phase, AppPhase::Submitted));
+        assert_eq!(app.submitted_email.as_deref(), Some("player@example.com"));
+        assert!(app.registered_emails.is_none());
+        assert!(!path.exists());
+
+        let cli = parse_args(&["--dry-run".to_string()]).unwrap();
+        assert!(cli.dry_run);
+        assert!(
+            Config::resolve(&ConfigFile::default(), |_| None)
+                .with_args(&cli)
+                .dry_run
+        );
+    }
+
+    #[test]
+    fn ring_connectors_sit_between_neighbors_and_o_toggles_the_layout() {
+        let ring = indicator_grid(2, 116, 12, 34, IndicatorLayout::Ring);
+        let connectors = ring.connectors();
+        assert_eq!(connectors.len(), INDICATOR_COUNT);
+        for &(x, y, piece) in &connectors {
+            for column in x..x + display_width(piece) as u16 {
+                assert_eq!(ring.hit(column, y), None, "{piece} at ({column}, {y})");
+            }
         }
-        _ => false,
+        assert!(indicator_grid(2, 116, 12, 22, IndicatorLayout::Ring)
+            .connectors()
+            .is_empty());
+        assert!(indicator_grid(2, 20, 12, 34, IndicatorLayout::Ring)
+            .connectors()
+            .is_empty());
+
+        let mut app = test_app();
+        app.layout = IndicatorLayout::Row;
+        let key = KeyEvent::new(KeyCode::Char('o'), KeyModifiers::NONE);
+        assert!(handle_puzzle_key(&mut app, key));
+        assert_eq!(app.layout, IndicatorLayout::Ring);
+        assert!(handle_puzzle_key(&mut app, key));
+        assert_eq!(app.layout, IndicatorLayout::Row);
+        assert_eq!(app.puzzle.status, "Row layout.");
     }
-}
 
-fn new_puzzle_state() -> PuzzleState {
-    let initial = START_STATE;
-    let mut rng = rand::thread_rng();
-    let (target, _generated_sequence) = generate_random_target_from_start(&mut rng);
-    let optimal_moves = shortest_solution(initial, target)
-        .map(|path| path.len())
-        .unwrap_or(0);
-    PuzzleState {
-        initial,
-        target,
-        current: initial,
-        optimal_moves,
-        moves_taken: 0,
-        focus: PuzzleFocus::Indicator(0),
-        show_rules: false,
-        status: "Good luck".to_string(),
+    #[test]
+    fn digit_keys_press_their_indicator_only_in_the_puzzle() {
+        let mut app = test_app();
+        let key = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+
+        assert!(handle_key(&mut app, key('5')).unwrap());
+        assert!(matches!(app.puzzle.focus, PuzzleFocus::Indicator(4)));
+        assert_eq!(app.puzzle.moves, vec![4]);
+        assert_eq!(app.puzzle.moves_taken, 1);
+        assert!(app.puzzle.status.starts_with("Pressed indicator 5."));
+
+        assert!(!handle_key(&mut app, key('7')).unwrap());
+        assert!(!handle_key(&mut app, key('0')).unwrap());
+        assert_eq!(app.puzzle.moves_taken, 1);
+
+        transition_to_email(&mut app);
+        assert!(handle_key(&mut app, key('1')).unwrap());
+        assert_eq!(app.email.email, "1");
+        assert_eq!(app.puzzle.moves_taken, 1);
     }
-}
 
-fn press_indicator(
-    mut state: [NodeColor; INDICATOR_COUNT],
-    index: usize,
-) -> [NodeColor; INDICATOR_COUNT] {
-    for target in 0..INDICATOR_COUNT {
-        let clockwise = (target + INDICATOR_COUNT - index) % INDICATOR_COUNT;
-        let counterclockwise = (index + INDICATOR_COUNT - target) % INDICATOR_COUNT;
-        let distance = clockwise.min(counterclockwise);
+    #[test]
+    fn submitted_view_names_the_resolved_submission_file() {
+        let dir = env::temp_dir().join(format!("boaai-destination-{}", process::id()));
+        let path = dir.join("invites.csv");
+        let mut registered = None;
+        let mut app = test_app();
+        app.email.email = "a@example.com".to_string();
+        assert_eq!(
+            store_submission(&SubmissionRecord::now(&app), &mut registered, Some(&path)).unwrap(),
+            SubmitOutcome::Stored(Some(path.clone()))
+        );
+        let _ = fs::remove_dir_all(&dir);
+
+        app.last_submission_path = Some(path.clone());
+        assert_eq!(submission_destination(&app), path.display().to_string());
+        app.dry_run = true;
+        assert_eq!(submission_destination(&app), "(not recorded)");
+    }
 
-        let delta = match distance {
-            0 => 2, // pressed button
-            1 => 1, // immediate neighbors
-            2 => 5, // one step backward in color cycle
-            3 => 3, // opposite button
-            _ => 0,
-        };
+    #[test]
+    fn unwritable_submission_file_keeps_the_email_form_open() {
+        // A regular file where the parent directory should be fails even
+        // for root, unlike a permission bit.
+        let blocker = env::temp_dir().join(format!("boaai-blocker-{}", process::id()));
+        fs::write(&blocker, "").unwrap();
+        let mut app = test_app();
+        app.invite_file = Some(blocker.join("invites.csv"));
+        transition_to_email(&mut app);
+        app.email.email = "player@example.com".to_string();
+
+        assert!(submit_email(&mut app));
+        let _ = fs::remove_file(&blocker);
+        assert!(matches!(app.phase, AppPhase::Email));
+        assert!(!app.should_quit);
+        assert!(app
+            .email
+            .status
+            .starts_with("Could not save submission (check file permissions): "));
+    }
 
-        for _ in 0..delta {
-            state[target] = state[target].next();
+    #[test]
+    fn submission_stats_count_the_utc_day_and_find_the_latest() {
+        let dir = env::temp_dir().join(format!("boaai-stats-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("invites.csv");
+        assert_eq!(
+            load_submissions(&path, InviteFormat::Csv).unwrap(),
+            Vec::new()
+        );
+
+        let mut sink = FileSink {
+            path: path.clone(),
+            format: InviteFormat::Csv,
+        };
+        // 2026-10-15 23:59:59, then 2026-10-16 00:00:00 and 12:30 UTC.
+        let record = |submitted_unix, email: &str| SubmissionRecord {
+            submitted_unix,
+            email: email.to_string(),
+            score: None,
+            solve_seconds: Some(30),
+            session_id: None,
+            moves: None,
+        };
+        for entry in [
+            record(1_792_108_799, "late@example.com"),
+            record(1_792_153_800, "noon@example.com"),
+            record(1_792_108_800, "midnight@example.com"),
+        ] {
+            sink.submit(&entry.encode(InviteFormat::Csv)).unwrap();
         }
+        let records = load_submissions(&path, InviteFormat::Csv).unwrap();
+        assert_eq!(records.len(), 3);
+
+        let stats = submission_stats(&records, 1_792_170_000);
+        assert_eq!(stats.total, 3);
+        assert_eq!(stats.today, 2);
+        assert_eq!(
+            stats.latest,
+            Some(record(1_792_153_800, "noon@example.com"))
+        );
+        assert_eq!(format_utc(1_792_153_800), "2026-10-16 12:30 UTC");
+        assert_eq!(format_utc(1_792_108_799), "2026-10-15 23:59 UTC");
+        assert_eq!(
+            submission_stats(&[], 1_792_170_000),
+            SubmissionStats::default()
+        );
+        fs::remove_dir_all(&dir).unwrap();
     }
 
-    state
-}
+    #[test]
+    fn plain_buttons_mark_the_selection_with_brackets() {
+        assert_eq!(button_text("Go", 8, false), "   Go   ");
+        assert_eq!(button_text("Go", 8, true), "[  Go  ]");
+        assert_eq!(button_text("Confirm Invite", 8, true), "[Confir]");
+        assert_eq!(display_width(&button_text("1 red", 12, true)), 12);
+    }
 
-fn generate_random_target_from_start<R: Rng + ?Sized>(
-    rng: &mut R,
-) -> ([NodeColor; INDICATOR_COUNT], Vec<usize>) {
-    loop {
-        let sequence: Vec<usize> = (0..6).map(|_| rng.gen_range(0..INDICATOR_COUNT)).collect();
-        let mut state = START_STATE;
-        for &press in &sequence {
-            state = press_indicator(state, press);
-        }
+    #[test]
+    fn emitted_result_reports_the_solve_and_submission() {
+        let mut app = test_app();
+        app.puzzle.moves_taken = 4;
+        assert_eq!(
+            result_json(&app),
+            r#"{"solved":false,"moves":4,"solve_seconds":null,"email":null}"#
+        );
+
+        app.solve_secs = Some(42);
+        app.submitted_email = Some("player@example.com".to_string());
+        assert_eq!(
+            result_json(&app),
+            r#"{"solved":true,"moves":4,"solve_seconds":42,"email":"player@example.com"}"#
+        );
+        assert!(
+            parse_args(&["--emit-result".to_string()])
+                .unwrap()
+                .emit_result
+        );
+    }
+
+    #[test]
+    fn solve_mode_prints_shortest_presses_between_specs() {
+        let cycle = ColorCycle::FULL;
+        let target = press_indicator(
+            press_indicator(START_STATE, 0, cycle, PressRule::Classic),
+            3,
+            cycle,
+            PressRule::Classic,
+        );
+        let start_spec = render_state_spec(START_STATE);
+        let target_spec = render_state_spec(target);
+        let moves = solve_specs(&start_spec, &target_spec, cycle, PressRule::Classic)
+            .unwrap()
+            .unwrap();
+        assert_eq!(moves.len(), 2);
+        assert!(render_solution(&moves).ends_with("\nlength: 2"));
+        assert_eq!(render_solution(&[]), "moves: \nlength: 0");
+
+        let subset = ColorCycle::parse("OFF,GREEN").unwrap();
+        assert_eq!(
+            solve_specs(
+                &start_spec,
+                "RED,OFF,OFF,OFF,OFF,OFF",
+                subset,
+                PressRule::Classic
+            ),
+            Err(ConfigError::ColorOutsideCycle(NodeColor::Red))
+        );
+        assert_eq!(
+            parse_args(&["--solve".to_string(), "a".to_string(), "b".to_string()])
+                .unwrap()
+                .solve,
+            Some(("a".to_string(), "b".to_string()))
+        );
+    }
 
-        if state != START_STATE {
-            return (state, sequence);
+    #[test]
+    fn rules_pane_scrolls_on_show_rules_and_resets_when_collapsed() {
+        let mut app = test_app();
+        let page_down = KeyEvent::new(KeyCode::PageDown, KeyModifiers::NONE);
+        let page_up = KeyEvent::new(KeyCode::PageUp, KeyModifiers::NONE);
+        let enter = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+
+        app.puzzle.focus = PuzzleFocus::Action(2);
+        assert!(!handle_puzzle_key(&mut app, page_down));
+        assert!(handle_puzzle_key(&mut app, enter));
+        assert!(app.puzzle.show_rules);
+
+        let lines = rules_lines(&app.puzzle).len();
+        for _ in 0..lines + 3 {
+            handle_puzzle_key(&mut app, page_down);
         }
+        assert_eq!(app.puzzle.rules_scroll, lines - 1);
+        handle_puzzle_key(&mut app, page_up);
+        assert_eq!(app.puzzle.rules_scroll, lines - 2);
+
+        // A short body still keeps every line inside the frame.
+        let mut buffer = Vec::new();
+        draw_frame(&mut buffer, &app, 80, 24).unwrap();
+
+        handle_puzzle_key(&mut app, enter);
+        assert!(!app.puzzle.show_rules);
+        assert_eq!(app.puzzle.rules_scroll, 0);
     }
-}
 
-fn shortest_solution(
-    start: [NodeColor; INDICATOR_COUNT],
-    goal: [NodeColor; INDICATOR_COUNT],
-) -> Option<Vec<usize>> {
-    if start == goal {
-        return Some(Vec::new());
-    }
-
-    let mut queue = VecDeque::new();
-    let mut visited = HashSet::new();
-    let mut parent_map: HashMap<
-        [NodeColor; INDICATOR_COUNT],
-        ([NodeColor; INDICATOR_COUNT], usize),
-    > = HashMap::new();
-
-    queue.push_back(start);
-    visited.insert(start);
-
-    while let Some(state) = queue.pop_front() {
-        for index in 0..INDICATOR_COUNT {
-            let next_state = press_indicator(state, index);
-            if visited.insert(next_state) {
-                parent_map.insert(next_state, (state, index));
-                if next_state == goal {
-                    return Some(reconstruct_moves(start, goal, &parent_map));
-                }
-                queue.push_back(next_state);
-            }
+    #[test]
+    fn pinned_full_start_spec_sets_the_initial_board_and_its_distance() {
+        use NodeColor::*;
+        let start = parse_start_spec("GREEN,OFF,OFF,RED,OFF,OFF").unwrap();
+        assert_eq!(start, [Green, Off, Off, Red, Off, Off]);
+        assert_eq!(
+            parse_start_spec("1=GREEN,4=RED").unwrap(),
+            start,
+            "sparse overrides still parse"
+        );
+        assert_eq!(
+            parse_start_spec("GREEN,OFF,RED"),
+            Err(ConfigError::LengthMismatch {
+                expected: INDICATOR_COUNT,
+                found: 3
+            })
+        );
+
+        let cycle = ColorCycle::FULL;
+        for seed in 0..5 {
+            let puzzle = seeded_puzzle_state(seed, start, cycle, PressRule::Classic, false, None);
+            assert_eq!(puzzle.initial, start);
+            let bfs = shortest_solution(start, puzzle.target, cycle, PressRule::Classic)
+                .expect("generated targets are reachable");
+            assert_eq!(puzzle.optimal_moves, bfs.len());
         }
     }
 
-    None
-}
+    #[test]
+    fn debug_f11_copies_the_solution_through_osc52() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"1,4,6"), "MSw0LDY=");
+        assert_eq!(osc52_copy("1,4,6"), "\x1b]52;c;MSw0LDY=\x07");
+
+        let f11 = KeyEvent::new(KeyCode::F(11), KeyModifiers::NONE);
+        let mut app = test_app();
+        assert!(!handle_puzzle_key(&mut app, f11));
+        assert_eq!(app.clipboard_pending, None);
+
+        let mut app = App::with_config(
+            &Config {
+                debug: true,
+                ..Config::default()
+            },
+            START_STATE,
+            ColorCycle::FULL,
+        );
+        assert!(handle_puzzle_key(&mut app, f11));
+        let path = shortest_solution(
+            app.puzzle.current,
+            app.puzzle.target,
+            app.puzzle.cycle,
+            app.puzzle.rule,
+        )
+        .unwrap();
+        let copied = app.clipboard_pending.clone().unwrap();
+        assert_eq!(copied.split(',').count(), path.len());
+        assert_eq!(
+            app.puzzle.status,
+            format!("Solution copied ({} moves).", path.len())
+        );
+    }
 
-fn reconstruct_moves(
-    start: [NodeColor; INDICATOR_COUNT],
-    goal: [NodeColor; INDICATOR_COUNT],
-    parent_map: &HashMap<[NodeColor; INDICATOR_COUNT], ([NodeColor; INDICATOR_COUNT], usize)>,
-) -> Vec<usize> {
-    let mut cursor = goal;
-    let mut path = Vec::new();
-
-    while cursor != start {
-        if let Some((previous, pressed)) = parent_map.get(&cursor) {
-            path.push(*pressed);
-            cursor = *previous;
-        } else {
-            return Vec::new();
-        }
+    #[test]
+    fn pending_resize_shortens_the_poll_until_it_settles() {
+        let mut app = test_app();
+        app.phase = AppPhase::Menu;
+        let now = Instant::now();
+        assert_eq!(next_wake(&app, None, now), IDLE_POLL);
+        assert_eq!(next_wake(&app, Some(now), now), RESIZE_SETTLE);
+        assert_eq!(
+            next_wake(&app, Some(now), now + Duration::from_millis(40)),
+            Duration::from_millis(60)
+        );
+        assert_eq!(
+            next_wake(&app, Some(now), now + RESIZE_SETTLE),
+            Duration::ZERO
+        );
     }
 
-    path.reverse();
-    path
-}
+    #[test]
+    fn poll_sleeps_long_when_idle_and_wakes_for_pending_work() {
+        let mut app = test_app();
+        app.phase = AppPhase::Menu;
+        app.target_preview = TargetPreview::Done;
+        let now = Instant::now();
+        assert_eq!(next_wake(&app, None, now), IDLE_POLL);
+
+        app.flash_until = Some(now + SOLVE_FLASH);
+        assert_eq!(next_wake(&app, None, now), SOLVE_FLASH);
+        app.flash_until = None;
+
+        app.target_preview = TargetPreview::Running(now);
+        assert_eq!(next_wake(&app, None, now), TARGET_PREVIEW_FRAME);
+        app.target_preview = TargetPreview::Done;
+
+        // The puzzle header clock ticks on whole seconds.
+        app.phase = AppPhase::Puzzle;
+        app.puzzle.started_at = now - Duration::from_millis(1300);
+        assert_eq!(next_wake(&app, None, now), Duration::from_millis(700));
+        app.puzzle.solved_in = Some(Duration::from_secs(1));
+        assert_eq!(next_wake(&app, None, now), IDLE_POLL);
+
+        app.session_limit = Some(SessionLimit {
+            deadline: now + SESSION_WARNING + Duration::from_secs(2),
+            on_unsubmitted: UnsubmittedPolicy::Prompt,
+            grace_until: None,
+        });
+        assert_eq!(next_wake(&app, None, now), Duration::from_secs(2));
+        app.session_limit.as_mut().unwrap().deadline = now + Duration::from_millis(4250);
+        assert_eq!(next_wake(&app, None, now), Duration::from_millis(250));
+        app.session_limit = None;
+
+        // Deadlines that already passed do not make the poll return at once.
+        app.phase = AppPhase::Menu;
+        app.flash_until = Some(now - Duration::from_millis(5));
+        assert_eq!(next_wake(&app, None, now), IDLE_POLL);
+    }
+
+    #[test]
+    fn solved_hold_completes_inside_the_session_warning_window() {
+        let mut app = test_app();
+        let now = Instant::now();
+        app.session_limit = Some(SessionLimit {
+            deadline: now + SESSION_WARNING / 2,
+            on_unsubmitted: UnsubmittedPolicy::Prompt,
+            grace_until: None,
+        });
+        app.phase = AppPhase::Solved;
+        app.solved_at = Some(now - SOLVED_HOLD);
+        app.flash_until = Some(now - Duration::from_millis(1));
+        assert!(tick_app(&mut app));
+        assert!(matches!(app.phase, AppPhase::Email));
+        assert_eq!(app.flash_until, None);
+        assert!(!app.should_quit);
+    }
+
+    #[test]
+    fn indicator_locks_are_annotations_cleared_on_reset() {
+        let mut app = test_app();
+        let before = (
+            app.puzzle.current,
+            app.puzzle.moves_taken,
+            app.puzzle.optimal_moves,
+        );
+        app.puzzle.focus = PuzzleFocus::Indicator(2);
+        assert!(handle_puzzle_key(
+            &mut app,
+            KeyEvent::new(KeyCode::Enter, KeyModifiers::SHIFT)
+        ));
+        assert_eq!(app.puzzle.locked, [false, false, true, false, false, false]);
+        assert_eq!(
+            (
+                app.puzzle.current,
+                app.puzzle.moves_taken,
+                app.puzzle.optimal_moves
+            ),
+            before
+        );
+
+        let mut buffer = Vec::new();
+        draw_frame(&mut buffer, &app, 120, 40).unwrap();
+        assert!(String::from_utf8_lossy(&buffer).contains('╔'));
+
+        app.puzzle.focus = PuzzleFocus::Action(0);
+        assert!(!handle_puzzle_key(
+            &mut app,
+            KeyEvent::new(KeyCode::Enter, KeyModifiers::ALT)
+        ));
+        reset_board(&mut app.puzzle);
+        assert_eq!(app.puzzle.locked, [false; INDICATOR_COUNT]);
+    }
 
-fn store_submission(email: &str) -> io::Result<()> {
-    let output_path =
-        env::var("BOAAI_INVITE_FILE").unwrap_or_else(|_| "invite_submissions.csv".to_string());
-    let output = Path::new(&output_path);
+    #[test]
+    fn convert_round_trips_invite_files_and_counts_bad_lines() {
+        let dir = env::temp_dir().join(format!("boaai-convert-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let csv = dir.join("invites.csv");
+        fs::write(
+            &csv,
+            "submitted_unix,email,score,solve_seconds\n\
+             1792108800,first@example.com,875,42\n\
+             not a record\n\
+             1792153800,second@example.com,,\n",
+        )
+        .unwrap();
+
+        let jsonl = dir.join("invites.jsonl");
+        assert_eq!(
+            convert_submissions(&csv, &jsonl).unwrap(),
+            Conversion {
+                records: 2,
+                skipped: 1
+            }
+        );
+        let back = dir.join("back.csv");
+        assert_eq!(
+            convert_submissions(&jsonl, &back).unwrap(),
+            Conversion {
+                records: 2,
+                skipped: 0
+            }
+        );
+        assert_eq!(
+            load_submissions(&back, InviteFormat::Csv).unwrap(),
+            load_submissions(&csv, InviteFormat::Csv).unwrap()
+        );
+
+        assert_eq!(
+            convert_submissions(&csv, &jsonl).unwrap_err().kind(),
+            io::ErrorKind::AlreadyExists
+        );
+        assert_eq!(
+            convert_submissions(&csv, &dir.join("invites.txt"))
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::InvalidInput
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
 
-    if let Some(parent) = output.parent() {
-        if !parent.as_os_str().is_empty() {
-            fs::create_dir_all(parent)?;
+    #[test]
+    fn email_feedback_follows_typing_and_backspace() {
+        let mut app = test_app();
+        app.phase = AppPhase::Email;
+        assert_eq!(email_feedback(&app.email.email, &app.theme), None);
+
+        for c in "player@example.co".chars() {
+            handle_email_key(
+                &mut app,
+                KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE),
+            )
+            .unwrap();
+        }
+        assert_eq!(
+            email_feedback(&app.email.email, &app.theme),
+            Some(("✓ looks valid", app.theme.success))
+        );
+        for _ in 0..3 {
+            handle_email_key(
+                &mut app,
+                KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE),
+            )
+            .unwrap();
         }
+        assert_eq!(
+            email_feedback(&app.email.email, &app.theme),
+            Some(("… incomplete", Color::DarkGrey))
+        );
+        assert!(app.email.status.is_empty());
     }
 
-    let file_exists = output.exists();
-    let mut file = OpenOptions::new().create(true).append(true).open(output)?;
+    #[test]
+    fn sandbox_solves_roll_a_new_puzzle_without_the_email_form() {
+        let mut app = test_app();
+        app.sandbox = Some(0);
+        for round in 1..=2 {
+            let seed = app.puzzle.seed;
+            debug_solve(&mut app.puzzle);
+            let moves = app.puzzle.moves_taken;
+            enter_solved_hold(&mut app);
+            complete_puzzle(&mut app);
+            assert!(matches!(app.phase, AppPhase::Puzzle));
+            assert_eq!(app.sandbox, Some(round));
+            assert_ne!(app.puzzle.seed, seed);
+            assert_eq!(app.puzzle.moves_taken, 0);
+            assert!(app
+                .puzzle
+                .status
+                .starts_with(&format!("Solved in {moves} move")));
+        }
+        assert_eq!(app.submitted_email, None);
+        assert!(parse_args(&["--sandbox".to_string()]).unwrap().sandbox);
+    }
 
-    if !file_exists {
-        writeln!(file, "submitted_unix,email")?;
+    #[test]
+    fn target_overlay_shows_the_shorter_way_round_and_hides_matches() {
+        use NodeColor::*;
+        let cycle = ColorCycle::FULL;
+        let target = [Off, Green, Blue, Red, Purple, White];
+        let mut puzzle =
+            puzzle_state_for(START_STATE, target, cycle, PressRule::Classic).expect("reachable");
+        let overlays: Vec<Option<String>> = (0..INDICATOR_COUNT)
+            .map(|index| target_overlay(&puzzle, index))
+            .collect();
+        assert_eq!(
+            overlays,
+            [
+                None,
+                Some(" ↑1 ".to_string()),
+                Some(" ↑2 ".to_string()),
+                Some(" ↑3 ".to_string()),
+                Some(" ↓2 ".to_string()),
+                Some(" ↓1 ".to_string()),
+            ]
+        );
+
+        apply_press(&mut puzzle, 0);
+        let after: Vec<Option<String>> = (0..INDICATOR_COUNT)
+            .map(|index| target_overlay(&puzzle, index))
+            .collect();
+        assert_ne!(after, overlays);
+        reset_board(&mut puzzle);
+        assert_eq!(target_overlay(&puzzle, 1), Some(" ↑1 ".to_string()));
     }
 
-    let submitted_unix = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs();
-    writeln!(file, "{submitted_unix},{email}")?;
+    #[test]
+    fn keys_drive_a_full_solve_and_submission_end_to_end() {
+        let dir = env::temp_dir().join(format!("boaai-e2e-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let invites = dir.join("invites.csv");
+        let mut app = test_app();
+        app.invite_file = Some(invites.clone());
+        app.leaderboard_file = dir.join("leaderboard.csv");
+        app.reduced_motion = true;
+
+        let path = shortest_solution(
+            app.puzzle.current,
+            app.puzzle.target,
+            app.puzzle.cycle,
+            app.puzzle.rule,
+        )
+        .expect("generated puzzles are solvable");
+        let mut frames = Vec::new();
+        drive_keys(
+            &mut app,
+            path.iter().map(|&index| digit_key(index)),
+            &mut frames,
+            120,
+            40,
+        )
+        .unwrap();
+        assert!(matches!(app.phase, AppPhase::Email));
+
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        let typing = "player@example.com".chars().map(|c| key(KeyCode::Char(c)));
+        let confirm = [KeyCode::Tab, KeyCode::Enter, KeyCode::Enter].map(key);
+        drive_keys(&mut app, typing.chain(confirm), &mut frames, 120, 40).unwrap();
+        assert!(matches!(app.phase, AppPhase::Submitted));
+        assert!(String::from_utf8_lossy(&frames).contains("EVENT INVITE REQUEST"));
+
+        let records = load_submissions(&invites, InviteFormat::Csv).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].email, "player@example.com");
+        assert_eq!(records[0].session_id.as_ref(), Some(&app.session_id));
+        assert_eq!(records[0].moves, Some(path));
+        assert_eq!(app.session_id.len(), 8);
+        fs::remove_dir_all(&dir).unwrap();
+    }
 
-    Ok(())
-}
+    #[test]
+    fn accent_parses_rgb_triples_and_falls_back_when_malformed() {
+        assert_eq!(
+            parse_accent(" 10, 20 ,30"),
+            Some(Color::Rgb {
+                r: 10,
+                g: 20,
+                b: 30
+            })
+        );
+        for bad in ["256,0,0", "1,2", "a,b,c", "1,2,3,4"] {
+            assert_eq!(parse_accent(bad), None, "{bad}");
+        }
 
-fn render_state(state: [NodeColor; INDICATOR_COUNT]) -> String {
-    state
-        .iter()
-        .map(|color| color.as_str())
-        .collect::<Vec<_>>()
-        .join(" | ")
-}
+        let resolve = |value: &'static str| {
+            Config::resolve(&ConfigFile::default(), move |name| {
+                (name == "BOAAI_ACCENT").then(|| value.to_string())
+            })
+            .accent
+        };
+        assert_eq!(
+            resolve("0,128,255"),
+            Color::Rgb {
+                r: 0,
+                g: 128,
+                b: 255
+            }
+        );
+        assert_eq!(resolve("orange"), DEFAULT_ACCENT);
+        let theme = Theme::new(resolve("orange"));
+        assert_eq!(theme.border, Color::DarkGrey);
+        assert_eq!(
+            (theme.success, theme.warning, theme.background),
+            (Color::Green, Color::Yellow, Color::Black)
+        );
+    }
 
-fn trim_to_width(text: &str, width: usize) -> String {
-    text.chars().take(width).collect()
-}
+    #[test]
+    fn screen_falls_back_to_the_primary_buffer() {
+        /// Rejects the alternate screen the way a constrained terminal would.
+        struct NoAlternate(Vec<u8>);
+        impl Write for NoAlternate {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                if buf.windows(8).any(|w| w == b"\x1b[?1049h") {
+                    return Err(io::Error::new(io::ErrorKind::Unsupported, "no alt screen"));
+                }
+                self.0.write(buf)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
 
-fn center_text(text: &str, width: usize) -> String {
-    let clean = trim_to_width(text, width);
-    let clean_len = clean.chars().count();
-    if clean_len >= width {
-        return clean;
+        let mut out = Vec::new();
+        assert!(enter_screen(&mut out, true));
+        assert_eq!(out, b"\x1b[?1049h");
+
+        for alternate in [true, false] {
+            let mut out = NoAlternate(Vec::new());
+            assert!(!enter_screen(&mut out, alternate));
+            let written = String::from_utf8(out.0).unwrap();
+            assert!(written.contains("\x1b[2J"), "{written:?}");
+            assert!(!written.contains("1049"), "{written:?}");
+        }
     }
-    let left = (width - clean_len) / 2;
-    let right = width - clean_len - left;
-    format!("{}{}{}", " ".repeat(left), clean, " ".repeat(right))
-}
 
-fn is_valid_email(value: &str) -> bool {
-    if value.contains(' ') || value.len() < 5 {
-        return false;
+    #[test]
+    fn help_overlay_captures_keys_and_scrolls_on_small_terminals() {
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        let mut app = test_app();
+        app.phase = AppPhase::Puzzle;
+        let current = app.puzzle.current;
+
+        assert!(handle_key(&mut app, key(KeyCode::Char('?'))).unwrap());
+        assert_eq!(app.help, Some(0));
+        // Keys meant for the puzzle are swallowed, and Esc only closes.
+        assert!(!handle_key(&mut app, key(KeyCode::Char('1'))).unwrap());
+        assert_eq!(app.puzzle.current, current);
+        for _ in 0..50 {
+            handle_key(&mut app, key(KeyCode::Down)).unwrap();
+        }
+        assert_eq!(app.help, Some(help_lines(AppPhase::Puzzle).len() - 1));
+
+        let mut frame = Vec::new();
+        draw_frame(&mut frame, &app, 80, 24).unwrap();
+        let frame = String::from_utf8_lossy(&frame);
+        assert!(frame.contains("KEYS"));
+        assert!(frame.contains("of 21"), "the overlay should scroll");
+        assert!(frame.contains("Esc"));
+
+        assert!(handle_key(&mut app, key(KeyCode::Esc)).unwrap());
+        assert_eq!(app.help, None);
+        assert!(!app.should_quit);
+
+        // Each phase lists its own keys.
+        app.phase = AppPhase::Email;
+        assert!(handle_key(&mut app, key(KeyCode::Char('?'))).unwrap());
+        assert!(app.email.email.is_empty());
+        assert!(help_lines(app.phase).iter().any(|(keys, _)| *keys == "F2"));
+        assert!(handle_key(&mut app, key(KeyCode::Char('?'))).unwrap());
+        assert_eq!(app.help, None);
+        assert!(help_lines(AppPhase::Submitted)
+            .iter()
+            .any(|(keys, _)| *keys == "L"));
     }
 
-    let mut parts = value.split('@');
-    let local = parts.next().unwrap_or_default();
-    let domain = parts.next().unwrap_or_default();
+    #[test]
+    fn wide_terminals_move_history_and_rules_into_side_columns() {
+        let stacked = frame_layout(WIDE_FRAME_WIDTH + 3, 40).unwrap();
+        assert_eq!(stacked.width, FRAME_MAX_WIDTH);
+        assert_eq!(view_columns(stacked.x, stacked.width).sides, None);
+
+        let frame = frame_layout(WIDE_FRAME_WIDTH + 4, 40).unwrap();
+        let view = view_columns(frame.x, frame.width);
+        let sides = view.sides.expect("wide enough for side columns");
+        assert_eq!(view.width, FRAME_MAX_WIDTH);
+        assert!(sides.left_x + sides.width <= view.x);
+        assert!(view.x + view.width <= sides.right_x);
+        assert!(sides.right_x + sides.width < frame.x + frame.width);
+
+        let mut app = test_app();
+        app.phase = AppPhase::Puzzle;
+        app.layout = IndicatorLayout::Row;
+        app.puzzle.show_rules = true;
+        let cols = WIDE_FRAME_WIDTH + 4;
+        let mut frame_bytes = Vec::new();
+        draw_frame(&mut frame_bytes, &app, cols, 40).unwrap();
+        let text = String::from_utf8_lossy(&frame_bytes);
+        assert!(text.contains(" History ") && text.contains(" Rules "));
+
+        // Clicks follow the middle column.
+        let bottom = frame.body_y + frame.body_height - 1;
+        let grid = indicator_grid(
+            view.x,
+            view.width,
+            indicator_row(frame.body_y),
+            bottom,
+            app.layout,
+        );
+        let (cell_x, cell_y) = grid.cells[2];
+        assert_eq!(
+            hit_test(&app, cols, 40, cell_x, cell_y + 1),
+            Some(ClickTarget::Indicator(2))
+        );
+
+        assert_eq!(
+            wrap_words("Adjacent buttons advance by one", 12),
+            ["Adjacent", "buttons", "advance by", "one"]
+        );
+    }
 
-    if parts.next().is_some() {
-        return false;
+    #[test]
+    fn move_cap_runs_out_and_undo_or_retry_resume_play() {
+        let key = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+        let mut app = test_app();
+        app.phase = AppPhase::Puzzle;
+        app.move_cap = Some(2);
+        app.puzzle.target = press_indicator(app.puzzle.initial, 0, app.cycle, app.rule);
+        app.puzzle.target = press_indicator(app.puzzle.target, 0, app.cycle, app.rule);
+        app.puzzle.optimal_moves = 2;
+        assert!(moves_segment(&app).starts_with("2/2 left"));
+
+        handle_key(&mut app, key('2')).unwrap();
+        handle_key(&mut app, key('3')).unwrap();
+        assert!(matches!(app.phase, AppPhase::OutOfMoves));
+        assert!(moves_segment(&app).starts_with("0/2 left"));
+        assert!(app.puzzle.status.starts_with("Out of moves"));
+        // Presses are blocked until a retry or undo.
+        assert!(!handle_key(&mut app, key('1')).unwrap());
+        assert_eq!(app.puzzle.moves_taken, 2);
+
+        handle_key(&mut app, key('u')).unwrap();
+        assert!(matches!(app.phase, AppPhase::Puzzle));
+        assert!(moves_segment(&app).starts_with("1/2 left"));
+        handle_key(&mut app, key('4')).unwrap();
+        assert!(matches!(app.phase, AppPhase::OutOfMoves));
+
+        handle_key(&mut app, key('r')).unwrap();
+        assert!(matches!(app.phase, AppPhase::Puzzle));
+        assert_eq!(app.puzzle.moves_taken, 0);
+        // A solve on the last allowed press still counts.
+        app.reduced_motion = false;
+        handle_key(&mut app, key('1')).unwrap();
+        handle_key(&mut app, key('1')).unwrap();
+        assert!(matches!(app.phase, AppPhase::Solved));
+
+        // The debug solve is held to the same caps.
+        app.debug = true;
+        app.phase = AppPhase::Puzzle;
+        reset_board(&mut app.puzzle);
+        handle_key(&mut app, KeyEvent::new(KeyCode::F(12), KeyModifiers::NONE)).unwrap();
+        assert!(matches!(app.phase, AppPhase::Solved));
+
+        app.phase = AppPhase::Puzzle;
+        reset_board(&mut app.puzzle);
+        handle_key(&mut app, key('2')).unwrap();
+        let left = solution_depth(app.puzzle.current, app.puzzle.target, app.cycle, app.rule);
+        assert!(left.is_some_and(|left| left >= 2));
+        handle_key(&mut app, KeyEvent::new(KeyCode::F(12), KeyModifiers::NONE)).unwrap();
+        assert!(matches!(app.phase, AppPhase::OutOfMoves));
+
+        app.move_cap = None;
+        app.max_moves_over_optimal = Some(0);
+        reset_board(&mut app.puzzle);
+        app.phase = AppPhase::Puzzle;
+        handle_key(&mut app, key('2')).unwrap();
+        handle_key(&mut app, KeyEvent::new(KeyCode::F(12), KeyModifiers::NONE)).unwrap();
+        assert!(matches!(app.phase, AppPhase::Puzzle));
+        assert_eq!(app.puzzle.moves_taken, 0);
     }
 
-    !local.is_empty() && domain.contains('.') && !domain.starts_with('.') && !domain.ends_with('.')
-}
+    #[test]
+    fn submissions_record_the_presses_unless_debug_solved() {
+        let mut app = App::with_config(
+            &Config {
+                debug: true,
+                ..Config::default()
+            },
+            START_STATE,
+            ColorCycle::FULL,
+        );
+        app.phase = AppPhase::Puzzle;
+        app.reduced_motion = false;
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        handle_key(&mut app, key(KeyCode::Char('3'))).unwrap();
+        handle_key(&mut app, key(KeyCode::Char('5'))).unwrap();
+        assert_eq!(SubmissionRecord::now(&app).moves, Some(vec![2, 4]));
+
+        handle_key(&mut app, key(KeyCode::F(12))).unwrap();
+        assert!(matches!(app.phase, AppPhase::Solved));
+        assert_eq!(SubmissionRecord::now(&app).moves, None);
+    }
 
-fn is_email_char(c: char) -> bool {
-    c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | '+' | '@')
-}
+    #[test]
+    fn ctrl_z_requests_a_suspend_instead_of_reaching_the_view() {
+        let mut app = test_app();
+        app.phase = AppPhase::Puzzle;
+        handle_key(
+            &mut app,
+            KeyEvent::new(KeyCode::Char('2'), KeyModifiers::NONE),
+        )
+        .unwrap();
+        let ctrl_z = KeyEvent::new(KeyCode::Char('z'), KeyModifiers::CONTROL);
+        assert!(!handle_key(&mut app, ctrl_z).unwrap());
+        assert_eq!(app.suspend_pending, cfg!(unix));
+        // Plain `Z` would have restarted scoring from here.
+        assert_eq!(app.puzzle.moves_taken, 1);
+        assert!(help_lines(AppPhase::Puzzle)
+            .iter()
+            .any(|(keys, _)| *keys == "Ctrl+Z"));
+    }
 
-fn debug_enabled() -> bool {
-    env::var("BOAAI_DEBUG")
-        .map(|value| {
-            matches!(
-                value.trim().to_ascii_lowercase().as_str(),
-                "1" | "true" | "yes" | "on"
-            )
-        })
-        .unwrap_or(false)
-}
+    #[test]
+    fn target_preview_runs_once_and_settles_on_the_target() {
+        let mut app = test_app();
+        app.reduced_motion = false;
+        app.phase = AppPhase::Menu;
+        let start = Instant::now();
+        assert!(!tick_target_preview(&mut app, start));
+        assert_eq!(app.target_preview, TargetPreview::Waiting);
+
+        app.phase = AppPhase::Puzzle;
+        assert!(tick_target_preview(&mut app, start));
+        let frames: HashSet<_> = (0..12)
+            .map(|frame| shown_target(&app, start + TARGET_PREVIEW_FRAME * frame))
+            .collect();
+        assert!(frames.len() > 1, "the row should cycle");
+        assert_eq!(
+            shown_target(&app, start + TARGET_PREVIEW),
+            app.puzzle.target
+        );
+
+        // Keys still work while it runs.
+        handle_key(
+            &mut app,
+            KeyEvent::new(KeyCode::Char('1'), KeyModifiers::NONE),
+        )
+        .unwrap();
+        assert_eq!(app.puzzle.moves_taken, 1);
+
+        assert!(tick_target_preview(&mut app, start + TARGET_PREVIEW));
+        assert_eq!(app.target_preview, TargetPreview::Done);
+        app.puzzle = next_puzzle(&mut app);
+        assert!(!tick_target_preview(&mut app, start + TARGET_PREVIEW * 2));
+        assert_eq!(shown_target(&app, start), app.puzzle.target);
+
+        let mut calm = test_app();
+        calm.reduced_motion = true;
+        calm.phase = AppPhase::Puzzle;
+        assert!(!tick_target_preview(&mut calm, start));
+        assert_eq!(calm.target_preview, TargetPreview::Done);
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use rand::rngs::StdRng;
-    use rand::SeedableRng;
+    #[test]
+    fn press_effect_tags_match_press_indicator() {
+        for (spec, rule) in [
+            ("OFF,GREEN,BLUE,RED,PURPLE,WHITE", PressRule::Classic),
+            ("OFF,GREEN,BLUE", PressRule::Classic),
+            ("OFF,GREEN,BLUE,RED", PressRule::Lights),
+        ] {
+            let cycle = ColorCycle::parse(spec).unwrap();
+            let state = [NodeColor::Off; INDICATOR_COUNT];
+            for focused in 0..INDICATOR_COUNT {
+                let pressed = press_indicator(state, focused, cycle, rule);
+                for position in 0..INDICATOR_COUNT {
+                    let effect = press_effect(focused, position, cycle, rule);
+                    let steps: i64 = effect.parse().unwrap();
+                    let forward = steps.rem_euclid(cycle.len() as i64) as usize;
+                    assert_eq!(
+                        cycle.steps_between(state[position], pressed[position]),
+                        forward,
+                        "{spec} {rule:?} press {focused} at {position}: {effect}"
+                    );
+                }
+            }
+        }
+        assert_eq!(
+            press_effect(0, 2, ColorCycle::FULL, PressRule::Classic),
+            "-1"
+        );
+        assert_eq!(
+            press_effect(0, 3, ColorCycle::FULL, PressRule::Classic),
+            "+3"
+        );
+
+        let mut app = test_app();
+        app.phase = AppPhase::Puzzle;
+        app.puzzle.focus = PuzzleFocus::Indicator(0);
+        let mut frame = Vec::new();
+        draw_frame(&mut frame, &app, 120, 40).unwrap();
+        assert!(String::from_utf8_lossy(&frame).contains(" +3 "));
+        app.puzzle.focus = PuzzleFocus::Action(0);
+        let mut frame = Vec::new();
+        draw_frame(&mut frame, &app, 120, 40).unwrap();
+        assert!(!String::from_utf8_lossy(&frame).contains(" +3 "));
+    }
 
     #[test]
-    fn default_state_is_all_off() {
-        assert_eq!(START_STATE, [NodeColor::Off; INDICATOR_COUNT]);
+    fn press_preview_shows_the_focused_press_without_applying_it() {
+        let mut app = test_app();
+        app.phase = AppPhase::Puzzle;
+        app.press_preview = true;
+        app.puzzle.focus = PuzzleFocus::Indicator(2);
+        let current = app.puzzle.current;
+        let after = press_indicator(current, 2, app.cycle, app.rule);
+        let preview = press_preview_line(&app).unwrap();
+        assert!(preview.starts_with("Preview, pressing 3: "));
+        assert!(preview.contains(&render_state(after).to_ascii_uppercase()));
+        assert_eq!(app.puzzle.current, current);
+
+        let mut frame = Vec::new();
+        draw_frame(&mut frame, &app, 120, 40).unwrap();
+        assert!(String::from_utf8_lossy(&frame).contains("Preview, pressing 3"));
+
+        // Enter commits exactly the previewed board.
+        handle_key(&mut app, KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)).unwrap();
+        assert_eq!(app.puzzle.current, after);
+
+        app.puzzle.focus = PuzzleFocus::Action(1);
+        assert_eq!(press_preview_line(&app), None);
+        app.puzzle.focus = PuzzleFocus::Indicator(0);
+        app.press_preview = false;
+        assert_eq!(press_preview_line(&app), None);
     }
 
     #[test]
-    fn generated_target_matches_six_simulated_presses() {
-        let mut rng = StdRng::seed_from_u64(42);
-        let (target, sequence) = generate_random_target_from_start(&mut rng);
+    fn share_codes_round_trip_every_board() {
+        let mut codes = HashSet::new();
+        for value in 0..NodeColor::ALL.len().pow(INDICATOR_COUNT as u32) {
+            let state: [NodeColor; INDICATOR_COUNT] = std::array::from_fn(|index| {
+                NodeColor::ALL
+                    [value / NodeColor::ALL.len().pow(index as u32) % NodeColor::ALL.len()]
+            });
+            let code = encode_share_code(state);
+            assert_eq!(code.len(), SHARE_CODE_LEN);
+            assert_eq!(decode_share_code(&code).unwrap(), state, "{code}");
+            assert_eq!(decode_share_code(&code.to_lowercase()).unwrap(), state);
+            assert!(codes.insert(code));
+        }
+        assert_eq!(encode_share_code(START_STATE), "0000");
+        assert_eq!(
+            decode_share_code("YBPC"),
+            parse_state_spec("WHITE,PURPLE,GREEN,WHITE,PURPLE,GREEN")
+        );
+    }
 
-        assert_eq!(sequence.len(), 6);
+    #[test]
+    fn share_codes_reject_typos_and_junk() {
+        let code = encode_share_code([
+            NodeColor::White,
+            NodeColor::Purple,
+            NodeColor::Green,
+            NodeColor::White,
+            NodeColor::Purple,
+            NodeColor::Green,
+        ]);
+        for position in 0..SHARE_CODE_LEN {
+            for &digit in SHARE_CODE_DIGITS {
+                let mut typo = code.clone().into_bytes();
+                if typo[position] == digit {
+                    continue;
+                }
+                typo[position] = digit;
+                let typo = String::from_utf8(typo).unwrap();
+                assert_eq!(
+                    decode_share_code(&typo),
+                    Err(ConfigError::ShareCodeChecksum(typo.clone()))
+                );
+            }
+        }
+        for junk in ["", "00", "00000", "0-00", "ÄB1"] {
+            assert!(matches!(
+                decode_share_code(junk),
+                Err(ConfigError::BadShareCode(_))
+            ));
+        }
 
-        let mut state = START_STATE;
-        for press in sequence {
-            state = press_indicator(state, press);
+        let mut app = test_app();
+        let seed = app.puzzle.seed;
+        let shared = decode_share_code(&code).unwrap();
+        start_from_code(&mut app, shared);
+        assert_eq!((app.puzzle.initial, app.puzzle.current), (shared, shared));
+        assert_eq!(app.puzzle.seed, seed);
+        assert_ne!(app.puzzle.target, shared);
+
+        // A pinned target on the shared board is dropped, and says so.
+        let mut app = test_app();
+        app.fixed_target = Some(shared);
+        start_from_code(&mut app, shared);
+        assert_ne!(app.puzzle.target, shared);
+        assert_eq!(
+            app.puzzle.status,
+            "BOAAI_TARGET ignored (it equals the shared board); using a generated target."
+        );
+
+        let args = |list: &[&str]| list.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        for mode in [
+            &["--difficulty", "hard"][..],
+            &["--survival"],
+            &["--drills"],
+        ] {
+            let mut list = vec!["--code", "YBPC"];
+            list.extend_from_slice(mode);
+            assert!(parse_args(&args(&list)).is_err(), "{mode:?}");
         }
+    }
 
-        assert_eq!(state, target);
+    #[test]
+    fn env_overrides_stay_out_of_saved_preferences() {
+        let mut app = test_app();
+        app.prefs = Preferences::default();
+        // As if BOAAI_REDUCED_MOTION=1 had overridden the saved value.
+        app.reduced_motion = true;
+        assert!(!app.prefs.reduced_motion);
+
+        let key = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+        handle_puzzle_key(&mut app, key('f'));
+        assert!(!app.prefs.finishing_hint);
+        handle_puzzle_key(&mut app, key('m'));
+        assert_eq!(
+            (app.reduced_motion, app.prefs.reduced_motion),
+            (false, false)
+        );
+        handle_puzzle_key(&mut app, key('c'));
+        assert!(app.prefs.color_blind);
     }
 
     #[test]
-    fn shortest_solution_from_default_reaches_generated_target() {
-        let mut rng = StdRng::seed_from_u64(123);
-        let (target, _) = generate_random_target_from_start(&mut rng);
-        let path = shortest_solution(START_STATE, target).expect("path should exist");
-        let mut state = START_STATE;
-        for index in path {
-            state = press_indicator(state, index);
-        }
-        assert_eq!(state, target);
+    fn resumed_saves_keep_their_difficulty() {
+        let path = env::temp_dir().join(format!("boaai-difficulty-save-{}.txt", process::id()));
+        let mut app = test_app();
+        choose_difficulty(&mut app, Difficulty::Hard);
+        let first = (0..INDICATOR_COUNT)
+            .find(|&index| {
+                press_indicator(app.puzzle.current, index, app.cycle, app.rule) != app.puzzle.target
+            })
+            .expect("some press does not solve");
+        apply_press(&mut app.puzzle, first);
+        save_puzzle(&path, &app.puzzle, app.difficulty).unwrap();
+
+        let config = Config {
+            seed: Some(5),
+            save_file: Some(path.clone()),
+            ..Config::default()
+        };
+        let mut resumed = App::with_config(&config, START_STATE, ColorCycle::FULL);
+        fs::remove_file(&path).unwrap();
+        assert!(resumed.resumed);
+        assert_eq!(resumed.difficulty, Some(Difficulty::Hard));
+        assert_eq!(
+            Difficulty::ALL[resumed.difficulty_selected],
+            Difficulty::Hard
+        );
+
+        let expected = difficulty_puzzle_state(
+            resumed.next_seed,
+            START_STATE,
+            ColorCycle::FULL,
+            PressRule::Classic,
+            None,
+            Difficulty::Hard,
+        );
+        let next = next_puzzle(&mut resumed);
+        assert_eq!(
+            (next.initial, next.target, next.seed),
+            (expected.initial, expected.target, expected.seed)
+        );
     }
 
     #[test]
-    fn generated_target_is_not_all_off() {
-        let mut rng = StdRng::seed_from_u64(7);
-        let (target, _) = generate_random_target_from_start(&mut rng);
-        assert_ne!(target, START_STATE);
+    fn bad_targets_are_startup_errors_not_status_notes() {
+        let cycle = ColorCycle::parse("OFF,GREEN,BLUE").expect("valid color set");
+        let config = |target: &str, random_start| Config {
+            target: Some(target.to_string()),
+            random_start,
+            ..Config::default()
+        };
+        let reachable =
+            render_state_spec(press_indicator(START_STATE, 0, cycle, PressRule::Classic));
+        assert_eq!(
+            configured_target(&config(&reachable, true), START_STATE, cycle),
+            Ok(Some(press_indicator(
+                START_STATE,
+                0,
+                cycle,
+                PressRule::Classic
+            )))
+        );
+        assert_eq!(
+            configured_target(&config("RED,OFF,OFF,OFF,OFF,OFF", true), START_STATE, cycle),
+            Err(ConfigError::ColorOutsideCycle(NodeColor::Red))
+        );
+        assert!(matches!(
+            configured_target(
+                &Config {
+                    press_rule: PressRule::Lights,
+                    ..config("GREEN,OFF,OFF,OFF,OFF,OFF", true)
+                },
+                START_STATE,
+                cycle
+            ),
+            Err(ConfigError::UnreachableTarget { .. })
+        ));
+        assert_eq!(
+            configured_target(
+                &config("OFF,OFF,OFF,OFF,OFF,OFF", false),
+                START_STATE,
+                cycle
+            ),
+            Err(ConfigError::TargetIsStart(START_STATE))
+        );
+
+        // The config-file warning still reaches the status line.
+        let config = Config {
+            warning: Some("boaai.toml ignored (bad line); using defaults.".to_string()),
+            ..Config::default()
+        };
+        let app = App::with_config(&config, START_STATE, ColorCycle::FULL);
+        assert_eq!(
+            app.puzzle.status,
+            "boaai.toml ignored (bad line); using defaults."
+        );
+    }
+
+    #[test]
+    fn restarted_scoring_is_practice_and_stays_off_the_leaderboard() {
+        let dir = env::temp_dir().join(format!("boaai-restart-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let invites = dir.join("invites.csv");
+        let mut app = test_app();
+        app.invite_file = Some(invites.clone());
+        app.leaderboard_file = dir.join("leaderboard.csv");
+        app.reduced_motion = true;
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        let path = shortest_solution(
+            app.puzzle.current,
+            app.puzzle.target,
+            app.puzzle.cycle,
+            app.puzzle.rule,
+        )
+        .expect("generated puzzles are solvable");
+        let (last, first) = path.split_last().expect("a generated puzzle needs presses");
+        let mut frames = Vec::new();
+        drive_keys(
+            &mut app,
+            first.iter().map(|&index| digit_key(index)),
+            &mut frames,
+            120,
+            40,
+        )
+        .unwrap();
+        reveal_full_path(&mut app.puzzle);
+        let board = app.puzzle.current;
+        handle_key(&mut app, key(KeyCode::Char('z'))).unwrap();
+        assert_eq!(app.puzzle.current, board);
+        assert_eq!(app.puzzle.initial, board);
+        assert_eq!((app.puzzle.moves_taken, app.puzzle.path_reveals), (0, 0));
+        assert_eq!(app.puzzle.optimal_moves, 1);
+
+        drive_keys(&mut app, [digit_key(*last)], &mut frames, 120, 40).unwrap();
+        assert!(matches!(app.phase, AppPhase::Email));
+        assert_eq!(app.score, None);
+        let typing = "player@example.com".chars().map(|c| key(KeyCode::Char(c)));
+        let confirm = [KeyCode::Tab, KeyCode::Enter, KeyCode::Enter].map(key);
+        drive_keys(&mut app, typing.chain(confirm), &mut frames, 120, 40).unwrap();
+        assert!(matches!(app.phase, AppPhase::Submitted));
+
+        let records = load_submissions(&invites, InviteFormat::Csv).unwrap();
+        assert_eq!((records[0].score, records[0].moves.as_ref()), (None, None));
+        assert!(!app.leaderboard_file.exists());
+        assert!(app.leaderboard_status.starts_with("Not ranked"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn presses_track_distance_and_hint_only_once_the_streak_trips() {
+        let mut app = test_app();
+        app.phase = AppPhase::Puzzle;
+        let (cycle, rule) = (app.cycle, app.rule);
+        let target = press_indicator(START_STATE, 0, cycle, rule);
+        app.puzzle = puzzle_state_for(START_STATE, target, cycle, rule).expect("valid");
+        app.auto_hint_after = Some(2);
+        let depth = |state| solution_depth(state, target, cycle, rule).expect("reachable");
+        // Two presses that each move further from the target.
+        let (first, second) = (0..INDICATOR_COUNT)
+            .flat_map(|a| (0..INDICATOR_COUNT).map(move |b| (a, b)))
+            .find(|&(a, b)| {
+                let once = press_indicator(START_STATE, a, cycle, rule);
+                depth(once) > 1 && depth(press_indicator(once, b, cycle, rule)) > depth(once)
+            })
+            .expect("some presses move away from the target");
+
+        app.puzzle.focus = PuzzleFocus::Indicator(first);
+        activate_puzzle_focus(&mut app);
+        assert_eq!(app.puzzle.distance_to_target, depth(app.puzzle.current));
+        assert_eq!(app.puzzle.worsening_streak, 1);
+        assert!(app.puzzle.status.starts_with("Pressed indicator"));
+
+        app.puzzle.focus = PuzzleFocus::Indicator(second);
+        activate_puzzle_focus(&mut app);
+        assert_eq!(app.puzzle.distance_to_target, depth(app.puzzle.current));
+        let next = shortest_solution(app.puzzle.current, target, cycle, rule).unwrap()[0];
+        assert_eq!(
+            app.puzzle.status,
+            format!("Stuck? Try pressing indicator {}.", next + 1)
+        );
+        assert_eq!(app.puzzle.worsening_streak, 0);
     }
 }
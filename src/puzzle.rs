@@ -0,0 +1,645 @@
+//! Indicator ring rules: colors, presses and the breadth-first solver.
+//! Rendering and input stay in `main`.
+
+use rand::Rng;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+pub(crate) const INDICATOR_COUNT: usize = 6;
+pub(crate) const START_STATE: [NodeColor; INDICATOR_COUNT] = [NodeColor::Off; INDICATOR_COUNT];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum NodeColor {
+    Off,
+    Green,
+    Blue,
+    Red,
+    Purple,
+    White,
+}
+
+impl NodeColor {
+    pub(crate) const ALL: [NodeColor; 6] = [
+        Self::Off,
+        Self::Green,
+        Self::Blue,
+        Self::Red,
+        Self::Purple,
+        Self::White,
+    ];
+
+    pub(crate) fn next(self) -> Self {
+        match self {
+            Self::Off => Self::Green,
+            Self::Green => Self::Blue,
+            Self::Blue => Self::Red,
+            Self::Red => Self::Purple,
+            Self::Purple => Self::White,
+            Self::White => Self::Off,
+        }
+    }
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Off => "OFF",
+            Self::Green => "GREEN",
+            Self::Blue => "BLUE",
+            Self::Red => "RED",
+            Self::Purple => "PURPLE",
+            Self::White => "WHITE",
+        }
+    }
+
+    pub(crate) fn short(self) -> &'static str {
+        match self {
+            Self::Off => "OFF",
+            Self::Green => "GRN",
+            Self::Blue => "BLU",
+            Self::Red => "RED",
+            Self::Purple => "PUR",
+            Self::White => "WHT",
+        }
+    }
+}
+
+/// The subset of colors indicators cycle through, in canonical order.
+/// `OFF` is always part of the cycle so the all-OFF start stays valid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct ColorCycle {
+    active: [bool; 6],
+}
+
+impl ColorCycle {
+    pub(crate) const FULL: ColorCycle = ColorCycle { active: [true; 6] };
+
+    /// The cycle through `colors`, in canonical order whatever order they
+    /// come in. Callers check that `OFF` and one other color are present.
+    pub(crate) fn from_colors(colors: impl IntoIterator<Item = NodeColor>) -> Self {
+        let mut active = [false; 6];
+        for color in colors {
+            active[color as usize] = true;
+        }
+        Self { active }
+    }
+
+    pub(crate) fn len(self) -> usize {
+        self.active.iter().filter(|active| **active).count()
+    }
+
+    pub(crate) fn contains(self, color: NodeColor) -> bool {
+        self.active[color as usize]
+    }
+
+    pub(crate) fn colors(self) -> impl Iterator<Item = NodeColor> {
+        NodeColor::ALL
+            .into_iter()
+            .filter(move |color| self.contains(*color))
+    }
+
+    pub(crate) fn next(self, color: NodeColor) -> NodeColor {
+        let mut next = color.next();
+        while !self.contains(next) {
+            next = next.next();
+        }
+        next
+    }
+
+    /// Number of `next` steps needed to turn `from` into `to`.
+    pub(crate) fn steps_between(self, from: NodeColor, to: NodeColor) -> usize {
+        let mut color = from;
+        let mut steps = 0;
+        while color != to && steps < self.len() {
+            color = self.next(color);
+            steps += 1;
+        }
+        steps
+    }
+}
+
+/// Which indicators a press advances, chosen by `BOAAI_PRESS_RULE` or the
+/// `press_rule` config key. See `press_delta` for the steps of each.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum PressRule {
+    Classic,
+    Lights,
+}
+
+impl PressRule {
+    pub(crate) const ALL: [PressRule; 2] = [Self::Classic, Self::Lights];
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Classic => "classic",
+            Self::Lights => "lights",
+        }
+    }
+
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|rule| rule.name().eq_ignore_ascii_case(value.trim()))
+    }
+}
+
+/// Color steps a press applies to an indicator `distance` positions around a
+/// ring of `count` from the pressed one (the furthest is `count / 2`). Under
+/// `PressRule::Lights` the pressed button and its immediate neighbors step
+/// once and the rest stay put; under `PressRule::Classic`:
+///
+/// | distance                  | steps                          |
+/// |---------------------------|--------------------------------|
+/// | 0                         | 2 (pressed button)             |
+/// | 1                         | 1 (immediate neighbors)        |
+/// | 2                         | one step backward in the cycle |
+/// | `count / 2`, `count` even | 3 (opposite button)            |
+/// | anything else             | 0                              |
+///
+/// On a four-button ring the opposite button is also two apart; the two-apart
+/// rule takes precedence there.
+pub(crate) fn press_delta(
+    distance: usize,
+    count: usize,
+    cycle: ColorCycle,
+    rule: PressRule,
+) -> usize {
+    match (rule, distance) {
+        (PressRule::Lights, 0 | 1) => 1,
+        (PressRule::Lights, _) => 0,
+        (PressRule::Classic, 0) => 2,
+        (PressRule::Classic, 1) => 1,
+        (PressRule::Classic, 2) => cycle.len() - 1,
+        (PressRule::Classic, opposite) if count.is_multiple_of(2) && opposite == count / 2 => 3,
+        (PressRule::Classic, _) => 0,
+    }
+}
+
+/// Presses `index` on a ring of `N` indicators. The game uses
+/// `INDICATOR_COUNT`; other sizes share the same rules via `press_delta`.
+pub(crate) fn press_indicator<const N: usize>(
+    mut state: [NodeColor; N],
+    index: usize,
+    cycle: ColorCycle,
+    rule: PressRule,
+) -> [NodeColor; N] {
+    for (target, color) in state.iter_mut().enumerate() {
+        let clockwise = (target + N - index) % N;
+        let counterclockwise = (index + N - target) % N;
+        let delta = press_delta(clockwise.min(counterclockwise), N, cycle, rule);
+
+        for _ in 0..delta {
+            *color = cycle.next(*color);
+        }
+    }
+
+    state
+}
+
+pub(crate) fn generate_random_target_from_start<R: Rng + ?Sized>(
+    rng: &mut R,
+    start: [NodeColor; INDICATOR_COUNT],
+    cycle: ColorCycle,
+    rule: PressRule,
+) -> ([NodeColor; INDICATOR_COUNT], Vec<usize>) {
+    generate_target_with_presses(rng, start, cycle, rule, 6)
+}
+
+/// Scrambles `start` with `presses` random presses, retrying until the
+/// result differs from `start`.
+pub(crate) fn generate_target_with_presses<R: Rng + ?Sized>(
+    rng: &mut R,
+    start: [NodeColor; INDICATOR_COUNT],
+    cycle: ColorCycle,
+    rule: PressRule,
+    presses: usize,
+) -> ([NodeColor; INDICATOR_COUNT], Vec<usize>) {
+    let presses = presses.max(1);
+    loop {
+        let sequence: Vec<usize> = (0..presses)
+            .map(|_| rng.gen_range(0..INDICATOR_COUNT))
+            .collect();
+        let mut state = start;
+        for &press in &sequence {
+            state = press_indicator(state, press, cycle, rule);
+        }
+
+        if state != start {
+            return (state, sequence);
+        }
+    }
+}
+
+pub(crate) fn shortest_solution<const N: usize>(
+    start: [NodeColor; N],
+    goal: [NodeColor; N],
+    cycle: ColorCycle,
+    rule: PressRule,
+) -> Option<Vec<usize>> {
+    if start == goal {
+        return Some(Vec::new());
+    }
+
+    let mut queue = VecDeque::new();
+    let mut visited = HashSet::new();
+    let mut parent_map: HashMap<[NodeColor; N], ([NodeColor; N], usize)> = HashMap::new();
+
+    queue.push_back(start);
+    visited.insert(start);
+
+    while let Some(state) = queue.pop_front() {
+        for index in 0..N {
+            let next_state = press_indicator(state, index, cycle, rule);
+            if visited.insert(next_state) {
+                parent_map.insert(next_state, (state, index));
+                if next_state == goal {
+                    return Some(reconstruct_moves(start, goal, &parent_map));
+                }
+                queue.push_back(next_state);
+            }
+        }
+    }
+
+    None
+}
+
+/// Minimum number of presses from `start` to `goal`, without recording the
+/// path. Use `shortest_solution` when the presses themselves are needed.
+pub(crate) fn solution_depth<const N: usize>(
+    start: [NodeColor; N],
+    goal: [NodeColor; N],
+    cycle: ColorCycle,
+    rule: PressRule,
+) -> Option<usize> {
+    if start == goal {
+        return Some(0);
+    }
+
+    let mut queue = VecDeque::from([(start, 0)]);
+    let mut visited = HashSet::from([start]);
+    while let Some((state, depth)) = queue.pop_front() {
+        for index in 0..N {
+            let next_state = press_indicator(state, index, cycle, rule);
+            if next_state == goal {
+                return Some(depth + 1);
+            }
+            if visited.insert(next_state) {
+                queue.push_back((next_state, depth + 1));
+            }
+        }
+    }
+
+    None
+}
+
+pub(crate) fn reconstruct_moves<const N: usize>(
+    start: [NodeColor; N],
+    goal: [NodeColor; N],
+    parent_map: &HashMap<[NodeColor; N], ([NodeColor; N], usize)>,
+) -> Vec<usize> {
+    let mut cursor = goal;
+    let mut path = Vec::new();
+
+    while cursor != start {
+        if let Some((previous, pressed)) = parent_map.get(&cursor) {
+            path.push(*pressed);
+            cursor = *previous;
+        } else {
+            return Vec::new();
+        }
+    }
+
+    path.reverse();
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn default_state_is_all_off() {
+        assert_eq!(START_STATE, [NodeColor::Off; INDICATOR_COUNT]);
+    }
+
+    #[test]
+    fn generated_target_matches_six_simulated_presses() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let (target, sequence) = generate_random_target_from_start(
+            &mut rng,
+            START_STATE,
+            ColorCycle::FULL,
+            PressRule::Classic,
+        );
+
+        assert_eq!(sequence.len(), 6);
+
+        let mut state = START_STATE;
+        for press in sequence {
+            state = press_indicator(state, press, ColorCycle::FULL, PressRule::Classic);
+        }
+
+        assert_eq!(state, target);
+    }
+
+    #[test]
+    fn shortest_solution_from_default_reaches_generated_target() {
+        let mut rng = StdRng::seed_from_u64(123);
+        let (target, _) = generate_random_target_from_start(
+            &mut rng,
+            START_STATE,
+            ColorCycle::FULL,
+            PressRule::Classic,
+        );
+        let path = shortest_solution(START_STATE, target, ColorCycle::FULL, PressRule::Classic)
+            .expect("path should exist");
+        let mut state = START_STATE;
+        for index in path {
+            state = press_indicator(state, index, ColorCycle::FULL, PressRule::Classic);
+        }
+        assert_eq!(state, target);
+    }
+
+    #[test]
+    fn generated_target_is_not_all_off() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let (target, _) = generate_random_target_from_start(
+            &mut rng,
+            START_STATE,
+            ColorCycle::FULL,
+            PressRule::Classic,
+        );
+        assert_ne!(target, START_STATE);
+    }
+
+    fn all_states(cycle: ColorCycle) -> Vec<[NodeColor; INDICATOR_COUNT]> {
+        let colors: Vec<NodeColor> = cycle.colors().collect();
+        let total = colors.len().pow(INDICATOR_COUNT as u32);
+        (0..total)
+            .map(|mut code| {
+                let mut state = START_STATE;
+                for slot in state.iter_mut() {
+                    *slot = colors[code % colors.len()];
+                    code /= colors.len();
+                }
+                state
+            })
+            .collect()
+    }
+
+    #[test]
+    fn verify_press_is_permutation() {
+        let subset = ColorCycle::from_colors([NodeColor::Off, NodeColor::Green, NodeColor::Red]);
+        for cycle in [ColorCycle::FULL, subset] {
+            let states = all_states(cycle);
+            for index in 0..INDICATOR_COUNT {
+                let successors: HashSet<_> = states
+                    .iter()
+                    .map(|state| press_indicator(*state, index, cycle, PressRule::Classic))
+                    .collect();
+                assert_eq!(successors.len(), states.len(), "press {index} collides");
+
+                for state in states.iter().step_by(97) {
+                    let mut pressed = *state;
+                    for _ in 0..cycle.len() {
+                        pressed = press_indicator(pressed, index, cycle, PressRule::Classic);
+                    }
+                    assert_eq!(pressed, *state, "press {index} does not cycle back");
+                }
+            }
+
+            let mut reachable = HashSet::from([START_STATE]);
+            let mut queue = VecDeque::from([START_STATE]);
+            while let Some(state) = queue.pop_front() {
+                for index in 0..INDICATOR_COUNT {
+                    let next = press_indicator(state, index, cycle, PressRule::Classic);
+                    assert!(next.iter().all(|color| cycle.contains(*color)));
+                    if reachable.insert(next) {
+                        queue.push_back(next);
+                    }
+                }
+            }
+            assert!(reachable.len() <= states.len());
+        }
+    }
+
+    #[test]
+    fn solution_depth_matches_shortest_solution_length() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let subset = ColorCycle::from_colors([NodeColor::Off, NodeColor::Blue, NodeColor::Purple]);
+        for cycle in [ColorCycle::FULL, subset] {
+            for _ in 0..40 {
+                let start = press_indicator(
+                    START_STATE,
+                    rng.gen_range(0..INDICATOR_COUNT),
+                    cycle,
+                    PressRule::Classic,
+                );
+                let presses = rng.gen_range(0..10);
+                let (goal, _) = generate_target_with_presses(
+                    &mut rng,
+                    start,
+                    cycle,
+                    PressRule::Classic,
+                    presses,
+                );
+                assert_eq!(
+                    solution_depth(start, goal, cycle, PressRule::Classic),
+                    shortest_solution(start, goal, cycle, PressRule::Classic)
+                        .map(|path| path.len())
+                );
+            }
+            assert_eq!(
+                solution_depth(START_STATE, START_STATE, cycle, PressRule::Classic),
+                Some(0)
+            );
+        }
+    }
+
+    #[test]
+    fn other_ring_sizes_stay_solvable() {
+        fn check<const N: usize>(cycle: ColorCycle) {
+            let mut rng = StdRng::seed_from_u64(N as u64);
+            for _ in 0..20 {
+                let start = [NodeColor::Off; N];
+                let presses: Vec<usize> = (0..4).map(|_| rng.gen_range(0..N)).collect();
+                let target = presses.iter().fold(start, |state, &press| {
+                    press_indicator(state, press, cycle, PressRule::Classic)
+                });
+                let path = shortest_solution(start, target, cycle, PressRule::Classic)
+                    .expect("target is reachable");
+                assert!(path.len() <= presses.len());
+                let reached = path.iter().fold(start, |state, &press| {
+                    press_indicator(state, press, cycle, PressRule::Classic)
+                });
+                assert_eq!(reached, target);
+                assert_eq!(
+                    solution_depth(start, target, cycle, PressRule::Classic),
+                    Some(path.len())
+                );
+            }
+        }
+        check::<4>(ColorCycle::FULL);
+        check::<6>(ColorCycle::FULL);
+        check::<8>(ColorCycle::FULL);
+        let subset = ColorCycle::from_colors([NodeColor::Off, NodeColor::Green, NodeColor::Blue]);
+        check::<8>(subset);
+
+        // An odd ring has no opposite button; an even one steps it three times.
+        assert_eq!(
+            press_delta(2, 5, ColorCycle::FULL, PressRule::Classic),
+            ColorCycle::FULL.len() - 1
+        );
+        assert_eq!(press_delta(3, 7, ColorCycle::FULL, PressRule::Classic), 0);
+        assert_eq!(press_delta(4, 8, ColorCycle::FULL, PressRule::Classic), 3);
+        assert_eq!(press_delta(3, 8, ColorCycle::FULL, PressRule::Classic), 0);
+    }
+
+    #[test]
+    fn presses_are_deterministic_and_solver_paths_reach_the_goal() {
+        let mut rng = StdRng::seed_from_u64(778);
+        let subset = ColorCycle::from_colors([NodeColor::Off, NodeColor::Red, NodeColor::White]);
+        for cycle in [ColorCycle::FULL, subset] {
+            let colors: Vec<NodeColor> = cycle.colors().collect();
+            for _ in 0..50 {
+                let start: [NodeColor; INDICATOR_COUNT] =
+                    std::array::from_fn(|_| colors[rng.gen_range(0..colors.len())]);
+                let index = rng.gen_range(0..INDICATOR_COUNT);
+                assert_eq!(
+                    press_indicator(start, index, cycle, PressRule::Classic),
+                    press_indicator(start, index, cycle, PressRule::Classic)
+                );
+
+                let presses = rng.gen_range(0..8);
+                let (goal, _) = generate_target_with_presses(
+                    &mut rng,
+                    start,
+                    cycle,
+                    PressRule::Classic,
+                    presses,
+                );
+                let path = shortest_solution(start, goal, cycle, PressRule::Classic)
+                    .expect("goal is reachable");
+                let reached = path.iter().fold(start, |state, &press| {
+                    press_indicator(state, press, cycle, PressRule::Classic)
+                });
+                assert_eq!(reached, goal);
+            }
+        }
+    }
+
+    #[test]
+    fn every_state_reachable_from_the_target_solves_back_to_it() {
+        fn explore(
+            from: [NodeColor; INDICATOR_COUNT],
+            step: impl Fn([NodeColor; INDICATOR_COUNT], usize) -> [NodeColor; INDICATOR_COUNT],
+        ) -> Vec<([NodeColor; INDICATOR_COUNT], usize)> {
+            let mut seen = HashSet::from([from]);
+            let mut order = vec![(from, 0)];
+            let mut cursor = 0;
+            while let Some(&(state, depth)) = order.get(cursor) {
+                for index in 0..INDICATOR_COUNT {
+                    let next = step(state, index);
+                    if seen.insert(next) {
+                        order.push((next, depth + 1));
+                    }
+                }
+                cursor += 1;
+            }
+            order
+        }
+
+        // The target from the README, on the game's full six-color cycle.
+        let target = [
+            NodeColor::White,
+            NodeColor::Purple,
+            NodeColor::Green,
+            NodeColor::White,
+            NodeColor::Purple,
+            NodeColor::Green,
+        ];
+        let three = ColorCycle::from_colors([NodeColor::Off, NodeColor::Green, NodeColor::Blue]);
+        let three_target = press_indicator(
+            press_indicator(START_STATE, 0, three, PressRule::Classic),
+            3,
+            three,
+            PressRule::Classic,
+        );
+        for (cycle, target) in [(ColorCycle::FULL, target), (three, three_target)] {
+            let reachable: HashSet<_> = explore(target, |state, index| {
+                press_indicator(state, index, cycle, PressRule::Classic)
+            })
+            .into_iter()
+            .map(|(state, _)| state)
+            .collect();
+
+            // Undoing a press is pressing it `len - 1` more times, so walking
+            // undone presses out from the target gives every state's distance
+            // back to it.
+            let back = explore(target, |state, index| {
+                (1..cycle.len()).fold(state, |state, _| {
+                    press_indicator(state, index, cycle, PressRule::Classic)
+                })
+            });
+            assert_eq!(reachable.len(), cycle.len().pow(INDICATOR_COUNT as u32));
+            assert_eq!(back.len(), reachable.len());
+            assert!(back.iter().all(|(state, _)| reachable.contains(state)));
+
+            // Each solver call is its own BFS, so the 46656 six-color states
+            // would take hours in a debug build. There the solver runs on
+            // everything within three presses and the first state of every
+            // distance layer out to the farthest; the three-color space is
+            // small enough to solve from every state.
+            let checked = back.iter().enumerate().filter(|&(position, &(_, depth))| {
+                cycle.len() < 6 || depth <= 3 || position == 0 || back[position - 1].1 != depth
+            });
+            for (_, &(state, depth)) in checked {
+                let path = shortest_solution(state, target, cycle, PressRule::Classic)
+                    .expect("state is solvable");
+                let reached = path.iter().fold(state, |state, &press| {
+                    press_indicator(state, press, cycle, PressRule::Classic)
+                });
+                assert_eq!(reached, target);
+                assert_eq!(path.len(), depth);
+                assert_eq!(
+                    solution_depth(state, target, cycle, PressRule::Classic),
+                    Some(depth)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn each_press_rule_solves_every_state_it_reaches() {
+        use NodeColor::{Green, Off};
+        assert_eq!(
+            press_indicator(START_STATE, 0, ColorCycle::FULL, PressRule::Lights),
+            [Green, Green, Off, Off, Off, Green]
+        );
+
+        let cycle = ColorCycle::from_colors([Off, Green, NodeColor::Blue]);
+        // Classic reaches all 3^6 boards; Lights only a ninth of them, which is
+        // why its targets are generated by pressing rather than picked.
+        for (rule, expected) in [(PressRule::Classic, 729), (PressRule::Lights, 81)] {
+            let mut reachable = HashSet::from([START_STATE]);
+            let mut queue = VecDeque::from([START_STATE]);
+            while let Some(state) = queue.pop_front() {
+                for index in 0..INDICATOR_COUNT {
+                    let next = press_indicator(state, index, cycle, rule);
+                    if reachable.insert(next) {
+                        queue.push_back(next);
+                    }
+                }
+            }
+            assert_eq!(reachable.len(), expected, "{rule:?}");
+
+            for &state in reachable.iter() {
+                let path = shortest_solution(START_STATE, state, cycle, rule)
+                    .expect("reachable states are solvable");
+                let reached = path.iter().fold(START_STATE, |state, &press| {
+                    press_indicator(state, press, cycle, rule)
+                });
+                assert_eq!(reached, state);
+            }
+        }
+    }
+}